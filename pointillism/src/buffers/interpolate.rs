@@ -10,18 +10,18 @@ pub fn linear<S: smp::SampleBase>(x0: S, x1: S, t: unt::Val) -> S {
     x0 * (1.0 - t) + x1 * t
 }
 
-/// Interpolates cubically between `x1` and `x2`. Makes use of the previous sample `x0` and the next
-/// sample `x3`.
+/// Interpolates cubically between `x1` and `x2`, through the Lagrange polynomial which also goes
+/// through the previous sample `x0` and the next sample `x3`.
 ///
-/// Adapted from <https://stackoverflow.com/a/1126113/12419072>.
+/// This is exact for any polynomial of degree at most three.
 pub fn cubic<S: smp::SampleBase>(x0: S, x1: S, x2: S, x3: S, t: unt::Val) -> S {
     let t = t.inner();
-    let a0 = x3 - x2 - x0 + x1;
-    let a1 = x0 - x1 - a0;
-    let a2 = x2 - x0;
-    let a3 = x1;
+    let (prev, next, next2) = (t + 1.0, t - 1.0, t - 2.0);
 
-    ((a0 * t + a1) * t + a2) * t + a3
+    x0 * (-t * next * next2 / 6.0)
+        + x1 * (prev * next * next2 / 2.0)
+        + x2 * (-prev * t * next2 / 2.0)
+        + x3 * (prev * t * next / 6.0)
 }
 
 /// Applies Hermite interpolation between `x1` and `x2`. Makes use of the previous sample `x0` and
@@ -200,6 +200,7 @@ impl<A: Audio> Interpolate for Linear<A> {
 /// Cubic interpolation uses the cubic [Lagrange
 /// polynomial](https://en.wikipedia.org/wiki/Lagrange_polynomial) for the previous, current, next,
 /// and next next samples. This will often yield good results, along with [`Hermite`] interpolation.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Cubic<A: Audio>(pub buf::ring::Shift<buf::Stc<A, 4>>);
 
 impl<A: Audio> Cubic<A> {
@@ -246,6 +247,7 @@ impl<A: Audio> Interpolate for Cubic<A> {
 /// spline](https://en.wikipedia.org/wiki/Catmull–Rom_spline) (a special case of the cubic Hermite
 /// spline) for interpolation. This will often yield good results, along with [`Cubic`]
 /// interpolation.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Hermite<A: Audio>(pub buf::ring::Shift<buf::Stc<A, 4>>);
 
 impl<A: Audio> Hermite<A> {
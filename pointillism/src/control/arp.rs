@@ -0,0 +1,895 @@
+//! Implements arpeggios and arpeggiators.
+//!
+//! The simplest way to arpeggiate a signal is through an [`Arpeggio`], which changes the frequency
+//! of a signal at regular intervals, according to an [`Arp`].
+//!
+//! For more control, an [`Arpeggiator`] plays notes on a synth, starting and stopping each of them.
+//! Its notes come from a set of held notes, which can be changed while it plays, for instance
+//! through an [`ArpSeq`].
+
+use crate::prelude::*;
+use std::hash::Hash;
+
+/// The order in which an [`Arp`] plays its notes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ArpMode {
+    /// From lowest to highest.
+    Up,
+    /// From highest to lowest.
+    Down,
+    /// From lowest to highest and back, without repeating the endpoints.
+    UpDown,
+    /// From highest to lowest and back, without repeating the endpoints.
+    DownUp,
+    /// Picks a note at random each time.
+    Random,
+    /// In the order in which the notes were given.
+    #[default]
+    AsPlayed,
+}
+
+/// The function that arpeggiates a signal.
+///
+/// The notes are played in the order specified by the [`ArpMode`], over the specified number of
+/// octaves. For instance, an upwards arpeggio over two octaves first plays all notes in ascending
+/// order, and then all of them again an octave higher.
+///
+/// This is used to implement [`Arpeggio`] and [`Arpeggiator`].
+#[derive(Clone, Debug)]
+pub struct Arp {
+    /// The notes to play, in the order they were given.
+    pub notes: Vec<unt::Freq>,
+
+    /// The index of the note currently playing, within the pattern.
+    pub index: usize,
+
+    /// The order in which notes are played.
+    pub mode: ArpMode,
+
+    /// The number of octaves the pattern spans. This should be at least `1`.
+    pub octaves: u8,
+}
+
+impl Arp {
+    /// Initializes a new arpeggio with the given notes, played in order.
+    #[must_use]
+    pub const fn new(notes: Vec<unt::Freq>) -> Self {
+        Self::new_mode(notes, ArpMode::AsPlayed, 1)
+    }
+
+    /// Initializes a new arpeggio with the given notes, mode, and octave range.
+    #[must_use]
+    pub const fn new_mode(notes: Vec<unt::Freq>, mode: ArpMode, octaves: u8) -> Self {
+        Self {
+            notes,
+            index: 0,
+            mode,
+            octaves,
+        }
+    }
+
+    /// The currently played note.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the arpeggio is empty.
+    #[must_use]
+    pub fn current(&self) -> unt::Freq {
+        self.note_at(self.index).expect("arpeggio can't be empty")
+    }
+
+    /// The length of the arpeggio.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Whether the arpeggio has no notes.
+    ///
+    /// Note that this will generally result in other methods panicking, and thus should be avoided.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// The number of notes in the arpeggio over all of its octaves.
+    fn range_len(&self) -> usize {
+        self.len() * usize::from(self.octaves.max(1))
+    }
+
+    /// The number of steps until the pattern repeats.
+    #[must_use]
+    pub fn pattern_len(&self) -> usize {
+        let len = self.range_len();
+        match self.mode {
+            ArpMode::UpDown | ArpMode::DownUp if len > 1 => 2 * len - 2,
+            _ => len,
+        }
+    }
+
+    /// Returns the index of the `n`-th lowest note, breaking ties by order.
+    ///
+    /// This is quadratic in the number of notes, but avoids allocating.
+    fn sorted(&self, n: usize) -> usize {
+        let rank = |i: usize| {
+            let note = self.notes[i].samples;
+            self.notes
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| other.samples.total_cmp(&note).then(j.cmp(&i)).is_lt())
+                .count()
+        };
+
+        (0..self.len()).find(|&i| rank(i) == n).expect(crate::OOB)
+    }
+
+    /// The note at a given step of the pattern, or `None` if the arpeggio is empty.
+    ///
+    /// Steps outside of the pattern wrap around.
+    #[must_use]
+    pub fn note_at(&self, step: usize) -> Option<unt::Freq> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let len = self.range_len();
+        let step = step % self.pattern_len();
+        let rank = match self.mode {
+            ArpMode::Up | ArpMode::Random | ArpMode::AsPlayed => step,
+            ArpMode::Down => len - 1 - step,
+            ArpMode::UpDown => {
+                if step < len {
+                    step
+                } else {
+                    2 * len - 2 - step
+                }
+            }
+            ArpMode::DownUp => {
+                if step < len {
+                    len - 1 - step
+                } else {
+                    step + 1 - len
+                }
+            }
+        };
+
+        let (octave, index) = (rank / self.len(), rank % self.len());
+        let note = if self.mode == ArpMode::AsPlayed {
+            self.notes[index]
+        } else {
+            self.notes[self.sorted(index)]
+        };
+
+        // The octave range is at most 255.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        Some(note * unt::Interval::OCTAVE.powi(octave as i32))
+    }
+
+    /// Moves on to the next step of the pattern.
+    pub fn step(&mut self) {
+        let len = self.pattern_len();
+        if len == 0 {
+            return;
+        }
+
+        if self.mode == ArpMode::Random {
+            use rand::Rng;
+            self.index = rand::thread_rng().gen_range(0..len);
+        } else {
+            self.index = (self.index + 1) % len;
+        }
+    }
+}
+
+impl<S: Frequency> Mut<S> for Arp {
+    fn modify(&mut self, sgn: &mut S) {
+        *sgn.freq_mut() = self.current();
+        self.step();
+    }
+}
+
+/// An arpeggiated signal.
+///
+/// ## Example
+///
+/// We create a single arpeggio which plays two chords.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// // Basic parameters.
+/// const SAMPLE_RATE: unt::SampleRate = unt::SampleRate::CD;
+/// const NOTE_TIME: unt::RawTime = unt::RawTime::new(3.0 / 32.0);
+/// const LENGTH: unt::RawTime = unt::RawTime::new(3.0);
+///
+/// let note_time = unt::Time::from_raw(NOTE_TIME, SAMPLE_RATE);
+/// let length = unt::Time::from_raw(LENGTH, SAMPLE_RATE);
+///
+/// // The notes played in the arpeggio.
+/// let notes = [unt::RawFreq::C4, unt::RawFreq::E4, unt::RawFreq::G4, unt::RawFreq::A4]
+///     .map(|raw| unt::Freq::from_raw(raw, SAMPLE_RATE))
+///     .to_vec();
+///
+/// // Initializes the arpeggio.
+/// let mut arp = ctr::Arpeggio::new_arp(
+///     vec![note_time],
+///     gen::Loop::<smp::Mono, _>::new(crv::Tri, unt::Freq::ZERO),
+///     notes,
+/// );
+///
+/// // Zero is a dummy value that gets replaced here.
+/// arp.skip();
+///
+/// let mut timer = ctr::Timer::new(length);
+/// Song::new_func(2u8 * length, SAMPLE_RATE, |time| {
+///     // We switch up the arpeggio after the first phrase.
+///     if timer.tick(time) {
+///         arp.notes_mut()[2] = unt::Freq::from_raw(unt::RawFreq::F4, SAMPLE_RATE);
+///     }
+///
+///     arp.next()
+/// })
+/// .export("examples/arpeggio.wav");
+/// ```
+pub type Arpeggio<S> = ctr::Loop<S, Arp>;
+
+impl<S: Frequency> Arpeggio<S> {
+    /// Initializes a new [`Arpeggio`].
+    ///
+    /// Note that the note being played by the signal won't be updated until the first time interval
+    /// transpires, unless you call [`Self::skip`].
+    ///
+    /// ## Panics
+    ///
+    /// This method panics if the `times` vector is empty.
+    pub fn new_arp(times: Vec<unt::Time>, sgn: S, notes: Vec<unt::Freq>) -> Self {
+        Self::new(times, sgn, Arp::new(notes))
+    }
+
+    /// Returns a reference to the [`Arp`].
+    pub const fn arp(&self) -> &Arp {
+        self.func()
+    }
+
+    /// Returns a mutable reference to the [`Arp`].
+    pub fn arp_mut(&mut self) -> &mut Arp {
+        self.func_mut()
+    }
+
+    /// Returns a reference to the notes.
+    pub fn notes(&self) -> &[unt::Freq] {
+        &self.arp().notes
+    }
+
+    /// Returns a mutable reference to the notes.
+    pub fn notes_mut(&mut self) -> &mut [unt::Freq] {
+        &mut self.arp_mut().notes
+    }
+}
+
+/// A synth that can be played by an [`Arpeggiator`].
+///
+/// The arpeggiator only ever plays a single note at a time, which it starts with
+/// [`Self::note_on`] and stops with [`Self::note_off`]. However, notes can keep ringing after
+/// they've been stopped.
+pub trait ArpSynth: SignalMut {
+    /// Starts playing a note with the given frequency.
+    fn note_on(&mut self, freq: unt::Freq);
+
+    /// Stops the last note that was started.
+    fn note_off(&mut self);
+}
+
+/// A monophonic synth for an [`Arpeggiator`].
+///
+/// Each new note changes the frequency of the signal and retriggers it, and each note off
+/// [stops](Stop) it.
+#[derive(Clone, Debug, Default)]
+pub struct ArpMono<S: Frequency + Stop> {
+    /// The signal being played.
+    pub sgn: S,
+}
+
+impl<S: Frequency + Stop> ArpMono<S> {
+    /// Initializes a new [`ArpMono`].
+    pub const fn new(sgn: S) -> Self {
+        Self { sgn }
+    }
+}
+
+impl<S: Frequency + Stop> Signal for ArpMono<S> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.sgn.get()
+    }
+}
+
+impl<S: Frequency + Stop> SignalMut for ArpMono<S> {
+    fn advance(&mut self) {
+        self.sgn.advance();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+    }
+}
+
+impl<S: Frequency + Stop + Panic> Panic for ArpMono<S> {
+    fn panic(&mut self) {
+        self.sgn.panic();
+    }
+}
+
+impl<S: Frequency + Stop> ArpSynth for ArpMono<S> {
+    fn note_on(&mut self, freq: unt::Freq) {
+        *self.sgn.freq_mut() = freq;
+        self.sgn.retrigger();
+    }
+
+    fn note_off(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+/// A polyphonic synth for an [`Arpeggiator`].
+///
+/// Each new note builds a new signal from its frequency, so that stopped notes can keep ringing
+/// while the next ones play.
+#[derive(Clone, Debug)]
+pub struct ArpPoly<F: Map<Input = unt::Freq>>
+where
+    F::Output: Stop + Done,
+{
+    /// The notes currently playing.
+    pub poly: poly::Polyphony<usize, F::Output>,
+    /// The function that builds a new signal from a frequency.
+    pub func: F,
+    /// The key for the last note that was started.
+    key: usize,
+}
+
+impl<F: Map<Input = unt::Freq>> ArpPoly<F>
+where
+    F::Output: Stop + Done,
+{
+    /// Initializes a new [`ArpPoly`], playing nothing.
+    pub fn new(func: F) -> Self {
+        Self {
+            poly: poly::Polyphony::new(),
+            func,
+            key: 0,
+        }
+    }
+}
+
+impl<F: Map<Input = unt::Freq>> Signal for ArpPoly<F>
+where
+    F::Output: Stop + Done,
+{
+    type Sample = <F::Output as Signal>::Sample;
+
+    fn get(&self) -> Self::Sample {
+        Signal::get(&self.poly)
+    }
+}
+
+impl<F: Map<Input = unt::Freq>> SignalMut for ArpPoly<F>
+where
+    F::Output: Stop + Done,
+{
+    fn advance(&mut self) {
+        self.poly.advance();
+    }
+
+    fn retrigger(&mut self) {
+        self.poly.retrigger();
+    }
+}
+
+impl<F: Map<Input = unt::Freq>> Panic for ArpPoly<F>
+where
+    F::Output: Stop + Done,
+{
+    fn panic(&mut self) {
+        self.poly.panic();
+    }
+}
+
+impl<F: Map<Input = unt::Freq>> ArpSynth for ArpPoly<F>
+where
+    F::Output: Stop + Done,
+{
+    fn note_on(&mut self, freq: unt::Freq) {
+        self.key = self.key.wrapping_add(1);
+        self.poly.add(self.key, self.func.eval(freq));
+    }
+
+    fn note_off(&mut self) {
+        self.poly.stop(&self.key);
+    }
+}
+
+/// A note held down on an [`Arpeggiator`].
+#[derive(Clone, Copy, Debug)]
+struct Held<K> {
+    /// The key identifying the note.
+    key: K,
+    /// The frequency of the note.
+    freq: unt::Freq,
+    /// Whether the note is still physically held, as opposed to latched.
+    down: bool,
+}
+
+/// An arpeggiator, which plays the notes being held on a synth, one at a time.
+///
+/// Notes are pressed and released through [`Self::note_on`] and [`Self::note_off`], or through
+/// [`Self::read_event`]. The arpeggiator steps through them according to its [`Arp`], at a fixed
+/// rate. Each note is held for a fraction of each step given by the gate length, after which it's
+/// [stopped](Stop).
+///
+/// If latch is enabled, notes keep playing after they're released, until a new note is pressed
+/// while no other notes are held.
+///
+/// ## Example
+///
+/// We arpeggiate a C major chord upwards over two octaves, at sixteenth notes in 120 BPM.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let synth = ctr::ArpPoly::new(map::Func::new(|freq| {
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Mono, _>::new(crv::Saw, freq),
+///         eff::env::Ar::new(
+///             unt::Time::from_msec_default(5.0),
+///             unt::Time::from_msec_default(100.0),
+///         ),
+///     )
+/// }));
+///
+/// let mut arp = ctr::Arpeggiator::new_bpm(synth, ctr::ArpMode::Up, 120.0, 4.0);
+/// arp.arp_mut().octaves = 2;
+/// arp.gate = 0.5;
+///
+/// for (key, note) in [unt::RawFreq::C4, unt::RawFreq::E4, unt::RawFreq::G4]
+///     .into_iter()
+///     .enumerate()
+/// {
+///     arp.note_on(key, unt::Freq::from_raw_default(note));
+/// }
+///
+/// let length = unt::Time::from_sec_default(2.0);
+/// Song::new(length, unt::SampleRate::default(), arp).export("examples/arpeggiator.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Arpeggiator<K: Eq + Hash + Clone, T: ArpSynth> {
+    /// The synth being played.
+    synth: T,
+    /// The arpeggio pattern. Its notes are kept in sync with the held notes.
+    arp: Arp,
+    /// The notes being held or latched, in the order they were pressed.
+    held: Vec<Held<K>>,
+
+    /// The time between successive notes.
+    pub rate: unt::Time,
+    /// The fraction of each step for which a note is held, between `0.0` and `1.0`.
+    pub gate: f64,
+    /// Whether notes keep playing after they're released.
+    latch: bool,
+
+    /// Time since the last step.
+    since: unt::Time,
+    /// Whether the last note is still being held.
+    gate_open: bool,
+}
+
+impl<K: Eq + Hash + Clone, T: ArpSynth> Arpeggiator<K, T> {
+    /// Initializes a new arpeggiator with no held notes, with a given rate and mode.
+    pub fn new(synth: T, mode: ArpMode, rate: unt::Time) -> Self {
+        Self {
+            synth,
+            arp: Arp::new_mode(Vec::new(), mode, 1),
+            held: Vec::new(),
+            rate,
+            gate: 1.0,
+            latch: false,
+            since: unt::Time::ZERO,
+            gate_open: false,
+        }
+    }
+
+    /// Initializes a new arpeggiator with a rate synced to a tempo, playing a given number of notes
+    /// per beat. This uses the default sample rate.
+    pub fn new_bpm(synth: T, mode: ArpMode, bpm: f64, per_beat: f64) -> Self {
+        Self::new(
+            synth,
+            mode,
+            unt::Time::from_raw_default(unt::RawTime::new_beat(bpm)) / per_beat,
+        )
+    }
+
+    /// Syncs the rate to a tempo, playing a given number of notes per beat.
+    pub fn set_bpm(&mut self, bpm: f64, per_beat: f64, sample_rate: unt::SampleRate) {
+        self.rate = unt::Time::from_raw(unt::RawTime::new_beat(bpm), sample_rate) / per_beat;
+    }
+
+    /// Returns a reference to the synth.
+    pub const fn synth(&self) -> &T {
+        &self.synth
+    }
+
+    /// Returns a mutable reference to the synth.
+    pub fn synth_mut(&mut self) -> &mut T {
+        &mut self.synth
+    }
+
+    /// Returns a reference to the [`Arp`].
+    pub const fn arp(&self) -> &Arp {
+        &self.arp
+    }
+
+    /// Returns a mutable reference to the [`Arp`].
+    ///
+    /// Note that its notes are overwritten whenever the held notes change.
+    pub fn arp_mut(&mut self) -> &mut Arp {
+        &mut self.arp
+    }
+
+    /// Whether latch is enabled.
+    pub const fn latch(&self) -> bool {
+        self.latch
+    }
+
+    /// Enables or disables latch.
+    ///
+    /// Disabling latch releases any notes that aren't physically held.
+    pub fn set_latch(&mut self, latch: bool) {
+        self.latch = latch;
+        if !latch {
+            self.held.retain(|held| held.down);
+            self.update_notes();
+        }
+    }
+
+    /// The number of notes currently held or latched.
+    pub fn held_len(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Copies the held notes into the arpeggio, and stops playing if there are none left.
+    fn update_notes(&mut self) {
+        self.arp.notes.clear();
+        self.arp
+            .notes
+            .extend(self.held.iter().map(|held| held.freq));
+
+        if self.held.is_empty() {
+            self.close_gate();
+        }
+    }
+
+    /// Stops the current note, if it's still playing.
+    fn close_gate(&mut self) {
+        if self.gate_open {
+            self.synth.note_off();
+            self.gate_open = false;
+        }
+    }
+
+    /// Optionally moves on to the next note, and plays it.
+    ///
+    /// We step before playing, rather than after, so that the notes of a chord pressed right after
+    /// the first one are taken into account.
+    fn trigger(&mut self, step: bool) {
+        self.close_gate();
+        if step {
+            self.arp.step();
+        }
+
+        if let Some(freq) = self.arp.note_at(self.arp.index) {
+            self.synth.note_on(freq);
+            self.gate_open = true;
+        }
+    }
+
+    /// Presses a note with a given key and frequency.
+    ///
+    /// If no notes were held before, the arpeggio starts playing immediately.
+    pub fn note_on(&mut self, key: K, freq: unt::Freq) {
+        // A new chord replaces the latched notes.
+        if self.latch && self.held.iter().all(|held| !held.down) {
+            self.held.clear();
+        }
+
+        let start = self.held.is_empty();
+        self.held.retain(|held| held.key != key);
+        self.held.push(Held {
+            key,
+            freq,
+            down: true,
+        });
+        self.update_notes();
+
+        if start {
+            self.arp.index = 0;
+            self.since = unt::Time::ZERO;
+            self.trigger(false);
+        }
+    }
+
+    /// Releases the note with a given key.
+    pub fn note_off(&mut self, key: &K) {
+        if self.latch {
+            for held in &mut self.held {
+                if held.key == *key {
+                    held.down = false;
+                }
+            }
+        } else {
+            self.held.retain(|held| held.key != *key);
+            self.update_notes();
+        }
+    }
+
+    /// Presses or releases a note according to a [`ctr::NoteEvent`].
+    pub fn read_event(&mut self, event: &ctr::NoteEvent<K, unt::Freq>) {
+        match event {
+            ctr::NoteEvent::Add { key, data } => self.note_on(key.clone(), *data),
+            ctr::NoteEvent::Stop { key } => self.note_off(key),
+            ctr::NoteEvent::Expr { .. } | ctr::NoteEvent::Skip => {}
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, T: ArpSynth> Signal for Arpeggiator<K, T> {
+    type Sample = T::Sample;
+
+    fn get(&self) -> T::Sample {
+        self.synth.get()
+    }
+}
+
+impl<K: Eq + Hash + Clone, T: ArpSynth> SignalMut for Arpeggiator<K, T> {
+    fn advance(&mut self) {
+        self.synth.advance();
+        if self.held.is_empty() {
+            return;
+        }
+
+        self.since.advance();
+        if self.since >= self.rate * self.gate {
+            self.close_gate();
+        }
+
+        if self.since >= self.rate {
+            self.since -= self.rate;
+            self.trigger(true);
+        }
+    }
+
+    fn retrigger(&mut self) {
+        self.synth.retrigger();
+        self.arp.index = 0;
+        self.since = unt::Time::ZERO;
+        self.gate_open = false;
+
+        if !self.held.is_empty() {
+            self.trigger(false);
+        }
+    }
+}
+
+/// Releasing an arpeggiator releases all of its notes, including latched ones.
+impl<K: Eq + Hash + Clone, T: ArpSynth> Stop for Arpeggiator<K, T> {
+    fn stop(&mut self) {
+        self.held.clear();
+        self.update_notes();
+    }
+}
+
+impl<K: Eq + Hash + Clone, T: ArpSynth + Panic> Panic for Arpeggiator<K, T> {
+    fn panic(&mut self) {
+        self.held.clear();
+        self.update_notes();
+        self.synth.panic();
+    }
+}
+
+/// A function that reads through [`ctr::NoteEvent`]s in order, pressing and releasing notes on an
+/// [`Arpeggiator`].
+///
+/// This is used to implement [`ArpSeq`].
+#[derive(Clone, Debug)]
+pub struct ArpReader<K: Eq + Hash + Clone> {
+    /// The note events.
+    pub events: Vec<ctr::NoteEvent<K, unt::Freq>>,
+    /// The index of the current note event.
+    pub index: usize,
+}
+
+impl<K: Eq + Hash + Clone> ArpReader<K> {
+    /// Initializes a new [`ArpReader`].
+    #[must_use]
+    pub const fn new(events: Vec<ctr::NoteEvent<K, unt::Freq>>) -> Self {
+        Self { events, index: 0 }
+    }
+}
+
+impl<K: Eq + Hash + Clone, T: ArpSynth> Mut<Arpeggiator<K, T>> for ArpReader<K> {
+    fn modify(&mut self, sgn: &mut Arpeggiator<K, T>) {
+        if let Some(event) = self.events.get(self.index) {
+            sgn.read_event(event);
+            crate::mod_inc(self.events.len(), &mut self.index);
+        }
+    }
+}
+
+/// An [`Arpeggiator`] whose held notes are given by a [`ctr::Melody`].
+pub type ArpSeq<K, T> = ctr::Seq<Arpeggiator<K, T>, ArpReader<K>>;
+
+impl<K: Eq + Hash + Clone, T: ArpSynth> ArpSeq<K, T> {
+    /// Initializes a new [`ArpSeq`] from a [`ctr::Melody`] of held notes.
+    pub fn new_arp(melody: ctr::Melody<K, unt::Freq>, arp: Arpeggiator<K, T>) -> Self {
+        Self::new(melody.times, arp, ArpReader::new(melody.events))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the notes of an arpeggio over a full pattern.
+    fn pattern(mode: ArpMode, octaves: u8) -> Vec<f64> {
+        let arp = Arp::new_mode([3.0, 1.0, 2.0].map(unt::Freq::new).to_vec(), mode, octaves);
+        (0..arp.pattern_len())
+            .map(|step| arp.note_at(step).unwrap().samples)
+            .collect()
+    }
+
+    /// Test the different arpeggio modes.
+    #[test]
+    fn modes() {
+        assert_eq!(pattern(ArpMode::AsPlayed, 1), [3.0, 1.0, 2.0]);
+        assert_eq!(pattern(ArpMode::Up, 2), [1.0, 2.0, 3.0, 2.0, 4.0, 6.0]);
+        assert_eq!(pattern(ArpMode::Down, 1), [3.0, 2.0, 1.0]);
+        assert_eq!(pattern(ArpMode::UpDown, 1), [1.0, 2.0, 3.0, 2.0]);
+        assert_eq!(pattern(ArpMode::DownUp, 1), [3.0, 2.0, 1.0, 2.0]);
+    }
+
+    /// A synth that logs when each note starts and stops.
+    #[derive(Default)]
+    struct Log {
+        /// The number of samples played.
+        time: u64,
+        /// The time and frequency of each note on, and the time of each note off.
+        events: Vec<(u64, Option<f64>)>,
+    }
+
+    impl Signal for Log {
+        type Sample = smp::Mono;
+
+        fn get(&self) -> smp::Mono {
+            smp::Mono::ZERO
+        }
+    }
+
+    impl SignalMut for Log {
+        fn advance(&mut self) {
+            self.time += 1;
+        }
+
+        fn retrigger(&mut self) {}
+    }
+
+    impl ArpSynth for Log {
+        fn note_on(&mut self, freq: unt::Freq) {
+            self.events.push((self.time, Some(freq.samples)));
+        }
+
+        fn note_off(&mut self) {
+            self.events.push((self.time, None));
+        }
+    }
+
+    /// Initializes an arpeggiator stepping upwards every four samples.
+    fn arp() -> Arpeggiator<u8, Log> {
+        Arpeggiator::new(Log::default(), ArpMode::Up, unt::Time::from_samples(4))
+    }
+
+    /// Advances an arpeggiator by some number of samples, and returns the notes played meanwhile.
+    fn play(arp: &mut Arpeggiator<u8, Log>, samples: u64) -> Vec<(u64, Option<f64>)> {
+        for _ in 0..samples {
+            arp.advance();
+        }
+        std::mem::take(&mut arp.synth_mut().events)
+    }
+
+    /// Test that notes are stopped according to the gate length.
+    #[test]
+    fn gate() {
+        let mut arp = arp();
+        arp.gate = 0.5;
+        arp.note_on(0, unt::Freq::new(1.0));
+        arp.note_on(1, unt::Freq::new(2.0));
+
+        assert_eq!(
+            play(&mut arp, 9),
+            [
+                (0, Some(1.0)),
+                (2, None),
+                (4, Some(2.0)),
+                (6, None),
+                (8, Some(1.0))
+            ]
+        );
+    }
+
+    /// Test that the arpeggio follows the held notes.
+    #[test]
+    fn held() {
+        let mut arp = arp();
+        for (key, freq) in [(0, 1.0), (1, 2.0), (2, 3.0)] {
+            arp.note_on(key, unt::Freq::new(freq));
+        }
+        play(&mut arp, 3);
+
+        // The released note is skipped.
+        arp.note_off(&1);
+        assert_eq!(
+            play(&mut arp, 8),
+            [(4, None), (4, Some(3.0)), (8, None), (8, Some(1.0))]
+        );
+
+        // Releasing every note stops the arpeggio.
+        arp.note_off(&0);
+        arp.note_off(&2);
+        assert_eq!(play(&mut arp, 8), [(11, None)]);
+        assert_eq!(arp.held_len(), 0);
+    }
+
+    /// Test that latched notes keep playing until a new chord is pressed.
+    #[test]
+    fn latch() {
+        let mut arp = arp();
+        arp.set_latch(true);
+        arp.note_on(0, unt::Freq::new(1.0));
+        arp.note_on(1, unt::Freq::new(2.0));
+        arp.note_off(&0);
+
+        // A note pressed while another is held is added to the chord.
+        arp.note_on(2, unt::Freq::new(3.0));
+        arp.note_off(&1);
+        arp.note_off(&2);
+        assert_eq!(arp.held_len(), 3);
+        play(&mut arp, 12);
+        assert_eq!(arp.arp().notes.len(), 3);
+
+        // A note pressed while none are held replaces the chord.
+        arp.note_on(3, unt::Freq::new(4.0));
+        assert_eq!(arp.held_len(), 1);
+        assert_eq!(
+            play(&mut arp, 4),
+            [(12, None), (12, Some(4.0)), (16, None), (16, Some(4.0))]
+        );
+
+        // Disabling latch releases the notes that aren't held.
+        arp.note_off(&3);
+        arp.set_latch(false);
+        assert_eq!(arp.held_len(), 0);
+        assert_eq!(play(&mut arp, 4), [(16, None)]);
+    }
+
+    /// Test that the rate is synced to the tempo.
+    #[test]
+    fn bpm() {
+        // Sixteenth notes at 120 BPM are 5512.5 samples apart.
+        let mut arp = Arpeggiator::new_bpm(Log::default(), ArpMode::Up, 120.0, 4.0);
+        assert_eq!(arp.rate, unt::Time::from_sec_default(0.125));
+
+        arp.note_on(0, unt::Freq::new(1.0));
+        let starts: Vec<_> = play(&mut arp, 11025)
+            .into_iter()
+            .filter_map(|(time, freq)| freq.map(|_| time))
+            .collect();
+        assert_eq!(starts, [0, 5513, 11025]);
+    }
+}
@@ -0,0 +1,396 @@
+//! Implements per-note expression, in the style of MPE.
+//!
+//! An [`Expr`] event changes the pitch bend, pressure, or timbre of a single note. These events can
+//! be sent to a voice in a [`poly::Polyphony`] through [`poly::Polyphony::express`], or read from a
+//! [`ctr::Melody`] through an [`ExprSeq`].
+//!
+//! Voices respond to these events by implementing [`Express`]. The simplest way to do this is
+//! through an [`Expressive`] signal, which keeps track of the current [`Expression`] of a note, and
+//! uses it to modify a signal on every frame.
+
+use crate::prelude::*;
+use std::hash::Hash;
+
+/// A per-note expression event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expr {
+    /// Bends the note by a given amount of 12-EDO semitones.
+    Bend(f64),
+    /// Sets the pressure of the note, usually between `0` and `1`.
+    Pressure(f64),
+    /// Sets the timbre of the note, usually between `0` and `1`.
+    Timbre(f64),
+}
+
+/// A signal which responds to [`Expr`] events.
+pub trait Express: SignalMut {
+    /// Applies an expression event to the signal.
+    fn express(&mut self, expr: Expr);
+}
+
+/// The current expression of a note, as passed to the function of an [`Expressive`] signal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Expression {
+    /// The pitch bend, in 12-EDO semitones.
+    pub bend: smp::Env,
+    /// The pressure of the note.
+    pub pressure: smp::Env,
+    /// The timbre of the note.
+    pub timbre: smp::Env,
+}
+
+impl Expression {
+    /// The default expression of a note: no bend, no pressure, and a neutral timbre.
+    pub const NEUTRAL: Self = Self {
+        bend: smp::Env(0.0),
+        pressure: smp::Env(0.0),
+        timbre: smp::Env(0.5),
+    };
+
+    /// The interval by which the pitch bend transposes a note.
+    #[must_use]
+    pub fn interval(&self) -> unt::Interval {
+        unt::Interval::note(self.bend.0)
+    }
+}
+
+/// A value which glides linearly towards a target.
+#[derive(Clone, Copy, Debug)]
+struct Slew {
+    /// The value at the start of the glide.
+    from: f64,
+    /// The value at the end of the glide.
+    to: f64,
+    /// Time elapsed since the start of the glide.
+    elapsed: unt::Time,
+}
+
+impl Slew {
+    /// Initializes a value that isn't gliding.
+    const fn new(value: f64) -> Self {
+        Self {
+            from: value,
+            to: value,
+            elapsed: unt::Time::MAX,
+        }
+    }
+
+    /// The current value.
+    fn get(&self, slew: unt::Time) -> f64 {
+        if self.elapsed < slew {
+            self.from + (self.to - self.from) * (self.elapsed / slew)
+        } else {
+            self.to
+        }
+    }
+
+    /// Starts gliding towards a new target.
+    fn set(&mut self, to: f64, slew: unt::Time) {
+        self.from = self.get(slew);
+        self.to = to;
+        self.elapsed = unt::Time::ZERO;
+    }
+
+    /// Advances the glide by a sample.
+    fn advance(&mut self) {
+        if self.elapsed != unt::Time::MAX {
+            self.elapsed.advance();
+        }
+    }
+}
+
+/// A signal that keeps track of the [`Expression`] of a note, and uses it to modify a signal on
+/// every frame.
+///
+/// Expression events don't change the values immediately. Instead, they glide towards the new
+/// values over the [slew](Self::slew) time, which avoids zipper noise.
+///
+/// ## Example
+///
+/// We bend a note up a whole tone, while its pulse width narrows.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let base = unt::Freq::from_raw_default(unt::RawFreq::A3);
+///
+/// let mut sgn = ctr::Expressive::new(
+///     gen::Loop::<smp::Mono, _>::new(crv::Pulse::sq(), base),
+///     map::Func::new(move |sgn: &mut gen::Loop<smp::Mono, crv::Pulse>, expr: ctr::Expression| {
+///         *sgn.freq_mut() = base * expr.interval();
+///         sgn.curve_mut().shape = 0.5 - 0.4 * expr.timbre.0;
+///     }),
+/// );
+/// sgn.slew = sec(1.0);
+/// sgn.express(ctr::Expr::Bend(2.0));
+/// sgn.express(ctr::Expr::Timbre(1.0));
+///
+/// Song::new(sec(2.0), unt::SampleRate::default(), eff::Volume::new(sgn, unt::Vol::HALF))
+///     .export("examples/expressive.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Expressive<S: SignalMut, F: Val<S, Val = Expression>> {
+    /// The signal being modified.
+    sgn: S,
+    /// The function modifying the signal.
+    func: F,
+
+    /// The pitch bend.
+    bend: Slew,
+    /// The pressure.
+    pressure: Slew,
+    /// The timbre.
+    timbre: Slew,
+
+    /// How long it takes for the expression to reach a new value.
+    pub slew: unt::Time,
+}
+
+impl<S: SignalMut, F: Val<S, Val = Expression>> Expressive<S, F> {
+    /// The default slew time.
+    ///
+    /// This is about 5 ms at the default sample rate.
+    pub const SLEW: unt::Time = unt::Time::from_samples(220);
+
+    /// Initializes a new [`Expressive`] signal, with a [neutral](Expression::NEUTRAL) expression.
+    pub fn new(sgn: S, func: F) -> Self {
+        let expr = Expression::NEUTRAL;
+        let mut res = Self {
+            sgn,
+            func,
+            bend: Slew::new(expr.bend.0),
+            pressure: Slew::new(expr.pressure.0),
+            timbre: Slew::new(expr.timbre.0),
+            slew: Self::SLEW,
+        };
+
+        res.modify();
+        res
+    }
+
+    /// Returns a reference to the modified signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the modified signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the function modifying the signal.
+    pub const fn func(&self) -> &F {
+        &self.func
+    }
+
+    /// Returns a mutable reference to the function modifying the signal.
+    pub fn func_mut(&mut self) -> &mut F {
+        &mut self.func
+    }
+
+    /// The current expression of the note.
+    pub fn expression(&self) -> Expression {
+        Expression {
+            bend: self.bend(),
+            pressure: self.pressure(),
+            timbre: self.timbre(),
+        }
+    }
+
+    /// The current pitch bend, in 12-EDO semitones.
+    pub fn bend(&self) -> smp::Env {
+        smp::Env(self.bend.get(self.slew))
+    }
+
+    /// The current pressure.
+    pub fn pressure(&self) -> smp::Env {
+        smp::Env(self.pressure.get(self.slew))
+    }
+
+    /// The current timbre.
+    pub fn timbre(&self) -> smp::Env {
+        smp::Env(self.timbre.get(self.slew))
+    }
+
+    /// Modifies the signal according to the function.
+    fn modify(&mut self) {
+        let expr = self.expression();
+        self.func.modify_val(&mut self.sgn, expr);
+    }
+}
+
+impl<S: SignalMut, F: Val<S, Val = Expression>> Signal for Expressive<S, F> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.sgn.get()
+    }
+}
+
+impl<S: SignalMut, F: Val<S, Val = Expression>> SignalMut for Expressive<S, F> {
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.bend.advance();
+        self.pressure.advance();
+        self.timbre.advance();
+        self.modify();
+    }
+
+    /// Retriggers the signal. The expression of the note is kept.
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+    }
+}
+
+impl<S: Frequency, F: Val<S, Val = Expression>> Frequency for Expressive<S, F> {
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, F: Val<S, Val = Expression>> Base for Expressive<S, F> {
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: SignalMut + Done, F: Val<S, Val = Expression>> Done for Expressive<S, F> {
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, F: Val<S, Val = Expression>> Stop for Expressive<S, F> {
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, F: Val<S, Val = Expression>> Panic for Expressive<S, F> {
+    fn panic(&mut self) {
+        self.sgn.panic();
+    }
+}
+
+impl<S: SignalMut, F: Val<S, Val = Expression>> Express for Expressive<S, F> {
+    fn express(&mut self, expr: Expr) {
+        match expr {
+            Expr::Bend(bend) => self.bend.set(bend, self.slew),
+            Expr::Pressure(pressure) => self.pressure.set(pressure, self.slew),
+            Expr::Timbre(timbre) => self.timbre.set(timbre, self.slew),
+        }
+    }
+}
+
+/// A "note reader" function that reads through different note events in order, and modifies a
+/// [`poly::Polyphony`] struct accordingly. It can also drive any other [`poly::Polyphonic`] struct.
+///
+/// Unlike a [`ctr::NoteReader`], which ignores [`ctr::NoteEvent::Expr`] events, this sends them to
+/// the corresponding voices.
+#[derive(Clone, Debug)]
+pub struct ExprReader<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>>
+where
+    F::Output: Express + Stop + Done,
+{
+    /// The note events.
+    pub events: Vec<ctr::NoteEvent<K, D>>,
+    /// The index of the current note event.
+    pub index: usize,
+    /// The function that builds a new signal from the specified note data.
+    pub func: F,
+}
+
+impl<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>> ExprReader<K, D, F>
+where
+    F::Output: Express + Stop + Done,
+{
+    /// Initializes a new [`ExprReader`].
+    pub const fn new(events: Vec<ctr::NoteEvent<K, D>>, func: F) -> Self {
+        Self {
+            events,
+            index: 0,
+            func,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>, P: poly::Polyphonic<K, F::Output>> Mut<P>
+    for ExprReader<K, D, F>
+where
+    F::Output: Express + Stop + Done,
+{
+    fn modify(&mut self, sgn: &mut P) {
+        if let Some(event) = self.events.get(self.index) {
+            match event {
+                ctr::NoteEvent::Add { key, data } => {
+                    sgn.add(key.clone(), self.func.eval(data.clone()));
+                }
+                ctr::NoteEvent::Stop { key } => {
+                    sgn.stop(key);
+                }
+                ctr::NoteEvent::Expr { key, expr } => {
+                    sgn.express(key, *expr);
+                }
+                ctr::NoteEvent::Skip => {}
+            }
+
+            crate::mod_inc(self.events.len(), &mut self.index);
+        }
+    }
+}
+
+/// A melody with per-note expression that plays from start to end.
+pub type ExprSeq<K, D, F> = ctr::Seq<poly::Polyphony<K, <F as Map>::Output>, ExprReader<K, D, F>>;
+
+impl<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>> ExprSeq<K, D, F>
+where
+    F::Output: Express + Stop + Done,
+{
+    /// Initializes a new [`ExprSeq`] from a [`ctr::Melody`].
+    ///
+    /// The passed function builds signals from the given note data.
+    pub fn new_melody(melody: ctr::Melody<K, D>, func: F) -> Self {
+        Self::new(
+            melody.times,
+            poly::Polyphony::new(),
+            ExprReader::new(melody.events, func),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that expression glides towards its new values.
+    #[test]
+    fn slew() {
+        let mut sgn = Expressive::new(
+            gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::new(0.01)),
+            map::Func::new(
+                |sgn: &mut gen::Loop<smp::Mono, crv::Sin>, expr: Expression| {
+                    *sgn.freq_mut() = unt::Freq::new(0.01) * expr.interval();
+                },
+            ),
+        );
+        sgn.slew = unt::Time::from_samples(4);
+        sgn.express(Expr::Bend(12.0));
+
+        sgn.advance();
+        sgn.advance();
+        assert_approx_eq::assert_approx_eq!(sgn.bend().0, 6.0);
+        sgn.advance();
+        sgn.advance();
+        assert_approx_eq::assert_approx_eq!(sgn.freq().samples, 0.02);
+    }
+}
@@ -0,0 +1,364 @@
+//! Implements the [`Legato`] type, for monophonic synths with portamento.
+
+use crate::prelude::*;
+use std::hash::Hash;
+
+/// Which of the held notes a [`Legato`] synth plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// The note pressed most recently.
+    #[default]
+    Last,
+    /// The lowest note.
+    Low,
+    /// The highest note.
+    High,
+}
+
+/// The curve followed by the frequency of a [`Legato`] synth as it glides between notes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Glide {
+    /// The frequency changes linearly in hertz.
+    Linear,
+    /// The frequency changes linearly in pitch, meaning exponentially in hertz.
+    #[default]
+    Exp,
+}
+
+impl Glide {
+    /// Interpolates between two frequencies.
+    ///
+    /// An exponential glide from or to a non-positive frequency falls back to a linear glide.
+    #[must_use]
+    pub fn eval(self, from: unt::Freq, to: unt::Freq, t: f64) -> unt::Freq {
+        let (from, to) = (from.samples, to.samples);
+        unt::Freq::new(match self {
+            Self::Exp if from > 0.0 && to > 0.0 => from * (to / from).powf(t),
+            _ => from + (to - from) * t,
+        })
+    }
+}
+
+/// A monophonic synth, which plays a single signal from a set of held notes.
+///
+/// Notes are pressed and released through [`Self::note_on`] and [`Self::note_off`], or by reading
+/// [`ctr::NoteEvents`](ctr::NoteEvent) through a [`LegatoReader`]. At any point, the synth plays
+/// one of the held notes, chosen according to its [`Priority`]. When the last held note is released,
+/// the signal is stopped.
+///
+/// When the played note changes while other notes are held, the frequency glides to the new one
+/// over the [portamento](Self::portamento) time. In [legato](Self::legato) mode, the signal is not
+/// retriggered in this case, so that its envelope carries on.
+///
+/// ## Example
+///
+/// We play a melody with overlapping notes, which glide into each other.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let sgn = eff::env::AdsrEnv::new_adsr(
+///     gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::ZERO),
+///     eff::env::Adsr::new(sec(0.05), sec(0.2), unt::Vol::HALF, sec(0.3)),
+/// );
+///
+/// let mut legato = ctr::Legato::new(sgn, ctr::Priority::Last);
+/// legato.portamento = sec(0.1);
+///
+/// let notes = [unt::RawFreq::C4, unt::RawFreq::E4, unt::RawFreq::G4, unt::RawFreq::C5]
+///     .into_iter()
+///     .enumerate()
+///     .map(|(i, raw)| ctr::Note::new(sec(i as f64 * 0.5), sec(0.6), raw));
+/// let melody = ctr::Melody::piano_roll(notes, |idx| idx);
+///
+/// let seq = ctr::LegatoSeq::new_melody(melody, legato, map::Func::new(unt::Freq::from_raw_default));
+/// Song::new(sec(2.5), unt::SampleRate::default(), seq).export("examples/legato.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Legato<K: Eq + Clone, S: Frequency + Stop> {
+    /// The signal being played.
+    sgn: S,
+    /// The notes being held, in the order they were pressed.
+    held: Vec<(K, unt::Freq)>,
+
+    /// The frequency at the start of the current glide.
+    from: unt::Freq,
+    /// The frequency at the end of the current glide.
+    to: unt::Freq,
+    /// Time elapsed since the start of the current glide.
+    elapsed: unt::Time,
+
+    /// Which of the held notes is played.
+    pub priority: Priority,
+    /// Whether to skip retriggering the signal when changing notes while others are held.
+    pub legato: bool,
+    /// How long it takes to glide between notes.
+    pub portamento: unt::Time,
+    /// The curve used to glide between notes.
+    pub glide: Glide,
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop> Legato<K, S> {
+    /// Initializes a new legato synth, with no portamento.
+    pub fn new(sgn: S, priority: Priority) -> Self {
+        let freq = sgn.freq();
+        Self {
+            sgn,
+            held: Vec::new(),
+            from: freq,
+            to: freq,
+            elapsed: unt::Time::ZERO,
+            priority,
+            legato: true,
+            portamento: unt::Time::ZERO,
+            glide: Glide::default(),
+        }
+    }
+
+    /// Returns a reference to the signal being played.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the signal being played.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The number of notes being held.
+    pub fn held_len(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Whether the synth is currently gliding between notes.
+    pub fn is_gliding(&self) -> bool {
+        self.elapsed < self.portamento
+    }
+
+    /// The frequency of the held note that should be played.
+    fn target(&self) -> Option<unt::Freq> {
+        let freqs = self.held.iter().map(|&(_, freq)| freq);
+        let by_freq = |a: &unt::Freq, b: &unt::Freq| a.samples.total_cmp(&b.samples);
+
+        match self.priority {
+            Priority::Last => freqs.last(),
+            Priority::Low => freqs.min_by(by_freq),
+            Priority::High => freqs.max_by(by_freq),
+        }
+    }
+
+    /// Sets the frequency of the signal according to the current glide.
+    fn update_freq(&mut self) {
+        *self.sgn.freq_mut() = if self.is_gliding() {
+            self.glide
+                .eval(self.from, self.to, self.elapsed / self.portamento)
+        } else {
+            self.to
+        };
+    }
+
+    /// Changes the played note, after the held notes change.
+    fn update_note(&mut self, start: bool) {
+        let Some(target) = self.target() else {
+            self.sgn.stop();
+            return;
+        };
+
+        if start {
+            // No glide from silence.
+            self.from = target;
+            self.to = target;
+            self.elapsed = self.portamento;
+            self.update_freq();
+            self.sgn.retrigger();
+        } else if target != self.to {
+            self.from = self.sgn.freq();
+            self.to = target;
+            self.elapsed = unt::Time::ZERO;
+            self.update_freq();
+
+            if !self.legato {
+                self.sgn.retrigger();
+            }
+        }
+    }
+
+    /// Presses a note with a given key and frequency.
+    pub fn note_on(&mut self, key: K, freq: unt::Freq) {
+        let start = self.held.is_empty();
+        self.held.retain(|(k, _)| *k != key);
+        self.held.push((key, freq));
+        self.update_note(start);
+    }
+
+    /// Releases a note with a given key.
+    pub fn note_off(&mut self, key: &K) {
+        let len = self.held_len();
+        self.held.retain(|(k, _)| k != key);
+
+        if self.held_len() != len {
+            self.update_note(false);
+        }
+    }
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop> Signal for Legato<K, S> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.sgn.get()
+    }
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop> SignalMut for Legato<K, S> {
+    fn advance(&mut self) {
+        self.sgn.advance();
+        if self.is_gliding() {
+            self.elapsed.advance();
+            self.update_freq();
+        }
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+    }
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop + Done> Done for Legato<K, S> {
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop> Stop for Legato<K, S> {
+    fn stop(&mut self) {
+        self.held.clear();
+        self.sgn.stop();
+    }
+}
+
+impl<K: Eq + Clone, S: Frequency + Stop + Panic> Panic for Legato<K, S> {
+    fn panic(&mut self) {
+        self.held.clear();
+        self.sgn.panic();
+    }
+}
+
+/// A "note reader" function that reads through different note events in order, and plays them on
+/// a [`Legato`] synth.
+///
+/// This reads the same events as a [`ctr::NoteReader`], but instead of building a signal from the
+/// data of each note, it only gets its frequency.
+#[derive(Clone, Debug)]
+pub struct LegatoReader<K: Eq + Hash + Clone, D, F: Map<Input = D, Output = unt::Freq>> {
+    /// The note events.
+    pub events: Vec<ctr::NoteEvent<K, D>>,
+    /// The index of the current note event.
+    pub index: usize,
+    /// The function that gets a frequency from the specified note data.
+    pub func: F,
+}
+
+impl<K: Eq + Hash + Clone, D, F: Map<Input = D, Output = unt::Freq>> LegatoReader<K, D, F> {
+    /// Initializes a new [`LegatoReader`].
+    pub const fn new(events: Vec<ctr::NoteEvent<K, D>>, func: F) -> Self {
+        Self {
+            events,
+            index: 0,
+            func,
+        }
+    }
+}
+
+impl<
+        K: Eq + Hash + Clone,
+        D: Clone,
+        F: Map<Input = D, Output = unt::Freq>,
+        S: Frequency + Stop,
+    > Mut<Legato<K, S>> for LegatoReader<K, D, F>
+{
+    fn modify(&mut self, sgn: &mut Legato<K, S>) {
+        if let Some(event) = self.events.get(self.index) {
+            match event {
+                ctr::NoteEvent::Add { key, data } => {
+                    sgn.note_on(key.clone(), self.func.eval(data.clone()));
+                }
+                ctr::NoteEvent::Stop { key } => sgn.note_off(key),
+                ctr::NoteEvent::Expr { .. } | ctr::NoteEvent::Skip => {}
+            }
+
+            crate::mod_inc(self.events.len(), &mut self.index);
+        }
+    }
+}
+
+/// A [`Legato`] synth playing a [`ctr::Melody`].
+pub type LegatoSeq<K, S, D, F> = ctr::Seq<Legato<K, S>, LegatoReader<K, D, F>>;
+
+impl<
+        K: Eq + Hash + Clone,
+        S: Frequency + Stop,
+        D: Clone,
+        F: Map<Input = D, Output = unt::Freq>,
+    > LegatoSeq<K, S, D, F>
+{
+    /// Initializes a new [`LegatoSeq`] from a [`ctr::Melody`].
+    ///
+    /// The passed function gets the frequency from the given note data.
+    pub fn new_melody(melody: ctr::Melody<K, D>, legato: Legato<K, S>, func: F) -> Self {
+        Self::new(melody.times, legato, LegatoReader::new(melody.events, func))
+    }
+
+    /// Returns a reference to the [`Legato`] synth.
+    pub const fn legato(&self) -> &Legato<K, S> {
+        self.sgn()
+    }
+
+    /// Returns a mutable reference to the [`Legato`] synth.
+    pub fn legato_mut(&mut self) -> &mut Legato<K, S> {
+        self.sgn_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test note priority and portamento.
+    #[test]
+    fn glide() {
+        let sgn = eff::env::ArEnv::new_ar(
+            gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::ZERO),
+            eff::env::Ar::new(unt::Time::from_samples(10), unt::Time::from_samples(10)),
+        );
+        let mut legato = Legato::new(sgn, Priority::Low);
+        legato.portamento = unt::Time::from_samples(4);
+        legato.glide = Glide::Linear;
+        let freq = |legato: &Legato<u8, eff::env::ArEnv<gen::Loop<smp::Mono, crv::Sin>>>| {
+            legato.sgn().freq().samples
+        };
+
+        legato.note_on(0, unt::Freq::new(0.02));
+        assert_approx_eq::assert_approx_eq!(freq(&legato), 0.02);
+
+        // A higher note doesn't play.
+        legato.note_on(1, unt::Freq::new(0.03));
+        for _ in 0..4 {
+            legato.advance();
+        }
+        assert_approx_eq::assert_approx_eq!(freq(&legato), 0.02);
+
+        // A lower note glides.
+        legato.note_on(2, unt::Freq::new(0.01));
+        legato.advance();
+        legato.advance();
+        assert_approx_eq::assert_approx_eq!(freq(&legato), 0.015);
+        legato.advance();
+        legato.advance();
+        assert_approx_eq::assert_approx_eq!(freq(&legato), 0.01);
+        assert!(!legato.is_gliding());
+
+        // The envelope wasn't retriggered.
+        assert_approx_eq::assert_approx_eq!(legato.sgn().env().get().0, 0.8);
+    }
+}
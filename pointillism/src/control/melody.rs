@@ -94,6 +94,11 @@ pub enum NoteEvent<K: Eq + Hash + Clone, D> {
     Add { key: K, data: D },
     /// Stops a note with a certain index.
     Stop { key: K },
+    /// Changes the expression of a note with a certain index.
+    ///
+    /// See [`ctr::Expr`] for more information. These events are ignored by a [`NoteReader`], but
+    /// are read by a [`ctr::ExprReader`].
+    Expr { key: K, expr: ctr::Expr },
     /// Does nothing. This exists so that loops can work properly.
     Skip,
 }
@@ -202,8 +207,109 @@ impl MidiNoteData {
     }
 }
 
+/// An MPE zone, which determines how per-note expression is read from a MIDI file.
+///
+/// A zone consists of a master channel, and a range of member channels. Each note on a member
+/// channel is expected to have that channel to itself, so that channel-wide messages like pitch
+/// bend can be applied to that single note.
+///
+/// See [`Melody::from_midi_mpe`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg(feature = "midly")]
+pub struct MpeZone {
+    /// The master channel.
+    pub master: u4,
+    /// The first member channel.
+    pub first: u4,
+    /// The last member channel.
+    pub last: u4,
+    /// The pitch bend range of the member channels, in 12-EDO semitones.
+    pub bend_range: f64,
+    /// The pitch bend range of the master channel, in 12-EDO semitones.
+    pub master_bend_range: f64,
+}
+
+#[cfg(feature = "midly")]
+impl MpeZone {
+    /// The default pitch bend range of the member channels.
+    pub const BEND_RANGE: f64 = 48.0;
+    /// The default pitch bend range of the master channel.
+    pub const MASTER_BEND_RANGE: f64 = 2.0;
+
+    /// The lower zone, whose master channel is the first one, and whose member channels start at
+    /// the second one.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the number of member channels isn't between 1 and 15.
+    #[must_use]
+    pub fn lower(members: u8) -> Self {
+        assert!((1..=15).contains(&members), "invalid number of members");
+        Self {
+            master: u4::new(0),
+            first: u4::new(1),
+            last: u4::new(members),
+            bend_range: Self::BEND_RANGE,
+            master_bend_range: Self::MASTER_BEND_RANGE,
+        }
+    }
+
+    /// The upper zone, whose master channel is the last one, and whose member channels end at the
+    /// second to last one.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the number of member channels isn't between 1 and 15.
+    #[must_use]
+    pub fn upper(members: u8) -> Self {
+        assert!((1..=15).contains(&members), "invalid number of members");
+        Self {
+            master: u4::new(15),
+            first: u4::new(15 - members),
+            last: u4::new(14),
+            bend_range: Self::BEND_RANGE,
+            master_bend_range: Self::MASTER_BEND_RANGE,
+        }
+    }
+
+    /// Whether a channel is one of the member channels.
+    #[must_use]
+    pub fn is_member(&self, channel: u4) -> bool {
+        (self.first..=self.last).contains(&channel)
+    }
+
+    /// Updates the expression of a member channel from a MIDI message, and returns the
+    /// corresponding event, if any. Pitch bend is measured in semitones.
+    fn read_expr(
+        &self,
+        expr: &mut ctr::Expression,
+        message: midly::MidiMessage,
+        master_bend: f64,
+    ) -> Option<ctr::Expr> {
+        /// The controller for MPE timbre.
+        const TIMBRE: u7 = u7::new(74);
+
+        match message {
+            midly::MidiMessage::PitchBend { bend } => {
+                expr.bend.0 = bend.as_f64() * self.bend_range;
+                Some(ctr::Expr::Bend(expr.bend.0 + master_bend))
+            }
+            midly::MidiMessage::ChannelAftertouch { vel } => {
+                expr.pressure.0 = f64::from(vel.as_int()) / 127.0;
+                Some(ctr::Expr::Pressure(expr.pressure.0))
+            }
+            midly::MidiMessage::Controller { controller, value } if controller == TIMBRE => {
+                expr.timbre.0 = f64::from(value.as_int()) / 127.0;
+                Some(ctr::Expr::Timbre(expr.timbre.0))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// A "note reader" function that reads through different note events in order, and modifies a
-/// [`poly::Polyphony`] struct accordingly.
+/// [`poly::Polyphony`] struct accordingly. It can also drive any other [`poly::Polyphonic`] struct,
+/// such as a [`poly::Pool`] or a [`poly::Voices`].
 ///
 /// This is used to implement [`MelSeq`] and [`MelLoop`].
 #[derive(Clone, Debug)]
@@ -268,18 +374,18 @@ where
     }
 }
 
-impl<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>> Mut<poly::Polyphony<K, F::Output>>
+impl<K: Eq + Hash + Clone, D: Clone, F: Map<Input = D>, P: poly::Polyphonic<K, F::Output>> Mut<P>
     for NoteReader<K, D, F>
 where
     F::Output: Frequency + Stop + Done,
 {
-    fn modify(&mut self, sgn: &mut poly::Polyphony<K, F::Output>) {
+    fn modify(&mut self, sgn: &mut P) {
         match self.current() {
             NoteEvent::Add { key, data } => sgn.add(key.clone(), self.func.eval(data.clone())),
             NoteEvent::Stop { key } => {
                 sgn.stop(key);
             }
-            NoteEvent::Skip => {}
+            NoteEvent::Expr { .. } | NoteEvent::Skip => {}
         }
 
         crate::mod_inc(self.len(), &mut self.index);
@@ -440,6 +546,38 @@ impl<K: Eq + Hash + Clone> Melody<K, MidiNoteData> {
     pub fn from_midi<G: FnMut(usize) -> K>(
         event_iter: midly::EventIter,
         tick_time: unt::Time,
+        idx_cast: G,
+    ) -> midly::Result<Self> {
+        Self::read_midi(event_iter, tick_time, None, idx_cast)
+    }
+
+    /// Builds a melody from a MIDI file using MPE, so that each note can have its own expression.
+    ///
+    /// Notes on the member channels of the zone get [`NoteEvent::Expr`] events from the pitch bend,
+    /// channel pressure, and CC74 (timbre) messages on their channel. Pitch bend on the master
+    /// channel is added to the bend of every note in the zone. Notes on other channels are read as
+    /// in [`Self::from_midi`].
+    ///
+    /// Every note in the zone starts with an expression event for each of its bend, pressure, and
+    /// timbre, since these might have been set before the note started.
+    ///
+    /// ## Errors
+    ///
+    /// Any errors returned will result from the event iterator itself.
+    pub fn from_midi_mpe<G: FnMut(usize) -> K>(
+        event_iter: midly::EventIter,
+        tick_time: unt::Time,
+        zone: MpeZone,
+        idx_cast: G,
+    ) -> midly::Result<Self> {
+        Self::read_midi(event_iter, tick_time, Some(zone), idx_cast)
+    }
+
+    /// Builds a melody from a MIDI file, optionally reading MPE expression from a given zone.
+    fn read_midi<G: FnMut(usize) -> K>(
+        event_iter: midly::EventIter,
+        tick_time: unt::Time,
+        zone: Option<MpeZone>,
         mut idx_cast: G,
     ) -> midly::Result<Self> {
         // The things we want to return.
@@ -452,6 +590,12 @@ impl<K: Eq + Hash + Clone> Melody<K, MidiNoteData> {
         // TODO: benchmark against just using a hash table.
         let mut latest = [usize::MAX; 128 * 16];
 
+        // The notes playing on each member channel, and the expression of each channel. Pitch bend
+        // is measured in semitones.
+        let mut playing: [Vec<usize>; 16] = Default::default();
+        let mut exprs = [ctr::Expression::NEUTRAL; 16];
+        let mut master_bend = 0.0;
+
         // A unique note index, time since last event.
         let mut idx = 0;
         let mut since_last = 0;
@@ -463,31 +607,55 @@ impl<K: Eq + Hash + Clone> Melody<K, MidiNoteData> {
 
             // We only read MIDI events.
             if let midly::TrackEventKind::Midi { channel, message } = event.kind {
+                let ch = channel.as_int() as usize;
+                let member = zone.is_some_and(|zone| zone.is_member(channel));
+                let master = zone.is_some_and(|zone| zone.master == channel);
+
                 // Gets an index in our "hash map".
-                let index = |key: u7| 128 * (channel.as_int() as usize) + key.as_int() as usize;
+                let index = |key: u7| 128 * ch + key.as_int() as usize;
 
-                // Stops the specified key.
-                let mut stop = |key: u7| {
-                    events.push(NoteEvent::Stop {
-                        key: idx_cast(latest[index(key)]),
-                    });
+                // Adds an event, along with the time since the last one.
+                let mut push = |event| {
+                    events.push(event);
                     times.push(since_last * tick_time);
                     since_last = 0;
                 };
 
+                // Stops the specified key.
+                let mut stop = |key: u7, push: &mut dyn FnMut(NoteEvent<K, MidiNoteData>)| {
+                    let old = latest[index(key)];
+                    push(NoteEvent::Stop { key: idx_cast(old) });
+                    playing[ch].retain(|&note| note != old);
+                };
+
                 match message {
                     // Note on event.
                     midly::MidiMessage::NoteOn { key, vel } => {
-                        stop(key);
+                        stop(key, &mut push);
 
                         // A note-on with velocity 0 just turns the note off.
                         if vel != u7::new(0) {
                             // Add new note.
-                            events.push(NoteEvent::Add {
+                            push(NoteEvent::Add {
                                 key: idx_cast(idx),
                                 data: MidiNoteData::new(channel, key, vel),
                             });
-                            times.push(unt::Time::ZERO);
+
+                            if member {
+                                let expr = exprs[ch];
+                                for expr in [
+                                    ctr::Expr::Bend(expr.bend.0 + master_bend),
+                                    ctr::Expr::Pressure(expr.pressure.0),
+                                    ctr::Expr::Timbre(expr.timbre.0),
+                                ] {
+                                    push(NoteEvent::Expr {
+                                        key: idx_cast(idx),
+                                        expr,
+                                    });
+                                }
+
+                                playing[ch].push(idx);
+                            }
 
                             latest[index(key)] = idx;
                             idx += 1;
@@ -496,7 +664,38 @@ impl<K: Eq + Hash + Clone> Melody<K, MidiNoteData> {
 
                     // Note off event.
                     midly::MidiMessage::NoteOff { key, vel: _ } => {
-                        stop(key);
+                        stop(key, &mut push);
+                    }
+
+                    // Pitch bend on the master channel affects all notes in the zone.
+                    midly::MidiMessage::PitchBend { bend } if master => {
+                        let zone = zone.expect("master channel must belong to a zone");
+                        master_bend = bend.as_f64() * zone.master_bend_range;
+
+                        for (notes, expr) in playing.iter().zip(&exprs) {
+                            for &note in notes {
+                                push(NoteEvent::Expr {
+                                    key: idx_cast(note),
+                                    expr: ctr::Expr::Bend(expr.bend.0 + master_bend),
+                                });
+                            }
+                        }
+                    }
+
+                    // Expression on a member channel affects the notes on that channel.
+                    _ if member => {
+                        let zone = zone.expect("member channel must belong to a zone");
+                        let Some(expr) = zone.read_expr(&mut exprs[ch], message, master_bend)
+                        else {
+                            continue;
+                        };
+
+                        for &note in &playing[ch] {
+                            push(NoteEvent::Expr {
+                                key: idx_cast(note),
+                                expr,
+                            });
+                        }
                     }
 
                     // Ignore anything else.
@@ -591,3 +790,70 @@ where
         self.func_mut()
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "midly")]
+mod test {
+    use super::*;
+
+    /// Test that MPE expression is routed to the right notes.
+    #[test]
+    fn mpe() {
+        #[rustfmt::skip]
+        let track = [
+            // Notes on member channels 1 and 2.
+            0, 0x91, 60, 100,
+            0, 0x92, 64, 100,
+            // Pitch bend up half the range on channel 1.
+            1, 0xE1, 0x00, 0x60,
+            // Full pressure on channel 2.
+            0, 0xD2, 127,
+            // Zero timbre on channel 1.
+            0, 0xB1, 74, 0,
+            // Pitch bend up half the range on the master channel.
+            0, 0xE0, 0x00, 0x60,
+            // Pressure on a channel outside the zone.
+            0, 0xD9, 127,
+            // Note offs.
+            1, 0x81, 60, 0,
+            0, 0x82, 64, 0,
+        ];
+
+        let melody = Melody::from_midi_mpe(
+            midly::EventIter::new(&track),
+            unt::Time::SAMPLE,
+            MpeZone::lower(8),
+            |idx| idx,
+        )
+        .unwrap();
+
+        let exprs: Vec<_> = melody
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                NoteEvent::Expr { key, expr } => Some((*key, *expr)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            exprs,
+            [
+                // Initial expression of each note.
+                (0, ctr::Expr::Bend(0.0)),
+                (0, ctr::Expr::Pressure(0.0)),
+                (0, ctr::Expr::Timbre(0.5)),
+                (1, ctr::Expr::Bend(0.0)),
+                (1, ctr::Expr::Pressure(0.0)),
+                (1, ctr::Expr::Timbre(0.5)),
+                // Member channel events.
+                (0, ctr::Expr::Bend(24.0)),
+                (1, ctr::Expr::Pressure(1.0)),
+                (0, ctr::Expr::Timbre(0.0)),
+                // Master channel pitch bend.
+                (0, ctr::Expr::Bend(25.0)),
+                (1, ctr::Expr::Bend(1.0)),
+            ]
+        );
+    }
+}
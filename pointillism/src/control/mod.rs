@@ -14,18 +14,44 @@
 //! If more granularity is needed, we also offer [`Time`], which allows you to modify a signal on
 //! every single frame, according to the number of elapsed samples.
 //!
+//! For events at arbitrary times, which might be added or cancelled while playing, use a
+//! [`Scheduler`].
+//!
 //! ## Type aliases
 //!
 //! This file also defines various useful type aliases. [`Arpeggio`] serves to arpeggiate a signal
 //! by changing its frequency in periodic intervals. [`MelSeq`] and [`MelLoop`] both functionally
 //! serve as piano rolls for a polyphonic signal.
+//!
+//! ## Arpeggiators
+//!
+//! An [`Arpeggiator`] plays the notes being held on a synth one at a time, with many different
+//! [modes](ArpMode). Its held notes can be given by a [`Melody`] through an [`ArpSeq`].
+//!
+//! ## Monophonic synths
+//!
+//! A [`Legato`] synth plays a single signal from the notes being held, gliding between them. Its
+//! notes can be given by a [`Melody`] through a [`LegatoSeq`].
+//!
+//! ## Expression
+//!
+//! Notes in a polyphonic signal can be bent, pressed, or have their timbre changed individually,
+//! through [`Expr`] events. See the [`Expressive`] type for a signal that responds to these.
 
+mod arp;
+mod expr;
+mod legato;
 mod melody;
+mod scheduler;
 mod timer;
 
-#[cfg(feature = "midly")]
-pub use melody::MidiNoteData;
+pub use arp::{Arp, ArpMode, ArpMono, ArpPoly, ArpReader, ArpSeq, ArpSynth, Arpeggiator, Arpeggio};
+pub use expr::{Expr, ExprReader, ExprSeq, Express, Expression, Expressive};
+pub use legato::{Glide, Legato, LegatoReader, LegatoSeq, Priority};
 pub use melody::{MelLoop, MelSeq, Melody, Note, NoteEvent, NoteReader};
+#[cfg(feature = "midly")]
+pub use melody::{MidiNoteData, MpeZone};
+pub use scheduler::{EventId, Scheduler};
 pub use timer::{Metronome, Timer};
 
 use crate::prelude::*;
@@ -152,6 +178,28 @@ impl<S: SignalMut, F: Mut<S>> Seq<S, F> {
     pub fn total_time(&self) -> unt::Time {
         self.times.iter().copied().sum()
     }
+
+    /// Renders the signal up to the next event, or up to the end of the buffer, and reads the
+    /// events that are due. Returns the number of samples written.
+    #[cfg(feature = "rayon")]
+    fn render_events(&mut self, buf: &mut [S::Sample], workers: &sgn::Workers) -> usize
+    where
+        S: Render,
+    {
+        let mut len = 0;
+        while len < buf.len() {
+            len += 1;
+            self.since.advance();
+
+            if self.current_time().is_some_and(|time| self.since >= time) {
+                break;
+            }
+        }
+
+        self.sgn.render(&mut buf[..len], workers);
+        self.read_events();
+        len
+    }
 }
 
 impl<S: SignalMut, F: Mut<S>> Signal for Seq<S, F> {
@@ -176,6 +224,17 @@ impl<S: SignalMut, F: Mut<S>> SignalMut for Seq<S, F> {
     }
 }
 
+/// Renders the signal in blocks between consecutive events.
+#[cfg(feature = "rayon")]
+impl<S: Render, F: Mut<S>> Render for Seq<S, F> {
+    fn render(&mut self, mut buf: &mut [S::Sample], workers: &sgn::Workers) {
+        while !buf.is_empty() {
+            let len = self.render_events(buf, workers);
+            buf = &mut buf[len..];
+        }
+    }
+}
+
 /// Changes a signal according to a specified function, at specified times. These times are looped.
 ///
 /// Although it is not undefined behavior to initialize an empty loop, doing so will lead to panics
@@ -311,128 +370,18 @@ impl<S: SignalMut, F: Mut<S>> SignalMut for Loop<S, F> {
     }
 }
 
-/// The function that arpeggiates a signal.
-///
-/// This is used to implement [`Arpeggio`].
-#[derive(Clone, Debug)]
-pub struct Arp {
-    /// The notes to play, in order.
-    pub notes: Vec<unt::Freq>,
-
-    /// The index of the note currently playing.
-    pub index: usize,
-}
-
-impl Arp {
-    /// Initializes a new arpeggio with the given notes.
-    #[must_use]
-    pub const fn new(notes: Vec<unt::Freq>) -> Self {
-        Self { notes, index: 0 }
-    }
-
-    /// The currently played note.
-    #[must_use]
-    pub fn current(&self) -> unt::Freq {
-        self.notes[self.index]
-    }
-
-    /// The length of the arpeggio.
-    #[must_use]
-    pub fn len(&self) -> usize {
-        self.notes.len()
-    }
-
-    /// Whether the arpeggio has no notes.
-    ///
-    /// Note that this will generally result in other methods panicking, and thus should be avoided.
-    #[must_use]
-    pub fn is_empty(&self) -> bool {
-        self.notes.is_empty()
-    }
-}
-
-impl<S: Frequency> Mut<S> for Arp {
-    fn modify(&mut self, sgn: &mut S) {
-        *sgn.freq_mut() = self.current();
-        crate::mod_inc(self.len(), &mut self.index);
-    }
-}
+/// Renders the signal in blocks between consecutive events.
+#[cfg(feature = "rayon")]
+impl<S: Render, F: Mut<S>> Render for Loop<S, F> {
+    fn render(&mut self, mut buf: &mut [S::Sample], workers: &sgn::Workers) {
+        while !buf.is_empty() {
+            let len = self.seq.render_events(buf, workers);
+            buf = &mut buf[len..];
 
-/// An arpeggiated signal.
-///
-/// ## Example
-///
-/// We create a single arpeggio which plays two chords.
-///
-/// ```
-/// # use pointillism::prelude::*;
-/// // Basic parameters.
-/// const SAMPLE_RATE: unt::SampleRate = unt::SampleRate::CD;
-/// const NOTE_TIME: unt::RawTime = unt::RawTime::new(3.0 / 32.0);
-/// const LENGTH: unt::RawTime = unt::RawTime::new(3.0);
-///
-/// let note_time = unt::Time::from_raw(NOTE_TIME, SAMPLE_RATE);
-/// let length = unt::Time::from_raw(LENGTH, SAMPLE_RATE);
-///
-/// // The notes played in the arpeggio.
-/// let notes = [unt::RawFreq::C4, unt::RawFreq::E4, unt::RawFreq::G4, unt::RawFreq::A4]
-///     .map(|raw| unt::Freq::from_raw(raw, SAMPLE_RATE))
-///     .to_vec();
-///
-/// // Initializes the arpeggio.
-/// let mut arp = ctr::Arpeggio::new_arp(
-///     vec![note_time],
-///     gen::Loop::<smp::Mono, _>::new(crv::Tri, unt::Freq::ZERO),
-///     notes,
-/// );
-///
-/// // Zero is a dummy value that gets replaced here.
-/// arp.skip();
-///
-/// let mut timer = ctr::Timer::new(length);
-/// Song::new_func(2u8 * length, SAMPLE_RATE, |time| {
-///     // We switch up the arpeggio after the first phrase.
-///     if timer.tick(time) {
-///         arp.notes_mut()[2] = unt::Freq::from_raw(unt::RawFreq::F4, SAMPLE_RATE);
-///     }
-///
-///     arp.next()
-/// })
-/// .export("examples/arpeggio.wav");
-/// ```
-pub type Arpeggio<S> = Loop<S, Arp>;
-
-impl<S: Frequency> Arpeggio<S> {
-    /// Initializes a new [`Arpeggio`].
-    ///
-    /// Note that the note being played by the signal won't be updated until the first time interval
-    /// transpires, unless you call [`Self::skip`].
-    ///
-    /// ## Panics
-    ///
-    /// This method panics if the `times` vector is empty.
-    pub fn new_arp(times: Vec<unt::Time>, sgn: S, notes: Vec<unt::Freq>) -> Self {
-        Self::new(times, sgn, Arp::new(notes))
-    }
-
-    /// Returns a reference to the [`Arp`].
-    pub const fn arp(&self) -> &Arp {
-        self.func()
-    }
-
-    /// Returns a mutable reference to the [`Arp`].
-    pub fn arp_mut(&mut self) -> &mut Arp {
-        self.func_mut()
-    }
-
-    /// Returns a reference to the notes.
-    pub fn notes(&self) -> &[unt::Freq] {
-        &self.arp().notes
-    }
-
-    /// Returns a mutable reference to the notes.
-    pub fn notes_mut(&mut self) -> &mut [unt::Freq] {
-        &mut self.arp_mut().notes
+            if self.seq.index == self.len() {
+                self.seq.index = 0;
+            }
+        }
     }
 }
 
@@ -0,0 +1,258 @@
+//! Implements the [`Scheduler`] type.
+
+use crate::prelude::*;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// An identifier for an event in a [`Scheduler`], which can be used to cancel it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventId(u64);
+
+/// An event in a [`Scheduler`], together with the time it's due.
+#[derive(Clone, Debug)]
+struct Entry<E> {
+    /// The time at which the event fires.
+    time: unt::Time,
+    /// The identifier of the event. Since these are increasing, they also break ties between
+    /// events due at the same time.
+    id: EventId,
+    /// The event itself.
+    event: E,
+}
+
+impl<E> PartialEq for Entry<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<E> Eq for Entry<E> {}
+
+impl<E> PartialOrd for Entry<E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Entries are ordered in reverse, so that the earliest event sits on top of the [`BinaryHeap`].
+impl<E> Ord for Entry<E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.id).cmp(&(self.time, self.id))
+    }
+}
+
+/// Changes a signal according to events scheduled at arbitrary absolute times.
+///
+/// Unlike a [`ctr::Seq`], which reads through a fixed list of intervals, a [`Scheduler`] keeps its
+/// events in a priority queue. Events can be scheduled and cancelled at any point, even while the
+/// signal is playing.
+///
+/// Each event is passed to a [`map::Val`] function, which modifies the signal accordingly. By using
+/// an `enum` for the events, many different kinds of events can be handled by the same scheduler.
+///
+/// ## Timing
+///
+/// Times are measured from the moment the scheduler starts, or from the last time it was
+/// [retriggered](SignalMut::retrigger). An event fires on the first sample whose time is at least
+/// the time it's due, before that sample is output. Events that are due at the same time fire in
+/// the order they were scheduled.
+///
+/// ## Example
+///
+/// We schedule some changes to the frequency and volume of a sine wave.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// type Sine = eff::Volume<gen::Loop<smp::Mono, crv::Sin>>;
+///
+/// // Our two kinds of events.
+/// enum Event {
+///     Freq(unt::RawFreq),
+///     Vol(unt::Vol),
+/// }
+///
+/// let sgn: Sine = eff::Volume::new(
+///     gen::Loop::new(crv::Sin, unt::Freq::from_raw_default(unt::RawFreq::A4)),
+///     unt::Vol::HALF,
+/// );
+/// let mut sched = ctr::Scheduler::new(
+///     sgn,
+///     map::Func::new(|sgn: &mut Sine, event: Event| match event {
+///         Event::Freq(raw) => *sgn.sgn_mut().freq_mut() = unt::Freq::from_raw_default(raw),
+///         Event::Vol(vol) => *sgn.vol_mut() = vol,
+///     }),
+/// );
+///
+/// let sec = unt::Time::from_sec_default;
+/// sched.schedule(sec(1.0), Event::Freq(unt::RawFreq::E5));
+/// sched.schedule(sec(2.0), Event::Vol(unt::Vol::ZERO));
+///
+/// // Changed our mind!
+/// let id = sched.schedule(sec(1.5), Event::Freq(unt::RawFreq::C5));
+/// assert!(sched.cancel(id));
+///
+/// Song::new(sec(3.0), unt::SampleRate::default(), sched).export("examples/scheduler.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scheduler<S: SignalMut, E, F: Val<S, Val = E>> {
+    /// The events yet to fire.
+    events: BinaryHeap<Entry<E>>,
+    /// The identifier for the next scheduled event.
+    next_id: u64,
+    /// Time elapsed.
+    time: unt::Time,
+
+    /// The signal being modified.
+    sgn: S,
+    /// The function modifying the signal.
+    func: F,
+}
+
+impl<S: SignalMut, E, F: Val<S, Val = E>> Scheduler<S, E, F> {
+    /// Initializes a new scheduler with no events.
+    pub fn new(sgn: S, func: F) -> Self {
+        Self {
+            events: BinaryHeap::new(),
+            next_id: 0,
+            time: unt::Time::ZERO,
+            sgn,
+            func,
+        }
+    }
+
+    /// Time elapsed.
+    pub const fn time(&self) -> unt::Time {
+        self.time
+    }
+
+    /// The number of events yet to fire.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether there are no events yet to fire.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The time at which the next event is due, if any.
+    pub fn next_time(&self) -> Option<unt::Time> {
+        self.events.peek().map(|entry| entry.time)
+    }
+
+    /// Returns a reference to the modified signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the modified signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the function modifying the signal.
+    pub const fn func(&self) -> &F {
+        &self.func
+    }
+
+    /// Returns a mutable reference to the function modifying the signal.
+    pub fn func_mut(&mut self) -> &mut F {
+        &mut self.func
+    }
+
+    /// Schedules an event at a given time, and returns an identifier that can be used to cancel
+    /// it.
+    ///
+    /// If the event is already due, it fires immediately.
+    pub fn schedule(&mut self, time: unt::Time, event: E) -> EventId {
+        let id = EventId(self.next_id);
+        self.next_id += 1;
+        self.events.push(Entry { time, id, event });
+        self.fire_due();
+        id
+    }
+
+    /// Schedules an event some time after the current time, and returns an identifier that can be
+    /// used to cancel it.
+    pub fn schedule_in(&mut self, delay: unt::Time, event: E) -> EventId {
+        self.schedule(self.time + delay, event)
+    }
+
+    /// Cancels an event, returns whether it was still pending.
+    ///
+    /// This takes linear time in the number of pending events.
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        let len = self.len();
+        self.events.retain(|entry| entry.id != id);
+        self.len() != len
+    }
+
+    /// Cancels all pending events.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Fires all events that are due.
+    fn fire_due(&mut self) {
+        while self.next_time().is_some_and(|time| time <= self.time) {
+            let entry = self.events.pop().expect("the heap can't be empty");
+            self.func.modify_val(&mut self.sgn, entry.event);
+        }
+    }
+}
+
+impl<S: SignalMut, E, F: Val<S, Val = E>> Signal for Scheduler<S, E, F> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.sgn.get()
+    }
+}
+
+impl<S: SignalMut, E, F: Val<S, Val = E>> SignalMut for Scheduler<S, E, F> {
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.time.advance();
+        self.fire_due();
+    }
+
+    /// Retriggers the signal and resets the time to zero. Pending events are kept, and are now due
+    /// relative to the new start.
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.time = unt::Time::ZERO;
+        self.fire_due();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that events fire on the exact sample they're due, in order.
+    #[test]
+    fn order() {
+        let mut sched = Scheduler::new(
+            gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::ZERO),
+            map::Func::new(|sgn: &mut gen::Loop<smp::Mono, crv::Sin>, freq: f64| {
+                *sgn.freq_mut() = unt::Freq::new(freq);
+            }),
+        );
+
+        let at = unt::Time::from_samples;
+        sched.schedule(at(3), 3.0);
+        sched.schedule(at(1), 1.0);
+        sched.schedule(at(3), 4.0);
+        let id = sched.schedule(at(2), 2.0);
+        assert!(sched.cancel(id));
+
+        let freqs: Vec<_> = (0..4)
+            .map(|_| {
+                let freq = sched.sgn().freq().samples;
+                sched.advance();
+                freq
+            })
+            .collect();
+        assert_eq!(freqs, [0.0, 1.0, 1.0, 4.0]);
+        assert!(sched.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Implements the [`ChainDelay`], whose feedback path runs through an arbitrary effect chain.
+
+use super::Line;
+use crate::prelude::*;
+
+/// A delay whose feedback is processed through an arbitrary chain of effects.
+///
+/// Whereas the feedback in a [`Delay`](super::Delay) is a [`Map`] on individual samples, here it's
+/// any signal with a [`Band`](eff::flt::Band) as its [`Base`]. Each frame, the delayed sample is
+/// written into this base, the chain is advanced, and its output is fed back into the delay line.
+/// This allows for filters and saturation in the loop, which is how tape and analog delays darken
+/// and degrade each successive repeat.
+///
+/// As with [`Delay`](super::Delay), only the delayed signal is output. The delay time can be changed
+/// at any point, and is read through [Hermite](buf::int::Hermite) interpolation. To sync it to a
+/// tempo, see [`unt::Time::from_beats`].
+///
+/// ## Example
+///
+/// We play a tape delay on some plucks, synced to a dotted eighth at 120 BPM. Each repeat is
+/// low-passed and saturated.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3));
+/// let pluck = ctr::Loop::new(
+///     vec![sec(1.0)],
+///     eff::env::ArEnv::new_ar(saw, eff::env::Ar::new(sec(0.005), sec(0.1))),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let time = unt::Time::from_beats_default(0.75, 120.0);
+/// let chain = eff::PwMapSgn::atan(
+///     eff::flt::LoFiltered::new_coefs(
+///         eff::flt::Band::new(),
+///         eff::flt::Biquad::low_pass(
+///             unt::Freq::from_hz_default(2000.0),
+///             unt::QFactor::BUTTERWORTH,
+///         ),
+///     ),
+///     1.5,
+/// );
+/// let mut delay = eff::dly::ChainDelay::new(pluck, time, time, chain);
+/// delay.feedback = unt::Vol::new(0.6);
+/// Song::new(sec(3.0), unt::SampleRate::default(), delay).export("examples/tape.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChainDelay<S: Signal, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>>
+where
+    S::Sample: Audio,
+{
+    /// The delayed signal.
+    sgn: S,
+    /// The effect chain in the feedback path.
+    chain: F,
+    /// The delay time.
+    ///
+    /// The delay time is clamped to what the line can hold, see [`Self::max`].
+    pub time: unt::Time,
+    /// The volume with which the output of the chain is fed back into the line.
+    pub feedback: unt::Vol,
+    /// The delay line.
+    line: Line<S::Sample, buf::int::Hermite<S::Sample>>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new delay, given the delay time, the longest delay time that will be needed,
+    /// and the effect chain in the feedback path.
+    ///
+    /// The feedback is set to -6 dB.
+    pub fn new(sgn: S, time: unt::Time, max: unt::Time, chain: F) -> Self {
+        let mut res = Self {
+            sgn,
+            chain,
+            time,
+            feedback: unt::Vol::MDB6,
+            line: Line::new(max),
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the delayed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the delayed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the effect chain in the feedback path.
+    pub const fn chain(&self) -> &F {
+        &self.chain
+    }
+
+    /// Returns a mutable reference to the effect chain in the feedback path.
+    pub fn chain_mut(&mut self) -> &mut F {
+        &mut self.chain
+    }
+
+    /// The longest delay time.
+    pub fn max(&self) -> unt::Time {
+        self.line.max()
+    }
+
+    /// Clears the delay line, and retriggers the effect chain.
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.chain.base_mut().0 = S::Sample::ZERO;
+        self.chain.retrigger();
+    }
+
+    /// Reads the current sample from the signal, runs the feedback through the chain, and computes
+    /// the output.
+    fn process(&mut self) {
+        let delayed = self.line.read(self.time);
+        self.chain.base_mut().0 = delayed;
+        self.chain.advance();
+
+        self.line
+            .push(self.sgn.get() + self.chain.get() * self.feedback.gain);
+        self.output = delayed;
+    }
+}
+
+impl<S: Signal, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Signal
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> SignalMut
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Frequency
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Base
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the delay tail.
+impl<S: Done, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Done
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Stop
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, F: Base<Sample = S::Sample, Base = eff::flt::Band<S::Sample>>> Panic
+    for ChainDelay<S, F>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the repeats go through the chain.
+    #[test]
+    fn repeats() {
+        let impulse = crate::test_util::impulse(0, smp::Mono(1.0));
+
+        // Every repeat is inverted and halved.
+        let chain = eff::Volume::new(eff::flt::Band::new(), unt::Vol::new(-1.0));
+        let time = unt::Time::from_samples(4);
+        let mut delay = ChainDelay::new(impulse, time, time, chain);
+        delay.feedback = unt::Vol::HALF;
+
+        let out: Vec<_> = (0..16).map(|_| delay.next().0).collect();
+        for (time, sample) in out.into_iter().enumerate() {
+            let expected = match time {
+                4 => 1.0,
+                8 => -0.5,
+                12 => 0.25,
+                _ => 0.0,
+            };
+            assert_approx_eq::assert_approx_eq!(sample, expected);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Implements delay lines whose delay time can be changed continuously.
+
+use std::marker::PhantomData;
+
+use crate::prelude::*;
+use buf::int::Interpolate;
+
+/// A delay line which can be read at any fractional time, through some
+/// [interpolation](Interpolate).
+///
+/// Samples are [pushed](Self::push) into the line once per frame, and can be
+/// [read](Self::read) at any time between [`Self::min`] and [`Self::max`] ago.
+#[derive(Clone, Debug)]
+pub struct Line<A: Audio, I: Interpolate>
+where
+    I::Buf: Buffer<Item = A>,
+{
+    /// The buffer holding the last few samples.
+    buffer: buf::Circ<buf::Dyn<A>>,
+    /// Dummy value.
+    phantom: PhantomData<I>,
+}
+
+impl<A: Audio, I: Interpolate> Line<A, I>
+where
+    I::Buf: Buffer<Item = A>,
+{
+    /// Initializes a new delay line, which can be read up to the given time ago.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn new(max: unt::Time) -> Self {
+        let max = (max.samples.into_f64().ceil() as usize).max(I::LOOK_AHEAD as usize);
+        Self {
+            buffer: buf::Circ::new(buf::Dyn::new(max + I::SIZE - I::LOOK_AHEAD as usize)),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The shortest time that can be read.
+    ///
+    /// The interpolation needs [`Interpolate::LOOK_AHEAD`] samples after the one being read, so
+    /// this is one sample for linear interpolation, and two for cubic or Hermite interpolation.
+    #[must_use]
+    pub const fn min(&self) -> unt::Time {
+        unt::Time::from_samples(I::LOOK_AHEAD as u64)
+    }
+
+    /// The longest time that can be read.
+    #[must_use]
+    pub fn max(&self) -> unt::Time {
+        unt::Time::from_samples((self.buffer.capacity() - I::SIZE + I::LOOK_AHEAD as usize) as u64)
+    }
+
+    /// Reads the line at the given time ago. A time of one sample returns the last sample pushed.
+    ///
+    /// The time is clamped between [`Self::min`] and [`Self::max`].
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn read(&self, time: unt::Time) -> A {
+        let time = time
+            .samples
+            .into_f64()
+            .clamp(self.min().samples.into_f64(), self.max().samples.into_f64());
+        let int = time.floor();
+        let fract = time - int;
+        let int = int as usize;
+
+        // We interpolate from the sample `int + 1` samples ago to the one `int` samples ago. The
+        // sample at index `cur` of the interpolation buffer is the first of these.
+        let cur = I::SIZE - 1 - I::LOOK_AHEAD as usize;
+        let mut inter = I::EMPTY;
+        for index in (0..I::SIZE).rev() {
+            inter.push(self.buffer.get(int + cur - index));
+        }
+
+        inter.eval(unt::Val::new(1.0 - fract))
+    }
+
+    /// Pushes a new sample into the line.
+    pub fn push(&mut self, sample: A) {
+        self.buffer.push(sample);
+    }
+
+    /// Clears the line.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+/// Plays a signal back with a delay that can change over time, and some optional feedback.
+///
+/// The delay time is a base [`unt::Time`], multiplied by the output of an envelope. This is read
+/// from a [`Line`] through some [interpolation](Interpolate), so that the time can change smoothly.
+/// As with [`Delay`](super::Delay), only the delayed signal is output.
+///
+/// ## Instanciation
+///
+/// If you call [`Self::new`], you'll have to write down the interpolation mode explicitly. Consider
+/// instead calling one of [`Self::new_linear`], [`Self::new_cubic`], or [`Self::new_hermite`].
+///
+/// ## Example
+///
+/// We play a tape-like delay, whose time slowly wobbles.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3));
+/// let pluck = ctr::Loop::new(
+///     vec![sec(0.6)],
+///     eff::env::ArEnv::new_ar(saw, eff::env::Ar::new(sec(0.005), sec(0.2))),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// // The delay time varies between 0.2 and 0.3 seconds.
+/// let wobble = gen::Loop::new(
+///     map::Comp::new(crv::Sin, map::Linear::rescale(-1.0, 1.0, 2.0 / 3.0, 1.0)),
+///     unt::Freq::from_hz_default(0.5),
+/// );
+/// let mut delay = eff::dly::HermiteDelay::new_hermite(
+///     pluck,
+///     wobble,
+///     sec(0.3),
+///     sec(0.3),
+/// );
+/// delay.feedback = unt::Vol::HALF;
+/// Song::new(sec(3.0), unt::SampleRate::default(), delay).export("examples/var_delay.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct VarDelay<S: Signal, E: SignalMut<Sample = smp::Env>, I: Interpolate>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    /// The delayed signal.
+    sgn: S,
+    /// The envelope multiplying the delay time.
+    env: E,
+    /// The base delay time.
+    ///
+    /// The delay time is clamped to what the line can hold, see [`Self::max`].
+    pub time: unt::Time,
+    /// The volume with which the delayed signal is fed back into the line.
+    pub feedback: unt::Vol,
+    /// The delay line.
+    line: Line<S::Sample, I>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal, E: SignalMut<Sample = smp::Env>, I: Interpolate> VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    /// Initializes a new variable delay, given the envelope and base time which determine the delay
+    /// time, and the longest delay time that will be needed.
+    pub fn new(sgn: S, env: E, time: unt::Time, max: unt::Time) -> Self {
+        let mut res = Self {
+            sgn,
+            env,
+            time,
+            feedback: unt::Vol::ZERO,
+            line: Line::new(max),
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the delayed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the delayed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the envelope multiplying the delay time.
+    pub const fn env(&self) -> &E {
+        &self.env
+    }
+
+    /// Returns a mutable reference to the envelope multiplying the delay time.
+    pub fn env_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    /// The longest delay time.
+    pub fn max(&self) -> unt::Time {
+        self.line.max()
+    }
+
+    /// Clears the delay line.
+    pub fn clear(&mut self) {
+        self.line.clear();
+    }
+
+    /// Reads the current sample from the signal and the envelope, and computes the output.
+    fn process(&mut self) {
+        self.output = self.line.read(self.time * self.env.get().0.max(0.0));
+        self.line
+            .push(self.sgn.get() + self.output * self.feedback.gain);
+    }
+}
+
+/// A [`VarDelay`] using [linear](buf::int::Linear) interpolation.
+pub type LinearDelay<S, E> = VarDelay<S, E, buf::int::Linear<<S as Signal>::Sample>>;
+
+impl<S: Signal, E: SignalMut<Sample = smp::Env>> LinearDelay<S, E>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new [`LinearDelay`].
+    pub fn new_linear(sgn: S, env: E, time: unt::Time, max: unt::Time) -> Self {
+        Self::new(sgn, env, time, max)
+    }
+}
+
+/// A [`VarDelay`] using [cubic](buf::int::Cubic) interpolation.
+pub type CubicDelay<S, E> = VarDelay<S, E, buf::int::Cubic<<S as Signal>::Sample>>;
+
+impl<S: Signal, E: SignalMut<Sample = smp::Env>> CubicDelay<S, E>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new [`CubicDelay`].
+    pub fn new_cubic(sgn: S, env: E, time: unt::Time, max: unt::Time) -> Self {
+        Self::new(sgn, env, time, max)
+    }
+}
+
+/// A [`VarDelay`] using [Hermite](buf::int::Hermite) interpolation.
+pub type HermiteDelay<S, E> = VarDelay<S, E, buf::int::Hermite<<S as Signal>::Sample>>;
+
+impl<S: Signal, E: SignalMut<Sample = smp::Env>> HermiteDelay<S, E>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new [`HermiteDelay`].
+    pub fn new_hermite(sgn: S, env: E, time: unt::Time, max: unt::Time) -> Self {
+        Self::new(sgn, env, time, max)
+    }
+}
+
+impl<S: Signal, E: SignalMut<Sample = smp::Env>, I: Interpolate> Signal for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut, E: SignalMut<Sample = smp::Env>, I: Interpolate> SignalMut for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.env.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.env.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency, E: SignalMut<Sample = smp::Env>, I: Interpolate> Frequency for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, E: SignalMut<Sample = smp::Env>, I: Interpolate> Base for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the delay tail.
+impl<S: Done, E: SignalMut<Sample = smp::Env>, I: Interpolate> Done for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, E: SignalMut<Sample = smp::Env>, I: Interpolate> Stop for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, E: SignalMut<Sample = smp::Env>, I: Interpolate> Panic for VarDelay<S, E, I>
+where
+    S::Sample: Audio,
+    I::Buf: Buffer<Item = S::Sample>,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that fractional delays are exact on a ramp, for every interpolation mode.
+    #[test]
+    fn ramp() {
+        /// Delays a ramp by some number of samples.
+        fn delay<I: Interpolate>(samples: f64) -> impl Iterator<Item = f64>
+        where
+            I::Buf: Buffer<Item = smp::Mono>,
+        {
+            let mut time = 0.0;
+            let ramp = gen::Func::new(move || {
+                time += 1.0;
+                smp::Mono(time - 1.0)
+            });
+            let env = gen::Loop::<smp::Env, _>::new(map::Const::new(1.0), unt::Freq::default());
+            let time = unt::Time::new(unt::FracInt::from_f64(samples));
+            let mut sgn = VarDelay::<_, _, I>::new(ramp, env, time, time);
+            (0..20).map(move |_| sgn.next().0)
+        }
+
+        // A fraction of one half would give the same result if it were flipped.
+        for samples in [2.25, 2.5, 2.75] {
+            let linear = delay::<buf::int::Linear<_>>(samples);
+            let cubic = delay::<buf::int::Cubic<_>>(samples);
+            let hermite = delay::<buf::int::Hermite<_>>(samples);
+            for (time, ((x, y), z)) in linear.zip(cubic).zip(hermite).enumerate().skip(4) {
+                #[allow(clippy::cast_precision_loss)]
+                let expected = time as f64 - samples;
+                assert_approx_eq::assert_approx_eq!(x, expected);
+                assert_approx_eq::assert_approx_eq!(y, expected);
+                assert_approx_eq::assert_approx_eq!(z, expected);
+            }
+        }
+    }
+}
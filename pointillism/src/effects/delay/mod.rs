@@ -1,4 +1,22 @@
-//! TODO: missing docs
+//! Implements delay effects.
+//!
+//! The basic [`Delay`] plays back a signal from a buffer whose length is the delay time. For delay
+//! times that can change over time, see the [`Line`] and [`VarDelay`] types. The [`ModDelay`] effect
+//! builds chorus and flanger effects on these.
+//!
+//! The [`ChainDelay`] runs its feedback through an arbitrary chain of effects, such as filters or
+//! distortion. The [`MultiTap`] delay plays a signal back several times, each with its own
+//! [`Tap`].
+
+mod chain;
+mod line;
+mod modulation;
+mod multitap;
+
+pub use chain::ChainDelay;
+pub use line::{CubicDelay, HermiteDelay, Line, LinearDelay, VarDelay};
+pub use modulation::ModDelay;
+pub use multitap::{MultiTap, Tap};
 
 use crate::prelude::*;
 
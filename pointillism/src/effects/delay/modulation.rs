@@ -0,0 +1,266 @@
+//! Implements the [`ModDelay`] effect, used for chorus and flanger effects.
+
+use super::Line;
+use crate::prelude::*;
+
+/// A short delay whose time is modulated by a sine LFO, and mixed back with the original signal.
+///
+/// The delay time swings between `center - depth` and `center + depth` according to an
+/// [`eff::Lfo`], and is read from a [`Line`] through [Hermite](buf::int::Hermite) interpolation. In
+/// a stereo signal, the LFO on the right channel is ahead of the one on the left by some phase
+/// offset, which widens the sound.
+///
+/// Depending on the parameters, this can be used for two classic effects:
+///
+/// - A chorus, see [`Self::new_chorus`], uses delays of a few tens of milliseconds, and little to
+///   no feedback. This makes the signal sound like many voices playing together.
+/// - A flanger, see [`Self::new_flanger`], uses delays of a few milliseconds, and some feedback.
+///   This creates a comb filter whose notches sweep up and down.
+///
+/// ## Example
+///
+/// We apply a stereo chorus to a sustained chord.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = |raw| gen::Loop::<smp::Stereo, _>::new(crv::Saw, unt::Freq::from_raw_default(raw));
+/// let chord = rtn::Mix::new(saw(unt::RawFreq::C3), saw(unt::RawFreq::G3));
+/// let sgn = eff::dly::ModDelay::new_chorus(eff::Volume::new(chord, unt::Vol::HALF));
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/chorus.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct ModDelay<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The modulated signal.
+    sgn: S,
+    /// The LFO which modulates the delay time.
+    pub lfo: eff::Lfo,
+    /// The average delay time.
+    ///
+    /// The delay time is clamped to what the line can hold, see [`Self::max`].
+    pub center: unt::Time,
+    /// The largest deviation from the average delay time.
+    pub depth: unt::Time,
+    /// The volume with which the delayed signal is fed back into the line.
+    ///
+    /// A negative gain inverts the signal fed back, which changes the position of the notches in a
+    /// flanger.
+    pub feedback: unt::Vol,
+    /// The volume of the delayed signal.
+    pub wet: unt::Vol,
+    /// The volume of the original signal.
+    pub dry: unt::Vol,
+
+    /// The delay line.
+    line: Line<S::Sample, buf::int::Hermite<S::Sample>>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal> ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new modulated delay, given its average time, depth, and LFO rate.
+    ///
+    /// The original and delayed signals are mixed in equal proportions at half volume, there's no
+    /// feedback, and no phase offset between channels.
+    pub fn new(sgn: S, center: unt::Time, depth: unt::Time, rate: unt::Freq) -> Self {
+        let mut res = Self {
+            sgn,
+            lfo: eff::Lfo::new(rate, unt::Val::ZERO),
+            center,
+            depth,
+            feedback: unt::Vol::ZERO,
+            wet: unt::Vol::HALF,
+            dry: unt::Vol::HALF,
+            line: Line::new(center + depth),
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Initializes a chorus, with a delay of 15 to 25 ms, an LFO at 0.8 Hz, and a quarter period
+    /// of phase offset between channels.
+    pub fn new_chorus(sgn: S) -> Self {
+        let mut res = Self::new(
+            sgn,
+            unt::Time::from_msec_default(20.0),
+            unt::Time::from_msec_default(5.0),
+            unt::Freq::from_hz_default(0.8),
+        );
+        res.lfo.offset = unt::Val::new(0.25);
+        res
+    }
+
+    /// Initializes a flanger, with a delay of 1 to 5 ms, an LFO at 0.25 Hz, and -6 dB of feedback.
+    pub fn new_flanger(sgn: S) -> Self {
+        let mut res = Self::new(
+            sgn,
+            unt::Time::from_msec_default(3.0),
+            unt::Time::from_msec_default(2.0),
+            unt::Freq::from_hz_default(0.25),
+        );
+        res.feedback = unt::Vol::MDB6;
+        res
+    }
+
+    /// Returns a reference to the modulated signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the modulated signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The longest delay time.
+    pub fn max(&self) -> unt::Time {
+        self.line.max()
+    }
+
+    /// Clears the delay line.
+    pub fn clear(&mut self) {
+        self.line.clear();
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        let center = self.center.samples.into_f64();
+        let depth = self.depth.samples.into_f64();
+        let lfo = self.lfo.get();
+        let delayed = S::Sample::from_fn(|channel| {
+            let time = center + depth * (2.0 * lfo[channel] - 1.0);
+            let time = unt::Time::new(unt::FracInt::from_f64(time.max(0.0)));
+            self.line.read(time)[channel]
+        });
+
+        let sample = self.sgn.get();
+        self.line.push(sample + delayed * self.feedback.gain);
+        self.output = sample * self.dry.gain + delayed * self.wet.gain;
+    }
+}
+
+impl<S: Signal> Signal for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.lfo.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.lfo.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the delay tail.
+impl<S: Done> Done for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for ModDelay<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the phase offset delays each channel differently.
+    #[test]
+    fn offset() {
+        let impulse = crate::test_util::impulse(1, smp::Mono(1.0).duplicate());
+
+        // The impulse starts on the second sample. The left channel is delayed by 14 samples, the
+        // right one by 6.
+        let mut sgn = ModDelay::new(
+            impulse,
+            unt::Time::from_samples(10),
+            unt::Time::from_samples(4),
+            unt::Freq::ZERO,
+        );
+        sgn.lfo = eff::Lfo::new_phase(unt::Freq::ZERO, unt::Val::HALF, unt::Val::new(0.25));
+        sgn.wet = unt::Vol::FULL;
+        sgn.dry = unt::Vol::ZERO;
+
+        let out: Vec<_> = (0..20).map(|_| sgn.next()).collect();
+        for (time, sample) in out.iter().enumerate() {
+            let left = if time == 15 { 1.0 } else { 0.0 };
+            let right = if time == 7 { 1.0 } else { 0.0 };
+            assert_approx_eq::assert_approx_eq!(sample[0], left);
+            assert_approx_eq::assert_approx_eq!(sample[1], right);
+        }
+    }
+}
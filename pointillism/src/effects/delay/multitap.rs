@@ -0,0 +1,261 @@
+//! Implements the [`MultiTap`] delay.
+
+use super::Line;
+use crate::prelude::*;
+
+/// A single tap of a [`MultiTap`] delay.
+#[derive(Clone, Copy, Debug)]
+pub struct Tap {
+    /// The delay time of the tap.
+    pub time: unt::Time,
+    /// The volume of the tap.
+    pub gain: unt::Vol,
+    /// The panning angle of the tap, as in [`eff::pan::Law::angle`].
+    ///
+    /// Hard left is `0.0`, center is `0.5`, hard right is `1.0`.
+    pub pan: f64,
+}
+
+impl Tap {
+    /// Initializes a new tap.
+    #[must_use]
+    pub const fn new(time: unt::Time, gain: unt::Vol, pan: f64) -> Self {
+        Self { time, gain, pan }
+    }
+}
+
+/// A delay which plays a signal back several times, each with its own time, volume, and panning.
+///
+/// The taps are read from a single [`Line`] through [Hermite](buf::int::Hermite) interpolation, and
+/// panned through the [power](eff::pan::Power) law. A mono signal is panned into stereo, whereas
+/// the channels of a stereo signal are each scaled by the corresponding gain. As with
+/// [`Delay`](super::Delay), only the delayed signal is output.
+///
+/// To sync the taps to a tempo, see [`unt::Time::from_beats`].
+///
+/// ## Example
+///
+/// We play three taps on some plucks, synced to eighth notes at 100 BPM, bouncing between the left
+/// and right.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3));
+/// let pluck = ctr::Loop::new(
+///     vec![sec(1.2)],
+///     eff::env::ArEnv::new_ar(saw, eff::env::Ar::new(sec(0.005), sec(0.1))),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let beats = |beats| unt::Time::from_beats_default(beats, 100.0);
+/// let delay = eff::dly::MultiTap::new(
+///     pluck,
+///     vec![
+///         eff::dly::Tap::new(beats(0.5), unt::Vol::FULL, 0.5),
+///         eff::dly::Tap::new(beats(1.0), unt::Vol::MDB6, 0.0),
+///         eff::dly::Tap::new(beats(1.5), unt::Vol::MDB10, 1.0),
+///     ],
+/// );
+/// Song::new(sec(3.0), unt::SampleRate::default(), delay).export("examples/multitap.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiTap<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The delayed signal.
+    sgn: S,
+    /// The taps of the delay.
+    taps: Vec<Tap>,
+    /// The delay line.
+    line: Line<S::Sample, buf::int::Hermite<S::Sample>>,
+    /// The current output.
+    output: smp::Stereo,
+}
+
+impl<S: Signal> MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new multi-tap delay.
+    ///
+    /// The delay line is made long enough to hold the longest tap.
+    pub fn new(sgn: S, taps: Vec<Tap>) -> Self {
+        let max = taps
+            .iter()
+            .map(|tap| tap.time)
+            .max()
+            .unwrap_or(unt::Time::ZERO);
+
+        let mut res = Self {
+            sgn,
+            taps,
+            line: Line::new(max),
+            output: smp::Stereo::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the delayed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the delayed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the taps.
+    pub fn taps(&self) -> &[Tap] {
+        &self.taps
+    }
+
+    /// Returns a mutable reference to the taps.
+    ///
+    /// The delay times are clamped to what the line can hold, see [`Self::max`].
+    pub fn taps_mut(&mut self) -> &mut [Tap] {
+        &mut self.taps
+    }
+
+    /// The longest delay time.
+    pub fn max(&self) -> unt::Time {
+        self.line.max()
+    }
+
+    /// Clears the delay line.
+    pub fn clear(&mut self) {
+        self.line.clear();
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        self.output = self
+            .taps
+            .iter()
+            .map(|tap| {
+                let sample = self.line.read(tap.time) * tap.gain.gain;
+                let (left, right) = eff::pan::power_gain(tap.pan);
+                smp::Stereo::new(sample[0] * left, sample[S::Sample::SIZE - 1] * right)
+            })
+            .sum();
+
+        self.line.push(self.sgn.get());
+    }
+}
+
+impl<S: Signal> Signal for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = smp::Stereo;
+
+    fn get(&self) -> smp::Stereo {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the delay tail.
+impl<S: Done> Done for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for MultiTap<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = smp::Stereo::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that each tap is delayed and panned.
+    #[test]
+    fn taps() {
+        let mut delay = MultiTap::new(
+            crate::test_util::impulse(0, smp::Mono(1.0)),
+            vec![
+                Tap::new(unt::Time::from_samples(3), unt::Vol::FULL, 0.0),
+                Tap::new(unt::Time::from_samples(5), unt::Vol::HALF, 1.0),
+            ],
+        );
+
+        let out: Vec<_> = (0..8).map(|_| delay.next()).collect();
+        for (time, sample) in out.into_iter().enumerate() {
+            let (left, right) = match time {
+                3 => (1.0, 0.0),
+                5 => (0.0, 0.5),
+                _ => (0.0, 0.0),
+            };
+            assert_approx_eq::assert_approx_eq!(sample[0], left);
+            assert_approx_eq::assert_approx_eq!(sample[1], right);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Implements the [`Comp`] processor and the [`Compressor`] effect.
+
+use super::{Ballistics, Detection, Dynamic, Dynamics, Level};
+use crate::prelude::*;
+
+/// A feed-forward compressor, which reduces the gain of a signal once its level goes over a
+/// threshold.
+///
+/// The level of the key is measured according to the [`Detection`] mode, and smoothed according to
+/// the [`Ballistics`]. The gain reduction is then computed from this level in decibels, through the
+/// usual static curve with a soft knee.
+///
+/// If [linked](Self::link), both channels of a stereo key are measured separately, but the same
+/// gain is applied to both, according to the loudest one. This keeps the stereo image from shifting
+/// around.
+#[derive(Clone, Debug)]
+pub struct Comp {
+    /// The level above which the signal is compressed.
+    pub threshold: unt::Vol,
+    /// The compression ratio. For instance, a ratio of `4.0` means that every 4 dB over the
+    /// threshold in the input result in 1 dB over the threshold in the output.
+    pub ratio: f64,
+    /// The width of the knee in decibels, centered at the threshold. Within it, the ratio
+    /// gradually increases from `1.0`.
+    pub knee: f64,
+    /// The gain applied after compression.
+    pub makeup: unt::Vol,
+    /// How the level of the key is measured.
+    pub detection: Detection,
+    /// How fast the level of the key rises and falls.
+    pub ballistics: Ballistics,
+    /// Whether both channels receive the same gain.
+    pub link: bool,
+
+    /// The level detectors for each channel.
+    levels: [Level; 2],
+    /// The current gain for each channel.
+    gains: [f64; 2],
+}
+
+impl Comp {
+    /// Initializes a new compressor with a hard knee, no makeup gain, peak detection, and linked
+    /// channels.
+    #[must_use]
+    pub const fn new(threshold: unt::Vol, ratio: f64, ballistics: Ballistics) -> Self {
+        Self {
+            threshold,
+            ratio,
+            knee: 0.0,
+            makeup: unt::Vol::FULL,
+            detection: Detection::Peak,
+            ballistics,
+            link: true,
+            levels: [Level::new(); 2],
+            gains: [1.0; 2],
+        }
+    }
+
+    /// The gain change in decibels for a given level in decibels. This is never positive.
+    #[must_use]
+    pub fn curve(&self, level: f64) -> f64 {
+        let threshold = self.threshold.db();
+        let over = level - threshold;
+        let slope = 1.0 / self.ratio - 1.0;
+
+        if self.knee > 0.0 && 2.0 * over.abs() <= self.knee {
+            let over = over + self.knee / 2.0;
+            slope * over * over / (2.0 * self.knee)
+        } else if over > 0.0 {
+            slope * over
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Dynamics for Comp {
+    fn process<A: Audio>(&mut self, key: A) {
+        let mut levels = [0.0; 2];
+        A::for_each(|channel| {
+            levels[channel] =
+                self.levels[channel].process(key[channel], self.detection, self.ballistics);
+        });
+
+        if A::SIZE == 1 || self.link {
+            let max = levels.iter().copied().fold(0.0, f64::max);
+            levels = [max; 2];
+        }
+
+        self.gains =
+            levels.map(|level| unt::Vol::from_db(self.curve(unt::Vol::new(level).db())).gain);
+    }
+
+    fn gain(&self, channel: usize) -> unt::Vol {
+        unt::Vol::new(self.gains[channel])
+    }
+
+    fn makeup(&self) -> unt::Vol {
+        self.makeup
+    }
+
+    fn reset(&mut self) {
+        self.levels = [Level::new(); 2];
+        self.gains = [1.0; 2];
+    }
+}
+
+/// Compresses a signal, using its own level as the key.
+///
+/// See [`Comp`] for more information.
+///
+/// ## Example
+///
+/// We compress a plucked saw wave, so that it rings out for longer.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let pluck = eff::env::ArEnv::new_ar(
+///     gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3)),
+///     eff::env::Ar::new(sec(0.01), sec(2.0)),
+/// );
+///
+/// let mut comp = eff::dnm::Comp::new(
+///     unt::Vol::from_db(-18.0),
+///     4.0,
+///     eff::dnm::Ballistics::new(sec(0.005), sec(0.1)),
+/// );
+/// comp.knee = 6.0;
+/// comp.makeup = unt::Vol::DB6;
+///
+/// let sgn = eff::dnm::Compressor::new(pluck, comp);
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/compressor.wav");
+/// ```
+pub type Compressor<S> = Dynamic<S, Comp>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test the static curve of the compressor.
+    #[test]
+    fn curve() {
+        let mut comp = Comp::new(unt::Vol::from_db(-20.0), 4.0, Ballistics::INSTANT);
+        assert_approx_eq::assert_approx_eq!(comp.curve(-30.0), 0.0);
+        assert_approx_eq::assert_approx_eq!(comp.curve(-12.0), -6.0);
+
+        // A soft knee meets the hard knee curve at its edges.
+        comp.knee = 10.0;
+        assert_approx_eq::assert_approx_eq!(comp.curve(-25.0), 0.0);
+        assert_approx_eq::assert_approx_eq!(comp.curve(-15.0), -3.75);
+        assert!(comp.curve(-20.0) < 0.0);
+    }
+
+    /// Test that a linked compressor applies the same gain to both channels.
+    #[test]
+    fn link() {
+        let mut comp = Comp::new(unt::Vol::HALF, 2.0, Ballistics::INSTANT);
+        comp.process(smp::Stereo(1.0, 0.1));
+        assert_approx_eq::assert_approx_eq!(comp.gain(0).gain, comp.gain(1).gain);
+        // The signal is 6 dB over the threshold, so it's compressed by 3 dB.
+        assert_approx_eq::assert_approx_eq!(comp.gain(0).db(), unt::Vol::HALF.db() / 2.0);
+
+        comp.link = false;
+        comp.process(smp::Stereo(1.0, 0.1));
+        assert_approx_eq::assert_approx_eq!(comp.gain(1).gain, 1.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! Implements the [`Expander`] processor and the [`NoiseGate`] effect.
+
+use super::{Ballistics, Detection, Dynamic, Dynamics, Level, Sidechain};
+use crate::prelude::*;
+
+/// A noise gate or downward expander, which reduces the gain of a signal once its level falls
+/// under a threshold.
+///
+/// The gate opens once the level of the key reaches the [`open`](Self::open) threshold, and closes
+/// once it has stayed under the [`close`](Self::close) threshold for longer than the
+/// [`hold`](Self::hold) time. Setting the closing threshold a bit lower than the opening one
+/// prevents the gate from chattering when the level hovers around it.
+///
+/// While closed, every decibel under the opening threshold results in `ratio` decibels under it in
+/// the output, down to the [`range`](Self::range). An infinite ratio makes for a gate, while a
+/// finite one makes for a gentler downward expander.
+///
+/// The level of the key is measured according to the [`Detection`] mode. It rises instantly, and
+/// falls according to the release time. The gain then moves towards its target according to the
+/// [`Ballistics`]. Both channels always receive the same gain, according to the loudest one.
+#[derive(Clone, Debug)]
+pub struct Expander {
+    /// The level at which the gate opens.
+    pub open: unt::Vol,
+    /// The level under which the gate closes. This should be at most the opening threshold.
+    pub close: unt::Vol,
+    /// The lowest gain applied to the signal. Use [`unt::Vol::ZERO`] to mute it entirely.
+    pub range: unt::Vol,
+    /// The expansion ratio. For instance, a ratio of `2.0` means that every 1 dB under the
+    /// threshold in the input results in 2 dB under the threshold in the output.
+    pub ratio: f64,
+    /// How long the level must stay under the closing threshold for the gate to close.
+    pub hold: unt::Time,
+    /// How fast the gate opens and closes.
+    pub ballistics: Ballistics,
+    /// How the level of the key is measured.
+    pub detection: Detection,
+
+    /// The level detectors for each channel.
+    levels: [Level; 2],
+    /// Whether the gate is currently open.
+    is_open: bool,
+    /// The number of samples the level has stayed under the closing threshold.
+    held: u64,
+    /// The current gain.
+    gain: f64,
+}
+
+impl Expander {
+    /// Initializes a new noise gate with the given thresholds, which fully mutes the signal when
+    /// closed. The gate has no hold time and uses peak detection.
+    #[must_use]
+    pub const fn new(open: unt::Vol, close: unt::Vol, ballistics: Ballistics) -> Self {
+        Self {
+            open,
+            close,
+            range: unt::Vol::ZERO,
+            ratio: f64::INFINITY,
+            hold: unt::Time::ZERO,
+            ballistics,
+            detection: Detection::Peak,
+            levels: [Level::new(); 2],
+            is_open: false,
+            held: 0,
+            gain: 1.0,
+        }
+    }
+
+    /// Initializes a new downward expander with a single threshold and the given ratio.
+    #[must_use]
+    pub const fn new_expander(threshold: unt::Vol, ratio: f64, ballistics: Ballistics) -> Self {
+        let mut expander = Self::new(threshold, threshold, ballistics);
+        expander.ratio = ratio;
+        expander
+    }
+
+    /// Whether the gate is currently open.
+    #[must_use]
+    pub const fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// The gain change in decibels while the gate is closed, for a given level in decibels. This
+    /// is never positive.
+    #[must_use]
+    pub fn curve(&self, level: f64) -> f64 {
+        let under = level - self.open.db();
+        if self.ratio > 1.0 && under < 0.0 {
+            ((self.ratio - 1.0) * under).max(self.range.db())
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Dynamics for Expander {
+    fn process<A: Audio>(&mut self, key: A) {
+        let detector = Ballistics::new(unt::Time::ZERO, self.ballistics.release);
+        let mut level = 0.0;
+        A::for_each(|channel| {
+            level = f64::max(
+                level,
+                self.levels[channel].process(key[channel], self.detection, detector),
+            );
+        });
+
+        if level >= self.open.gain {
+            self.is_open = true;
+            self.held = 0;
+        } else if level < self.close.gain {
+            if self.is_open {
+                self.held += 1;
+                if self.held > self.hold.samples.int() {
+                    self.is_open = false;
+                }
+            }
+        } else {
+            self.held = 0;
+        }
+
+        let target = if self.is_open {
+            1.0
+        } else {
+            unt::Vol::from_db(self.curve(unt::Vol::new(level).db())).gain
+        };
+        self.gain = self.ballistics.smooth(self.gain, target);
+    }
+
+    fn gain(&self, _: usize) -> unt::Vol {
+        unt::Vol::new(self.gain)
+    }
+
+    fn reset(&mut self) {
+        self.levels = [Level::new(); 2];
+        self.is_open = false;
+        self.held = 0;
+        self.gain = 1.0;
+    }
+}
+
+/// Gates a signal, using its own level as the key.
+///
+/// See [`Expander`] for more information. Use [`Dynamic::new_lookahead`] so that the gate opens
+/// before a transient, instead of cutting into it.
+///
+/// ## Example
+///
+/// We gate a noisy pluck, so that the noise isn't heard once the pluck has faded.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let pluck = ctr::Loop::new(
+///     vec![sec(0.5)],
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3)),
+///         eff::env::Ar::new(sec(0.005), sec(0.3)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+/// let noise = eff::Volume::new(gen::NoiseGen::<smp::Mono>::new(), unt::Vol::from_db(-40.0));
+///
+/// let mut gate = eff::dnm::Expander::new(
+///     unt::Vol::from_db(-20.0),
+///     unt::Vol::from_db(-26.0),
+///     eff::dnm::Ballistics::new(sec(0.001), sec(0.05)),
+/// );
+/// gate.hold = sec(0.02);
+///
+/// let sgn = eff::dnm::NoiseGate::new_lookahead(
+///     rtn::Mix::new(pluck, noise),
+///     gate,
+///     unt::Time::from_msec_default(2.0),
+/// );
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/gate.wav");
+/// ```
+pub type NoiseGate<S> = Dynamic<S, Expander>;
+
+/// Gates a signal, using another signal as the key.
+///
+/// See [`Expander`] and [`Sidechain`] for more information.
+pub type SideGate<S, K> = Sidechain<S, K, Expander>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test the hysteresis and hold time of the gate.
+    #[test]
+    fn hold() {
+        let mut gate = Expander::new(unt::Vol::HALF, unt::Vol::new(0.25), Ballistics::INSTANT);
+        gate.hold = unt::Time::from_samples(2);
+
+        gate.process(smp::Mono(0.4));
+        assert!(!gate.is_open());
+        gate.process(smp::Mono(0.6));
+        assert!(gate.is_open());
+
+        // Between both thresholds, the gate stays open.
+        gate.process(smp::Mono(0.4));
+        assert!(gate.is_open());
+
+        // The gate only closes after the hold time.
+        for _ in 0..2 {
+            gate.process(smp::Mono(0.1));
+            assert!(gate.is_open());
+        }
+        gate.process(smp::Mono(0.1));
+        assert!(!gate.is_open());
+        assert_approx_eq::assert_approx_eq!(gate.gain(0).gain, 0.0);
+    }
+
+    /// Test the curve of a downward expander.
+    #[test]
+    fn curve() {
+        let mut expander =
+            Expander::new_expander(unt::Vol::from_db(-20.0), 2.0, Ballistics::INSTANT);
+        expander.range = unt::Vol::from_db(-12.0);
+        assert_approx_eq::assert_approx_eq!(expander.curve(-10.0), 0.0);
+        assert_approx_eq::assert_approx_eq!(expander.curve(-25.0), -5.0);
+        assert_approx_eq::assert_approx_eq!(expander.curve(-60.0), -12.0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Implements the [`Follower`] type, which turns the level of a signal into an envelope.
+
+use super::{Ballistics, Detection, Level};
+use crate::prelude::*;
+
+/// An envelope follower, which outputs the level of an audio signal as an [`smp::Env`].
+///
+/// The level is measured according to the [`Detection`] mode, and smoothed according to the
+/// [`Ballistics`]. In the case of stereo audio, the loudest channel is used.
+///
+/// The resulting envelope can control any other signal, for instance through an [`eff::Tremolo`],
+/// an [`eff::Gate`], or an [`eff::MutSgn`] modulating the frequency of a filter.
+///
+/// ## Example
+///
+/// We make a pad pulse along with a drum loop, without having to hear the drums.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let pad = gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::C3));
+///
+/// // A drum hit every quarter of a second.
+/// let drums = ctr::Loop::new(
+///     vec![sec(0.25)],
+///     eff::env::ArEnv::new_ar(
+///         gen::NoiseGen::<smp::Mono>::new(),
+///         eff::env::Ar::new(sec(0.001), sec(0.1)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let follower = eff::dnm::Follower::new(
+///     drums,
+///     eff::dnm::Detection::Peak,
+///     eff::dnm::Ballistics::new(sec(0.005), sec(0.15)),
+/// );
+/// let sgn = eff::Tremolo::new(pad, follower);
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/follower.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Follower<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The signal whose level is measured.
+    sgn: S,
+    /// How the level is measured.
+    pub detection: Detection,
+    /// How fast the level rises and falls.
+    pub ballistics: Ballistics,
+
+    /// The level detectors for each channel.
+    levels: [Level; 2],
+}
+
+impl<S: Signal> Follower<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new envelope follower.
+    pub fn new(sgn: S, detection: Detection, ballistics: Ballistics) -> Self {
+        let mut follower = Self {
+            sgn,
+            detection,
+            ballistics,
+            levels: [Level::new(); 2],
+        };
+
+        follower.process();
+        follower
+    }
+
+    /// Returns a reference to the signal whose level is measured.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the signal whose level is measured.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Reads the current sample of the signal, and updates the level.
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        S::Sample::for_each(|channel| {
+            self.levels[channel].process(sample[channel], self.detection, self.ballistics);
+        });
+    }
+}
+
+impl<S: Signal> Signal for Follower<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = smp::Env;
+
+    fn get(&self) -> smp::Env {
+        let mut level = 0.0;
+        S::Sample::for_each(|channel| level = f64::max(level, self.levels[channel].level()));
+        smp::Env(level)
+    }
+}
+
+impl<S: SignalMut> SignalMut for Follower<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.levels = [Level::new(); 2];
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for Follower<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for Follower<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: Done> Done for Follower<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for Follower<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for Follower<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.levels = [Level::new(); 2];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test the level of a sine wave in both detection modes.
+    #[test]
+    fn sine() {
+        let sine = || gen::Loop::<smp::Stereo, _>::new(crv::Sin, unt::Freq::new(0.01));
+        let slow = Ballistics::new(unt::Time::from_samples(1000), unt::Time::from_samples(1000));
+
+        let mut peak = Follower::new(sine(), Detection::Peak, Ballistics::INSTANT);
+        let mut rms = Follower::new(sine(), Detection::Rms(unt::Time::from_samples(500)), slow);
+        for _ in 0..20_000 {
+            peak.advance();
+            rms.advance();
+        }
+
+        assert!(peak.get().0 <= 1.0);
+        assert_approx_eq::assert_approx_eq!(rms.get().0, std::f64::consts::FRAC_1_SQRT_2, 0.01);
+    }
+}
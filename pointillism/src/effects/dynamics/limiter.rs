@@ -0,0 +1,334 @@
+//! Implements the [`Limiter`] effect.
+
+use crate::prelude::*;
+use std::collections::VecDeque;
+
+/// A lookahead brickwall limiter, which guarantees that no sample goes over a ceiling.
+///
+/// The signal is delayed by the lookahead time, which is reported as the [latency](Self::latency).
+/// This gives the limiter time to smoothly bring the gain down before a peak arrives, instead of
+/// abruptly clipping it. Once the peak has passed, the gain recovers according to the release
+/// time.
+///
+/// Peaks are measured on the samples themselves, as well as on an estimate of the peaks in between
+/// them, obtained through [Hermite interpolation](buf::int::hermite). This catches most of the
+/// inter-sample peaks that would otherwise go over the ceiling after digital-to-analog conversion.
+/// Regardless of this, output samples are always clamped to the ceiling, as a last line of
+/// defense.
+///
+/// In the case of stereo audio, both channels always receive the same gain.
+///
+/// ## Example
+///
+/// We limit a loud sawtooth chord before exporting it.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = |raw| gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(raw));
+/// let chord = rtn::Mix::new(
+///     rtn::Mix::new(saw(unt::RawFreq::A2), saw(unt::RawFreq::E3)),
+///     saw(unt::RawFreq::A3),
+/// );
+///
+/// let sgn = eff::dnm::Limiter::new(
+///     chord,
+///     unt::Vol::from_db(-1.0),
+///     unt::Time::from_msec_default(5.0),
+///     sec(0.1),
+/// );
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/limiter.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Limiter<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The limited signal.
+    sgn: S,
+    /// The maximum absolute value for any output sample.
+    pub ceiling: unt::Vol,
+    /// How long it takes for the gain to recover after a peak.
+    pub release: unt::Time,
+
+    /// The lookahead time in samples.
+    lookahead: usize,
+    /// Delays the signal by the lookahead time.
+    delay: buf::Circ<buf::Dyn<S::Sample>>,
+    /// Keeps track of the lowest gain needed over the lookahead time, together with the sample at
+    /// which it's needed. The gains in this queue are increasing.
+    mins: VecDeque<(u64, f64)>,
+    /// The last few minimum gains, which are averaged to get a smooth gain curve.
+    window: Vec<f64>,
+    /// The position in the window in which the next gain is written.
+    pos: usize,
+    /// The sum of the values in the window.
+    sum: f64,
+    /// The gain currently applied.
+    gain: f64,
+    /// The number of samples processed.
+    time: u64,
+}
+
+impl<S: Signal> Limiter<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new limiter.
+    ///
+    /// The lookahead time is rounded down to a whole number of samples, and is at least one
+    /// sample.
+    ///
+    /// ## Panics
+    ///
+    /// On a 32-bit machine, panics if the lookahead time is too large.
+    pub fn new(sgn: S, ceiling: unt::Vol, lookahead: unt::Time, release: unt::Time) -> Self {
+        let lookahead: usize = lookahead
+            .samples
+            .int()
+            .max(1)
+            .try_into()
+            .expect("lookahead too large");
+
+        let mut limiter = Self {
+            sgn,
+            ceiling,
+            release,
+            lookahead,
+            // We need at least four samples for interpolation.
+            delay: buf::Circ::new(buf::Dyn::new((lookahead + 1).max(4))),
+            mins: VecDeque::with_capacity(lookahead + 1),
+            window: vec![1.0; lookahead],
+            pos: 0,
+            sum: 0.0,
+            gain: 1.0,
+            time: 0,
+        };
+
+        limiter.reset();
+        limiter
+    }
+
+    /// Returns a reference to the limited signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the limited signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The time by which the signal is delayed.
+    pub const fn latency(&self) -> unt::Time {
+        unt::Time::from_samples(self.lookahead as u64)
+    }
+
+    /// The gain currently applied to the signal.
+    pub const fn gain(&self) -> unt::Vol {
+        unt::Vol::new(self.gain)
+    }
+
+    /// Estimates the peak of the last sample, and of the space between the two samples before it.
+    fn peak(&self) -> f64 {
+        let (x0, x1, x2, x3) = (
+            self.delay.get(3),
+            self.delay.get(2),
+            self.delay.get(1),
+            self.delay.get(0),
+        );
+
+        let mut peak = 0.0;
+        for t in [0.25, 0.5, 0.75] {
+            let sample = buf::int::hermite(x0, x1, x2, x3, unt::Val::new(t));
+            S::Sample::for_each(|channel| peak = f64::max(peak, sample[channel].abs()));
+        }
+        S::Sample::for_each(|channel| peak = f64::max(peak, x3[channel].abs()));
+
+        peak
+    }
+
+    /// Reads a new sample from the signal, and updates the gain.
+    fn process(&mut self) {
+        self.delay.push(self.sgn.get());
+        let peak = self.peak();
+        let target = if peak > self.ceiling.gain {
+            self.ceiling.gain / peak
+        } else {
+            1.0
+        };
+
+        // The lowest gain needed by the samples still in the delay line.
+        while self.mins.back().is_some_and(|&(_, gain)| gain >= target) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((self.time, target));
+        while self
+            .mins
+            .front()
+            .is_some_and(|&(time, _)| time + (self.lookahead as u64) < self.time)
+        {
+            self.mins.pop_front();
+        }
+        let min = self.mins.front().expect("the queue can't be empty").1;
+
+        // Every minimum in the window is at most the gain needed by the sample about to be output,
+        // and thus so is their average.
+        self.sum += min - self.window[self.pos];
+        self.window[self.pos] = min;
+        crate::mod_inc(self.lookahead, &mut self.pos);
+        #[allow(clippy::cast_precision_loss)]
+        let avg = self.sum / self.lookahead as f64;
+
+        self.gain = if avg < self.gain {
+            avg
+        } else {
+            let coef = super::coef(self.release);
+            avg + coef * (self.gain - avg)
+        };
+        self.time += 1;
+    }
+
+    /// Resets the state of the limiter, and reads the current sample from the signal.
+    fn reset(&mut self) {
+        self.delay.clear();
+        self.mins.clear();
+        self.window.fill(1.0);
+        self.pos = 0;
+        #[allow(clippy::cast_precision_loss)]
+        let sum = self.lookahead as f64;
+        self.sum = sum;
+        self.gain = 1.0;
+        self.time = 0;
+        self.process();
+    }
+}
+
+impl<S: Signal> Signal for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        let ceiling = self.ceiling.gain;
+        (self.delay.get(self.lookahead) * self.gain).map(|x| x.clamp(-ceiling, ceiling))
+    }
+}
+
+impl<S: SignalMut> SignalMut for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.reset();
+    }
+}
+
+impl<S: Frequency> Frequency for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the latency of the limiter.
+impl<S: Done> Done for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for Limiter<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the gain keeps loud noise under the ceiling, even before the output is clamped.
+    #[test]
+    fn ceiling() {
+        let noise = gen::Func::new(|| smp::Stereo::rand() * 3.0);
+        let mut limiter = Limiter::new(
+            noise,
+            unt::Vol::HALF,
+            unt::Time::from_samples(8),
+            unt::Time::from_samples(100),
+        );
+
+        for _ in 0..10_000 {
+            limiter.advance();
+            let sample = limiter.delay.get(limiter.lookahead) * limiter.gain;
+            assert!(sample.0.abs() <= 0.5 + 1e-9 && sample.1.abs() <= 0.5 + 1e-9);
+        }
+    }
+
+    /// Test that the signal is delayed by the lookahead time, and that the gain comes down before a
+    /// peak arrives.
+    #[test]
+    fn lookahead() {
+        let sgn = rtn::Mix::new(
+            crate::test_util::impulse(9, smp::Mono(1.9)),
+            gen::Func::new(|| smp::Mono(0.1)),
+        );
+        let mut limiter = Limiter::new(
+            sgn,
+            unt::Vol::FULL,
+            unt::Time::from_samples(4),
+            unt::Time::from_samples(100),
+        );
+
+        let out: Vec<_> = (0..20).map(|_| limiter.next().0).collect();
+        assert_approx_eq::assert_approx_eq!(out[0], 0.0);
+        assert_approx_eq::assert_approx_eq!(out[4], 0.1);
+        assert_approx_eq::assert_approx_eq!(out[13], 1.0);
+        assert!(out[12] < 0.1);
+    }
+}
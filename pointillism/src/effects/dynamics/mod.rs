@@ -0,0 +1,398 @@
+//! Effects that change the volume of a signal according to its level, such as compressors.
+//!
+//! A dynamics effect is split into two parts. A [`Dynamics`] processor reads the level of a key
+//! signal, and decides on the gain to apply to each channel. A [`Dynamic`] signal then applies this
+//! gain to the signal it wraps, using the signal itself as the key. A [`Sidechain`] instead reads
+//! the level of a separate key signal, which is used for effects like ducking. Both can optionally
+//! look ahead, delaying the signal so that the processor reacts before a change in level is heard.
+//!
+//! The available processors are the [`Comp`] compressor, the [`Expander`], which works both as a
+//! noise gate and a downward expander, and the [`Transient`] designer.
+//!
+//! The level of the key is measured by a [`Level`] detector, according to its [`Detection`] mode
+//! and [`Ballistics`]. A [`Follower`] outputs this level as an envelope, so that it can control
+//! other signals.
+//!
+//! The [`Limiter`] works differently, as it delays the signal in order to react to peaks before
+//! they happen.
+
+mod compressor;
+mod expander;
+mod follower;
+mod limiter;
+mod sidechain;
+mod transient;
+
+pub use compressor::{Comp, Compressor};
+pub use expander::{Expander, NoiseGate, SideGate};
+pub use follower::Follower;
+pub use limiter::Limiter;
+pub use sidechain::{SideCompressor, Sidechain};
+pub use transient::{Transient, TransientShaper};
+
+use crate::prelude::*;
+
+/// Converts a time into a coefficient for a one-pole smoothing filter, which makes the output
+/// reach about 63% of a step in the given time.
+fn coef(time: unt::Time) -> f64 {
+    let samples = time.samples.into_f64();
+    if samples > 0.0 {
+        (-1.0 / samples).exp()
+    } else {
+        0.0
+    }
+}
+
+/// How quickly the level measured by a detector rises and falls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ballistics {
+    /// How long it takes for the level to rise.
+    pub attack: unt::Time,
+    /// How long it takes for the level to fall.
+    pub release: unt::Time,
+}
+
+impl Ballistics {
+    /// Levels change instantly.
+    pub const INSTANT: Self = Self::new(unt::Time::ZERO, unt::Time::ZERO);
+
+    /// Initializes new [`Ballistics`].
+    #[must_use]
+    pub const fn new(attack: unt::Time, release: unt::Time) -> Self {
+        Self { attack, release }
+    }
+
+    /// Moves a value towards a target, using the attack time if it rises, and the release time
+    /// otherwise.
+    #[must_use]
+    pub fn smooth(&self, current: f64, target: f64) -> f64 {
+        let coef = coef(if target > current {
+            self.attack
+        } else {
+            self.release
+        });
+        target + coef * (current - target)
+    }
+}
+
+/// How the level of a signal is measured.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Detection {
+    /// The absolute value of each sample.
+    #[default]
+    Peak,
+    /// The root mean square of the samples, averaged over the given time.
+    Rms(unt::Time),
+}
+
+/// The state of a level detector for a single channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Level {
+    /// The averaged square of the signal, used in RMS detection.
+    square: f64,
+    /// The current level.
+    level: f64,
+}
+
+impl Level {
+    /// Initializes a new detector with a level of zero.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            square: 0.0,
+            level: 0.0,
+        }
+    }
+
+    /// The current level, as a linear gain.
+    #[must_use]
+    pub const fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Reads a new value, and returns the updated level.
+    pub fn process(&mut self, value: f64, detection: Detection, ballistics: Ballistics) -> f64 {
+        let target = match detection {
+            Detection::Peak => value.abs(),
+            Detection::Rms(window) => {
+                let coef = coef(window);
+                self.square = value * value + coef * (self.square - value * value);
+                self.square.sqrt()
+            }
+        };
+
+        self.level = ballistics.smooth(self.level, target);
+        self.level
+    }
+
+    /// Resets the level to zero.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// A processor that reads the level of a key signal, and determines the gain to apply to each
+/// channel of another.
+///
+/// Processors keep the state of both channels of stereo audio. A mono key sets the gain of both
+/// channels, so that it can control a stereo signal.
+pub trait Dynamics {
+    /// Reads a new sample from the key, and updates the gains.
+    fn process<A: Audio>(&mut self, key: A);
+
+    /// The gain currently applied to a given channel, not counting [`Self::makeup`].
+    fn gain(&self, channel: usize) -> unt::Vol;
+
+    /// A constant gain applied after processing.
+    fn makeup(&self) -> unt::Vol {
+        unt::Vol::FULL
+    }
+
+    /// Resets the state of the processor.
+    fn reset(&mut self);
+
+    /// The lowest gain currently applied among both channels, not counting [`Self::makeup`].
+    ///
+    /// A gain of `1.0` means the signal isn't being reduced.
+    fn min_gain(&self) -> smp::Env {
+        smp::Env(self.gain(0).gain.min(self.gain(1).gain))
+    }
+
+    /// Applies the current gains to a sample.
+    fn apply<A: Audio>(&self, sample: A) -> A {
+        let makeup = self.makeup().gain;
+        A::from_fn(|channel| sample[channel] * self.gain(channel).gain * makeup)
+    }
+}
+
+/// Initializes the delay line for a dynamics effect with a given lookahead time.
+///
+/// ## Panics
+///
+/// On a 32-bit machine, panics if the lookahead time is too large.
+fn delay<A: Audio>(lookahead: unt::Time) -> buf::Circ<buf::Dyn<A>> {
+    let lookahead: usize = lookahead
+        .samples
+        .int()
+        .try_into()
+        .expect("lookahead too large");
+    buf::Circ::new(buf::Dyn::new(lookahead + 1))
+}
+
+/// The lookahead time of a delay line built by [`delay`].
+fn latency<A: Audio>(delay: &buf::Circ<buf::Dyn<A>>) -> unt::Time {
+    unt::Time::from_samples(delay.capacity() as u64 - 1)
+}
+
+/// Applies a [`Dynamics`] processor to a signal, using the signal itself as the key.
+///
+/// The processor can optionally look ahead, meaning that the processed signal is delayed with
+/// respect to the key. This lets the processor react to changes in level before they're heard.
+///
+/// The current gain of the processor can be read through [`Self::reduction`], or as a signal
+/// through a [`Meter`].
+#[derive(Clone, Debug)]
+pub struct Dynamic<S: Signal, D: Dynamics>
+where
+    S::Sample: Audio,
+{
+    /// The processed signal.
+    sgn: S,
+    /// The dynamics processor.
+    dynamics: D,
+    /// Delays the processed signal by the lookahead time.
+    delay: buf::Circ<buf::Dyn<S::Sample>>,
+}
+
+impl<S: Signal, D: Dynamics> Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    /// Applies a dynamics processor to a signal.
+    pub fn new(sgn: S, dynamics: D) -> Self {
+        Self::new_lookahead(sgn, dynamics, unt::Time::ZERO)
+    }
+
+    /// Applies a dynamics processor to a signal, with a given lookahead time. This is rounded
+    /// down to a whole number of samples.
+    ///
+    /// ## Panics
+    ///
+    /// On a 32-bit machine, panics if the lookahead time is too large.
+    pub fn new_lookahead(sgn: S, dynamics: D, lookahead: unt::Time) -> Self {
+        let mut res = Self {
+            sgn,
+            dynamics,
+            delay: delay(lookahead),
+        };
+
+        res.process();
+        res
+    }
+
+    /// The time by which the signal is delayed.
+    pub fn latency(&self) -> unt::Time {
+        latency(&self.delay)
+    }
+
+    /// Reads the current sample from the signal.
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        self.delay.push(sample);
+        self.dynamics.process(sample);
+    }
+
+    /// Resets the state of the effect, and reads the current sample from the signal.
+    fn reset(&mut self) {
+        self.delay.clear();
+        self.dynamics.reset();
+        self.process();
+    }
+
+    /// Returns a reference to the processed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the processed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the dynamics processor.
+    pub const fn dynamics(&self) -> &D {
+        &self.dynamics
+    }
+
+    /// Returns a mutable reference to the dynamics processor.
+    pub fn dynamics_mut(&mut self) -> &mut D {
+        &mut self.dynamics
+    }
+
+    /// The gain currently applied to the signal, not counting makeup gain. In the case of stereo
+    /// audio, this is the lowest gain among both channels.
+    ///
+    /// See [`Dynamics::min_gain`].
+    pub fn reduction(&self) -> smp::Env {
+        self.dynamics.min_gain()
+    }
+}
+
+impl<S: Signal, D: Dynamics> Signal for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.dynamics
+            .apply(self.delay.get(self.delay.capacity() - 1))
+    }
+}
+
+impl<S: SignalMut, D: Dynamics> SignalMut for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.reset();
+    }
+}
+
+impl<S: Frequency, D: Dynamics> Frequency for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, D: Dynamics> Base for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: Done, D: Dynamics> Done for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, D: Dynamics> Stop for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, D: Dynamics> Panic for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.reset();
+    }
+}
+
+/// An effect whose gain reduction can be read as an envelope, through a [`Meter`].
+pub trait Reduction {
+    /// The gain currently applied to the signal, not counting makeup gain. In the case of stereo
+    /// audio, this is the lowest gain among both channels.
+    fn reduction(&self) -> smp::Env;
+}
+
+impl<S: Signal, D: Dynamics> Reduction for Dynamic<S, D>
+where
+    S::Sample: Audio,
+{
+    fn reduction(&self) -> smp::Env {
+        self.dynamics.min_gain()
+    }
+}
+
+/// Reads the gain applied by a [`Dynamic`] or [`Sidechain`] signal as an envelope, for metering.
+///
+/// Like [`rtn::Ref`], this doesn't implement [`SignalMut`], and must be rebuilt for every sample.
+pub struct Meter<'a, R: Reduction>(pub &'a R);
+
+impl<'a, R: Reduction> Meter<'a, R> {
+    /// Initializes a new [`Meter`].
+    pub const fn new(sgn: &'a R) -> Self {
+        Self(sgn)
+    }
+}
+
+impl<R: Reduction> Signal for Meter<'_, R> {
+    type Sample = smp::Env;
+
+    fn get(&self) -> smp::Env {
+        self.0.reduction()
+    }
+}
@@ -0,0 +1,297 @@
+//! Implements the [`Sidechain`] type, for dynamics effects keyed by another signal.
+
+use super::{Comp, Dynamics, Reduction};
+use crate::prelude::*;
+
+/// Applies a [`Dynamics`] processor to a signal, using a separate key signal to measure the level.
+///
+/// This is analogous to how [`eff::Gate`] reads a separate envelope. The key can be any audio
+/// signal, mono or stereo. A mono key controls both channels of a stereo signal.
+///
+/// The key is often filtered, so that the processor doesn't react to its low end. See
+/// [`Self::new_hi_pass`] for a convenient way to do this.
+///
+/// As with a [`Dynamic`](super::Dynamic) signal, the processor can optionally look ahead, meaning
+/// that the processed signal is delayed with respect to the key.
+///
+/// ## Example
+///
+/// We duck a sustained pad with a kick drum, which is played separately.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+///
+/// // A chord made of three saw waves.
+/// let pad = rtn::Mix::new(
+///     rtn::Mix::new(
+///         gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::C3)),
+///         gen::Loop::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::E3)),
+///     ),
+///     gen::Loop::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::G3)),
+/// );
+///
+/// // A kick drum on every beat.
+/// let kick = ctr::Loop::new(
+///     vec![sec(0.5)],
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_hz_default(55.0)),
+///         eff::env::Ar::new(sec(0.005), sec(0.2)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let comp = eff::dnm::Comp::new(
+///     unt::Vol::from_db(-30.0),
+///     10.0,
+///     eff::dnm::Ballistics::new(sec(0.001), sec(0.25)),
+/// );
+/// let sgn = eff::dnm::Sidechain::new(eff::Volume::new(pad, unt::Vol::MDB10), kick, comp);
+/// Song::new(sec(3.0), unt::SampleRate::default(), sgn).export("examples/sidechain.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sidechain<S: Signal, K: Signal, D: Dynamics>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    /// The processed signal.
+    sgn: S,
+    /// The signal whose level is measured.
+    key: K,
+    /// The dynamics processor.
+    dynamics: D,
+    /// Delays the processed signal by the lookahead time.
+    delay: buf::Circ<buf::Dyn<S::Sample>>,
+}
+
+impl<S: Signal, K: Signal, D: Dynamics> Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    /// Applies a dynamics processor to a signal, keyed by another.
+    pub fn new(sgn: S, key: K, dynamics: D) -> Self {
+        Self::new_lookahead(sgn, key, dynamics, unt::Time::ZERO)
+    }
+
+    /// Applies a dynamics processor to a signal, keyed by another, with a given lookahead time.
+    /// This is rounded down to a whole number of samples.
+    ///
+    /// ## Panics
+    ///
+    /// On a 32-bit machine, panics if the lookahead time is too large.
+    pub fn new_lookahead(sgn: S, key: K, dynamics: D, lookahead: unt::Time) -> Self {
+        let mut res = Self {
+            sgn,
+            key,
+            dynamics,
+            delay: super::delay(lookahead),
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the processed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the processed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the key signal.
+    pub const fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a mutable reference to the key signal.
+    pub fn key_mut(&mut self) -> &mut K {
+        &mut self.key
+    }
+
+    /// Returns a reference to the dynamics processor.
+    pub const fn dynamics(&self) -> &D {
+        &self.dynamics
+    }
+
+    /// Returns a mutable reference to the dynamics processor.
+    pub fn dynamics_mut(&mut self) -> &mut D {
+        &mut self.dynamics
+    }
+
+    /// The gain currently applied to the signal, not counting makeup gain. In the case of stereo
+    /// audio, this is the lowest gain among both channels.
+    ///
+    /// See [`Dynamics::min_gain`].
+    pub fn reduction(&self) -> smp::Env {
+        self.dynamics.min_gain()
+    }
+
+    /// The time by which the processed signal is delayed.
+    pub fn latency(&self) -> unt::Time {
+        super::latency(&self.delay)
+    }
+
+    /// Reads the current samples from the signal and the key.
+    fn process(&mut self) {
+        self.delay.push(self.sgn.get());
+        self.dynamics.process(self.key.get());
+    }
+
+    /// Resets the state of the effect, and reads the current samples from the signal and the key.
+    fn reset(&mut self) {
+        self.delay.clear();
+        self.dynamics.reset();
+        self.process();
+    }
+}
+
+impl<S: Signal, K: Signal, D: Dynamics> Sidechain<S, eff::flt::LoFiltered<K, 3, 2>, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    /// Applies a dynamics processor to a signal, keyed by another which is first filtered through
+    /// a [hi-pass](eff::flt::Biquad::hi_pass).
+    pub fn new_hi_pass(sgn: S, key: K, freq: unt::Freq, q: unt::QFactor, dynamics: D) -> Self {
+        Self::new(
+            sgn,
+            eff::flt::LoFiltered::new_coefs(key, eff::flt::Biquad::hi_pass(freq, q)),
+            dynamics,
+        )
+    }
+}
+
+impl<S: Signal, K: Signal, D: Dynamics> Signal for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.dynamics
+            .apply(self.delay.get(self.delay.capacity() - 1))
+    }
+}
+
+impl<S: SignalMut, K: SignalMut, D: Dynamics> SignalMut for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.key.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.key.retrigger();
+        self.reset();
+    }
+}
+
+impl<S: Frequency, K: SignalMut, D: Dynamics> Frequency for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, K: SignalMut, D: Dynamics> Base for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: Done, K: Signal, D: Dynamics> Done for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, K: SignalMut, D: Dynamics> Stop for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, K: SignalMut, D: Dynamics> Panic for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.reset();
+    }
+}
+
+/// Compresses a signal, using another signal as the key. This is often used for ducking.
+///
+/// See [`Comp`] and [`Sidechain`] for more information.
+pub type SideCompressor<S, K> = Sidechain<S, K, Comp>;
+
+impl<S: Signal, K: Signal, D: Dynamics> Reduction for Sidechain<S, K, D>
+where
+    S::Sample: Audio,
+    K::Sample: Audio,
+{
+    fn reduction(&self) -> smp::Env {
+        self.dynamics.min_gain()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that a mono key ducks both channels of a stereo signal.
+    #[test]
+    fn duck() {
+        let mut sgn = Sidechain::new(
+            gen::Func::new(|| smp::Stereo(0.2, 0.4)),
+            gen::Func::new(|| smp::Mono(1.0)),
+            Comp::new(unt::Vol::HALF, f64::INFINITY, eff::dnm::Ballistics::INSTANT),
+        );
+        sgn.advance();
+
+        // An infinite ratio brings the key down to the threshold.
+        let sample = sgn.get();
+        assert_approx_eq::assert_approx_eq!(sample.0, 0.1);
+        assert_approx_eq::assert_approx_eq!(sample.1, 0.2);
+        assert_approx_eq::assert_approx_eq!(sgn.reduction().0, 0.5);
+        assert_approx_eq::assert_approx_eq!(eff::dnm::Meter::new(&sgn).get().0, 0.5);
+    }
+}
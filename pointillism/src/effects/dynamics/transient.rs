@@ -0,0 +1,172 @@
+//! Implements the [`Transient`] processor and the [`TransientShaper`] effect.
+
+use super::{Ballistics, Detection, Dynamic, Dynamics, Level};
+use crate::prelude::*;
+
+/// A transient designer, which boosts or cuts the attack and sustain of a signal independently,
+/// regardless of its level.
+///
+/// Three envelopes of the key are measured in each channel:
+///
+/// - A fast envelope, following the [`fast`](Self::fast) ballistics.
+/// - An envelope with the slow attack and the fast release, which lags behind the fast envelope
+///   whenever the signal rises. The gap between both is the attack.
+/// - An envelope with the fast attack and the slow release, which lags behind the fast envelope
+///   whenever the signal falls. The gap between both is the sustain.
+///
+/// Each gap is normalized between `0.0` and `1.0`, and scaled by the corresponding amount in
+/// decibels. The resulting gain changes are added together.
+///
+/// If [linked](Self::link), both channels of a stereo key receive the same gain, according to the
+/// loudest envelopes.
+#[derive(Clone, Debug)]
+pub struct Transient {
+    /// The gain change in decibels applied to the attack. Positive values make it punchier.
+    pub attack: f64,
+    /// The gain change in decibels applied to the sustain. Negative values make the signal drier.
+    pub sustain: f64,
+    /// The ballistics of the fast envelope.
+    pub fast: Ballistics,
+    /// The ballistics of the slow envelope.
+    pub slow: Ballistics,
+    /// Whether both channels receive the same gain.
+    pub link: bool,
+
+    /// The fast envelope of each channel.
+    fasts: [Level; 2],
+    /// The envelope of each channel that rises slowly.
+    rises: [Level; 2],
+    /// The envelope of each channel that falls slowly.
+    falls: [Level; 2],
+    /// The current gain for each channel.
+    gains: [f64; 2],
+}
+
+impl Transient {
+    /// Initializes a new transient designer with linked channels.
+    #[must_use]
+    pub const fn new(attack: f64, sustain: f64, fast: Ballistics, slow: Ballistics) -> Self {
+        Self {
+            attack,
+            sustain,
+            fast,
+            slow,
+            link: true,
+            fasts: [Level::new(); 2],
+            rises: [Level::new(); 2],
+            falls: [Level::new(); 2],
+            gains: [1.0; 2],
+        }
+    }
+
+    /// The gain change in decibels, given the fast envelope, the slowly rising envelope, and the
+    /// slowly falling envelope.
+    #[must_use]
+    pub fn curve(&self, fast: f64, rise: f64, fall: f64) -> f64 {
+        let attack = if fast > 0.0 {
+            (1.0 - rise / fast).max(0.0)
+        } else {
+            0.0
+        };
+        let sustain = if fall > 0.0 {
+            (1.0 - fast / fall).max(0.0)
+        } else {
+            0.0
+        };
+
+        self.attack * attack + self.sustain * sustain
+    }
+}
+
+impl Dynamics for Transient {
+    fn process<A: Audio>(&mut self, key: A) {
+        let rise = Ballistics::new(self.slow.attack, self.fast.release);
+        let fall = Ballistics::new(self.fast.attack, self.slow.release);
+
+        let mut envs = [[0.0; 3]; 2];
+        A::for_each(|channel| {
+            let value = key[channel];
+            envs[channel] = [
+                self.fasts[channel].process(value, Detection::Peak, self.fast),
+                self.rises[channel].process(value, Detection::Peak, rise),
+                self.falls[channel].process(value, Detection::Peak, fall),
+            ];
+        });
+
+        if A::SIZE == 1 || self.link {
+            let max = [0, 1, 2].map(|i| f64::max(envs[0][i], envs[1][i]));
+            envs = [max; 2];
+        }
+
+        self.gains =
+            envs.map(|[fast, rise, fall]| unt::Vol::from_db(self.curve(fast, rise, fall)).gain);
+    }
+
+    fn gain(&self, channel: usize) -> unt::Vol {
+        unt::Vol::new(self.gains[channel])
+    }
+
+    fn reset(&mut self) {
+        self.fasts = [Level::new(); 2];
+        self.rises = [Level::new(); 2];
+        self.falls = [Level::new(); 2];
+        self.gains = [1.0; 2];
+    }
+}
+
+/// Shapes the transients of a signal, using its own level as the key.
+///
+/// See [`Transient`] for more information.
+///
+/// ## Example
+///
+/// We make a drum loop punchier, and cut down its tail.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let msec = unt::Time::from_msec_default;
+/// let drums = ctr::Loop::new(
+///     vec![sec(0.25)],
+///     eff::env::ArEnv::new_ar(
+///         gen::NoiseGen::<smp::Stereo>::new(),
+///         eff::env::Ar::new(msec(5.0), sec(0.2)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let shaper = eff::dnm::Transient::new(
+///     6.0,
+///     -12.0,
+///     eff::dnm::Ballistics::new(msec(1.0), msec(50.0)),
+///     eff::dnm::Ballistics::new(msec(20.0), msec(300.0)),
+/// );
+/// let sgn = eff::dnm::TransientShaper::new(drums, shaper);
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/transient.wav");
+/// ```
+pub type TransientShaper<S> = Dynamic<S, Transient>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the attack is boosted, and that a steady signal is left alone.
+    #[test]
+    fn attack() {
+        let mut shaper = Transient::new(
+            6.0,
+            -6.0,
+            Ballistics::INSTANT,
+            Ballistics::new(unt::Time::from_samples(100), unt::Time::from_samples(100)),
+        );
+
+        shaper.process(smp::Stereo(1.0, 0.0));
+        assert!(shaper.gain(0).db() > 5.0);
+        assert_approx_eq::assert_approx_eq!(shaper.gain(0).gain, shaper.gain(1).gain);
+
+        for _ in 0..10_000 {
+            shaper.process(smp::Stereo(1.0, 0.0));
+        }
+        assert_approx_eq::assert_approx_eq!(shaper.gain(0).gain, 1.0);
+    }
+}
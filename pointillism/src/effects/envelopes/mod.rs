@@ -1,9 +1,28 @@
 //! Signal envelopes, notably including the [`ArEnv`] and [`AdsrEnv`].
 //!
+//! For arbitrary envelope shapes, see the multi-segment [`MsegEnv`].
+//!
 //! TODO: aren't Ar and Adsr generators?
 
+mod mseg;
+mod shape;
+
+pub use mseg::{Breakpoint, Mseg, MsegEnv};
+pub use shape::Shape;
+
 use crate::prelude::*;
 
+/// Determines what happens to the level of an envelope when it's retriggered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Retrig {
+    /// The attack restarts from zero.
+    #[default]
+    Reset,
+    /// The attack restarts from the current level of the envelope. This avoids clicks when
+    /// retriggering an envelope that hasn't finished.
+    Current,
+}
+
 /// Any of the stages in an AR envelope.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ArStage {
@@ -24,6 +43,23 @@ pub enum ArStage {
 ///  ⟋         \
 /// •―――――――――――•  [DC = 0]
 /// ```
+///
+/// Each stage ramps linearly by default. This can be changed by setting the [`Shape`] of each
+/// stage.
+///
+/// ## Example
+///
+/// An envelope with an analog-style attack and an exponential release.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let mut ar = eff::env::Ar::new(
+///     unt::Time::from_msec_default(10.0),
+///     unt::Time::from_msec_default(500.0),
+/// );
+/// ar.attack_shape = eff::env::Shape::Rc(4.0);
+/// ar.release_shape = eff::env::Shape::Exp(60.0);
+/// ```
 #[derive(Clone, Copy, Debug)]
 pub struct Ar {
     /// The time from the signal start to its peak.
@@ -31,9 +67,21 @@ pub struct Ar {
     /// The time from the signal stop to it being done.
     pub release: unt::Time,
 
+    /// The shape of the attack stage.
+    pub attack_shape: Shape,
+    /// The shape of the release stage.
+    pub release_shape: Shape,
+    /// What happens to the level when the envelope is retriggered.
+    pub retrig: Retrig,
+
     /// Current stage of the envelope.
     stage: ArStage,
 
+    /// The volume from which the attack starts.
+    ///
+    /// This can differ from zero if the envelope is retriggered with [`Retrig::Current`].
+    attack_vol: unt::Vol,
+
     /// The volume from which the release starts.
     ///
     /// This can differ from full volume if the envelope is stopped before the `Release` phase.
@@ -50,8 +98,12 @@ impl Ar {
         let mut ar = Self {
             attack,
             release,
+            attack_shape: Shape::Linear,
+            release_shape: Shape::Linear,
+            retrig: Retrig::Reset,
             stage: ArStage::Attack,
             phase_time: unt::Time::ZERO,
+            attack_vol: unt::Vol::ZERO,
 
             // Is properly initialized in `stop`, or when the release phase starts.
             release_vol: unt::Vol::ZERO,
@@ -91,8 +143,14 @@ impl Signal for Ar {
         // Division by zero should not be possible, as phases with length zero are immediately
         // skipped.
         smp::Env(match self.stage() {
-            ArStage::Attack => self.phase_time / self.attack,
-            ArStage::Release => self.release_vol.gain * (1.0 - self.phase_time / self.release),
+            ArStage::Attack => {
+                self.attack_shape
+                    .eval(self.attack_vol.gain, 1.0, self.phase_time / self.attack)
+            }
+            ArStage::Release => {
+                self.release_shape
+                    .eval(self.release_vol.gain, 0.0, self.phase_time / self.release)
+            }
             ArStage::Done => 0.0,
         })
     }
@@ -105,8 +163,13 @@ impl SignalMut for Ar {
     }
 
     fn retrigger(&mut self) {
+        self.attack_vol = match self.retrig {
+            Retrig::Reset => unt::Vol::ZERO,
+            Retrig::Current => unt::Vol::new(self.get().0),
+        };
         self.stage = ArStage::Attack;
         self.phase_time = unt::Time::ZERO;
+        self.set_stage();
     }
 }
 
@@ -181,6 +244,9 @@ impl From<ArStage> for AdsrStage {
 /// ```
 ///
 /// If you don't care for the sustain, you might want to use an [`ArEnv`] instead.
+///
+/// As with an [`Ar`], each stage ramps linearly by default, which can be changed by setting the
+/// [`Shape`] of each stage.
 #[derive(Clone, Copy, Debug)]
 pub struct Adsr {
     /// The time from the signal start to its peak.
@@ -192,9 +258,23 @@ pub struct Adsr {
     /// The time from the signal stop to it being done.
     pub release: unt::Time,
 
+    /// The shape of the attack stage.
+    pub attack_shape: Shape,
+    /// The shape of the decay stage.
+    pub decay_shape: Shape,
+    /// The shape of the release stage.
+    pub release_shape: Shape,
+    /// What happens to the level when the envelope is retriggered.
+    pub retrig: Retrig,
+
     /// Current stage of the envelope.
     stage: AdsrStage,
 
+    /// The volume from which the attack starts.
+    ///
+    /// This can differ from zero if the envelope is retriggered with [`Retrig::Current`].
+    attack_vol: unt::Vol,
+
     /// The volume from which the release starts.
     ///
     /// This can differ from the sustain value if the envelope is stopped before the `Sustain`
@@ -214,8 +294,13 @@ impl Adsr {
             decay,
             sustain,
             release,
+            attack_shape: Shape::Linear,
+            decay_shape: Shape::Linear,
+            release_shape: Shape::Linear,
+            retrig: Retrig::Reset,
             stage: AdsrStage::Attack,
             phase_time: unt::Time::ZERO,
+            attack_vol: unt::Vol::ZERO,
 
             // Is properly initialized in `stop`.
             release_vol: unt::Vol::ZERO,
@@ -259,10 +344,19 @@ impl Signal for Adsr {
         // Division by zero should not be possible, as phases with length zero are immediately
         // skipped.
         smp::Env(match self.stage() {
-            AdsrStage::Attack => self.phase_time / self.attack,
-            AdsrStage::Decay => 1.0 + (self.sustain.gain - 1.0) * (self.phase_time / self.decay),
+            AdsrStage::Attack => {
+                self.attack_shape
+                    .eval(self.attack_vol.gain, 1.0, self.phase_time / self.attack)
+            }
+            AdsrStage::Decay => {
+                self.decay_shape
+                    .eval(1.0, self.sustain.gain, self.phase_time / self.decay)
+            }
             AdsrStage::Sustain => self.sustain.gain,
-            AdsrStage::Release => self.release_vol.gain * (1.0 - self.phase_time / self.release),
+            AdsrStage::Release => {
+                self.release_shape
+                    .eval(self.release_vol.gain, 0.0, self.phase_time / self.release)
+            }
             AdsrStage::Done => 0.0,
         })
     }
@@ -275,8 +369,13 @@ impl SignalMut for Adsr {
     }
 
     fn retrigger(&mut self) {
+        self.attack_vol = match self.retrig {
+            Retrig::Reset => unt::Vol::ZERO,
+            Retrig::Current => unt::Vol::new(self.get().0),
+        };
         self.stage = AdsrStage::Attack;
         self.phase_time = unt::Time::ZERO;
+        self.set_stage();
     }
 }
 
@@ -311,3 +410,22 @@ impl<S: SignalMut> AdsrEnv<S> {
         Self::new(sgn, adsr)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Retriggering with [`Retrig::Current`] should restart the attack from the current level.
+    #[test]
+    fn retrig_current() {
+        let mut ar = Ar::new(unt::Time::from_samples(4), unt::Time::from_samples(4));
+        ar.retrig = Retrig::Current;
+        ar.advance();
+        ar.advance();
+
+        let level = ar.get().0;
+        ar.retrigger();
+        assert_eq!(ar.stage(), ArStage::Attack);
+        assert_approx_eq::assert_approx_eq!(ar.get().0, level);
+    }
+}
@@ -0,0 +1,392 @@
+//! Defines multi-segment envelopes, see [`Mseg`].
+
+use crate::prelude::*;
+
+/// A single breakpoint in an [`Mseg`].
+///
+/// Each breakpoint marks the end of a segment, which starts at the previous breakpoint (or at the
+/// [start level](Mseg::start) for the first one).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Breakpoint {
+    /// The time it takes to reach this breakpoint from the previous one.
+    pub time: unt::Time,
+    /// The envelope level at this breakpoint.
+    pub level: f64,
+    /// The shape of the segment leading to this breakpoint.
+    pub shape: eff::env::Shape,
+}
+
+impl Breakpoint {
+    /// Initializes a new [`Breakpoint`].
+    #[must_use]
+    pub const fn new(time: unt::Time, level: f64, shape: eff::env::Shape) -> Self {
+        Self { time, level, shape }
+    }
+
+    /// Initializes a new [`Breakpoint`], reached through a straight line.
+    #[must_use]
+    pub const fn new_linear(time: unt::Time, level: f64) -> Self {
+        Self::new(time, level, eff::env::Shape::Linear)
+    }
+}
+
+/// A multi-segment envelope, defined by an arbitrary list of [`Breakpoint`]s.
+///
+/// The envelope starts at the [start level](Self::start) and goes through every breakpoint in
+/// order, after which it's done.
+///
+/// ## Sustain and loop
+///
+/// If a sustain point is set, the envelope holds the level of that breakpoint until it's
+/// [stopped](Stop). After being stopped, it continues from its current level into the segment
+/// right after the sustain point. The segments after the sustain point thus act as a release.
+///
+/// If a loop point is also set, instead of holding at the sustain point, the envelope jumps back
+/// to the start of the specified segment, and keeps looping until stopped.
+///
+/// If no sustain point is set, stopping the envelope makes it jump from its current level into the
+/// last segment.
+///
+/// ```txt
+///      1         3
+///     ⟋⟍ 2  ⟋‾‾‾‾‾\
+///   ⟋   ⟍⟋        \ 4
+///  ⟋                \
+/// •――――――――――――――――――•  [DC = 0]
+///           ↑ loop  ↑ sustain
+/// ```
+///
+/// ## Example
+///
+/// We build a simple pluck with a short "bounce" in its attack.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let ms = unt::Time::from_msec_default;
+/// let mseg = eff::env::Mseg::new(
+///     0.0,
+///     vec![
+///         eff::env::Breakpoint::new_linear(ms(5.0), 1.0),
+///         eff::env::Breakpoint::new(ms(30.0), 0.6, eff::env::Shape::Tension(-3.0)),
+///         eff::env::Breakpoint::new(ms(300.0), 0.0, eff::env::Shape::Tension(-5.0)),
+///     ],
+/// );
+///
+/// let sgn = gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_hz_default(220.0));
+/// let mut env = eff::env::MsegEnv::new_mseg(sgn, mseg);
+///
+/// // The envelope finishes after all of its segments.
+/// for _ in 0..ms(336.0).samples.int() {
+///     env.next();
+/// }
+/// assert!(env.is_done());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mseg {
+    /// The level at which the envelope starts.
+    pub start: f64,
+    /// The breakpoints of the envelope.
+    breakpoints: Vec<Breakpoint>,
+    /// The index of the breakpoint to sustain at, if any.
+    sustain: Option<usize>,
+    /// The index of the segment to loop back into, if any.
+    loop_start: Option<usize>,
+
+    /// The index of the current segment. Equals the number of breakpoints once the envelope is
+    /// done.
+    index: usize,
+    /// The level from which the current segment starts. Once the envelope is done, this is the
+    /// level it outputs.
+    from: f64,
+    /// How many frames have we spent in this segment?
+    phase_time: unt::Time,
+    /// Whether the envelope has been stopped.
+    stopped: bool,
+}
+
+impl Mseg {
+    /// Initializes a new [`Mseg`] with no sustain or loop points.
+    #[must_use]
+    pub fn new(start: f64, breakpoints: Vec<Breakpoint>) -> Self {
+        Self::new_sustain_loop(start, breakpoints, None, None)
+    }
+
+    /// Initializes a new [`Mseg`] that holds at the given breakpoint until stopped.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the sustain index is out of bounds.
+    #[must_use]
+    pub fn new_sustain(start: f64, breakpoints: Vec<Breakpoint>, sustain: usize) -> Self {
+        Self::new_sustain_loop(start, breakpoints, Some(sustain), None)
+    }
+
+    /// Initializes a new [`Mseg`] that loops from the start of the segment with index
+    /// `loop_start` up to the breakpoint with index `sustain`, until stopped.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the sustain index is out of bounds, or if the loop starts after the sustain point.
+    #[must_use]
+    pub fn new_loop(
+        start: f64,
+        breakpoints: Vec<Breakpoint>,
+        loop_start: usize,
+        sustain: usize,
+    ) -> Self {
+        Self::new_sustain_loop(start, breakpoints, Some(sustain), Some(loop_start))
+    }
+
+    /// Initializes a new [`Mseg`] with optional sustain and loop points.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the sustain index is out of bounds, if a loop point is given without a sustain
+    /// point, or if the loop starts after the sustain point.
+    #[must_use]
+    pub fn new_sustain_loop(
+        start: f64,
+        breakpoints: Vec<Breakpoint>,
+        sustain: Option<usize>,
+        loop_start: Option<usize>,
+    ) -> Self {
+        if let Some(sustain) = sustain {
+            assert!(sustain < breakpoints.len(), "{}", crate::OOB);
+        }
+        if let Some(loop_start) = loop_start {
+            let sustain = sustain.expect("a loop requires a sustain point");
+            assert!(loop_start <= sustain, "the loop must start before it ends");
+        }
+
+        let mut mseg = Self {
+            start,
+            breakpoints,
+            sustain,
+            loop_start,
+            index: 0,
+            from: start,
+            phase_time: unt::Time::ZERO,
+            stopped: false,
+        };
+
+        // Skip any segments with zero time.
+        mseg.set_stage();
+        mseg
+    }
+
+    /// The breakpoints of the envelope.
+    #[must_use]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// A mutable reference to the breakpoints of the envelope.
+    ///
+    /// Changing these doesn't change the number of breakpoints, so the sustain and loop points
+    /// remain valid.
+    pub fn breakpoints_mut(&mut self) -> &mut [Breakpoint] {
+        &mut self.breakpoints
+    }
+
+    /// The index of the sustain breakpoint, if any.
+    #[must_use]
+    pub const fn sustain(&self) -> Option<usize> {
+        self.sustain
+    }
+
+    /// The index of the segment the envelope loops back into, if any.
+    #[must_use]
+    pub const fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+
+    /// The index of the current segment.
+    ///
+    /// This equals the number of breakpoints once the envelope is done.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Whether the envelope is currently holding at its sustain point.
+    #[must_use]
+    pub fn is_sustaining(&self) -> bool {
+        !self.stopped
+            && self.sustain == Some(self.index)
+            && self.phase_time >= self.breakpoints[self.index].time
+    }
+
+    /// The total length of the loop, or zero if there's none.
+    fn loop_time(&self) -> unt::Time {
+        match (self.loop_start, self.sustain) {
+            (Some(loop_start), Some(sustain)) => self.breakpoints[loop_start..=sustain]
+                .iter()
+                .map(|bp| bp.time)
+                .sum(),
+            _ => unt::Time::ZERO,
+        }
+    }
+
+    /// Sets our segment to the correct one, based on the elapsed time.
+    ///
+    /// This should work even if some segments take zero time.
+    fn set_stage(&mut self) {
+        while let Some(bp) = self.breakpoints.get(self.index) {
+            if self.phase_time < bp.time {
+                return;
+            }
+
+            if !self.stopped && self.sustain == Some(self.index) {
+                // A loop with zero length would never finish, so we just hold instead.
+                match self.loop_start {
+                    Some(loop_start) if !self.loop_time().is_zero() => {
+                        self.phase_time -= bp.time;
+                        self.from = bp.level;
+                        self.index = loop_start;
+                        continue;
+                    }
+                    _ => return,
+                }
+            }
+
+            self.phase_time -= bp.time;
+            self.from = bp.level;
+            self.index += 1;
+        }
+    }
+}
+
+impl Signal for Mseg {
+    type Sample = smp::Env;
+
+    fn get(&self) -> smp::Env {
+        smp::Env(match self.breakpoints.get(self.index) {
+            // Division by zero should not be possible, as segments with length zero are
+            // immediately skipped, and a held segment is clamped to its end.
+            Some(bp) => {
+                if self.phase_time >= bp.time {
+                    bp.level
+                } else {
+                    bp.shape
+                        .eval(self.from, bp.level, self.phase_time / bp.time)
+                }
+            }
+            None => self.from,
+        })
+    }
+}
+
+impl SignalMut for Mseg {
+    fn advance(&mut self) {
+        // Don't let the time grow unboundedly while sustaining.
+        if !self.is_sustaining() {
+            self.phase_time.advance();
+            self.set_stage();
+        }
+    }
+
+    fn retrigger(&mut self) {
+        self.index = 0;
+        self.from = self.start;
+        self.phase_time = unt::Time::ZERO;
+        self.stopped = false;
+        self.set_stage();
+    }
+}
+
+impl Done for Mseg {
+    fn is_done(&self) -> bool {
+        self.index >= self.breakpoints.len()
+    }
+}
+
+impl Stop for Mseg {
+    fn stop(&mut self) {
+        if self.stopped {
+            return;
+        }
+
+        let release = self
+            .sustain
+            .map_or(self.breakpoints.len().saturating_sub(1), |sustain| {
+                sustain + 1
+            });
+
+        if self.index < release {
+            self.from = self.get().0;
+            self.index = release;
+            self.phase_time = unt::Time::ZERO;
+        }
+
+        self.stopped = true;
+        self.set_stage();
+    }
+}
+
+impl Panic for Mseg {
+    fn panic(&mut self) {
+        self.index = self.breakpoints.len();
+        self.from = 0.0;
+    }
+}
+
+/// Hooks up a signal to an [`Mseg`] envelope.
+///
+/// Initialize with [`Self::new_mseg`].
+pub type MsegEnv<S> = eff::StopTremolo<S, Mseg>;
+
+impl<S: SignalMut> MsegEnv<S> {
+    /// Initializes an [`MsegEnv`] with the given parameters.
+    pub fn new_mseg(sgn: S, mseg: Mseg) -> Self {
+        Self::new(sgn, mseg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Builds an envelope with four segments of one sample each: `0 → 1 → 0.5 → 0.5 → 0`.
+    fn mseg(sustain: Option<usize>, loop_start: Option<usize>) -> Mseg {
+        let bp = |level| Breakpoint::new_linear(unt::Time::SAMPLE, level);
+        Mseg::new_sustain_loop(
+            0.0,
+            vec![bp(1.0), bp(0.5), bp(0.5), bp(0.0)],
+            sustain,
+            loop_start,
+        )
+    }
+
+    /// Advances an envelope a given number of times, and collects its outputs.
+    fn collect(mseg: &mut Mseg, n: usize) -> Vec<f64> {
+        (0..n).map(|_| mseg.next().0).collect()
+    }
+
+    /// Test an envelope without sustain.
+    #[test]
+    fn no_sustain() {
+        let mut env = mseg(None, None);
+        assert_eq!(collect(&mut env, 5), [0.0, 1.0, 0.5, 0.5, 0.0]);
+        assert!(env.is_done());
+    }
+
+    /// Test that the envelope holds at the sustain point, and releases once stopped.
+    #[test]
+    fn sustain() {
+        let mut env = mseg(Some(1), None);
+        assert_eq!(collect(&mut env, 5), [0.0, 1.0, 0.5, 0.5, 0.5]);
+        env.stop();
+        assert_eq!(collect(&mut env, 3), [0.5, 0.5, 0.0]);
+        assert!(env.is_done());
+    }
+
+    /// Test that the envelope loops until stopped.
+    #[test]
+    fn looping() {
+        let mut env = mseg(Some(1), Some(0));
+        assert_eq!(collect(&mut env, 6), [0.0, 1.0, 0.5, 1.0, 0.5, 1.0]);
+        env.stop();
+        assert_eq!(collect(&mut env, 3), [0.5, 0.5, 0.0]);
+        assert!(env.is_done());
+    }
+}
@@ -0,0 +1,125 @@
+//! Defines the [`Shape`] of an envelope segment.
+
+/// The shape of a single envelope segment.
+///
+/// A shape determines how an envelope moves from a start level into an end level, as the time
+/// through the segment goes from `0.0` to `1.0`. See [`Shape::eval`].
+///
+/// Some of these shapes are the same regardless of whether the segment rises or falls, while
+/// others (such as [`Shape::Exp`]) depend on its direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Shape {
+    /// A straight line from start to end.
+    #[default]
+    Linear,
+
+    /// Holds the start level throughout the segment, and jumps to the end level once it's over.
+    Hold,
+
+    /// An exponential curve with adjustable tension.
+    ///
+    /// A positive tension makes the segment start slow and end fast, while a negative tension
+    /// makes it start fast and end slow. A tension of `0.0` gives a straight line.
+    Tension(f64),
+
+    /// A curve that's linear in decibels, spanning the given dynamic range in dB.
+    ///
+    /// This is what an exponential curve sounds like when applied to amplitude. Rising segments
+    /// start slowly and end quickly, while falling segments start quickly and end slowly. Larger
+    /// ranges give more pronounced curves.
+    Exp(f64),
+
+    /// The mirror image of an [`Shape::Exp`] curve, spanning the given dynamic range in dB.
+    ///
+    /// Rising segments start quickly and end slowly, while falling segments start slowly and end
+    /// quickly.
+    Log(f64),
+
+    /// The curve of a capacitor charging or discharging through a resistor, as in analog
+    /// envelope generators, spanning the given number of time constants.
+    ///
+    /// Both rising and falling segments start quickly and end slowly. Around `3.0` to `5.0` time
+    /// constants give a typical analog sound.
+    Rc(f64),
+}
+
+impl Shape {
+    /// Tension values closer to zero than this are treated as a straight line.
+    ///
+    /// This avoids a division by a number close to zero.
+    const EPSILON: f64 = 1e-6;
+
+    /// Evaluates the curve `(e^(kx) - 1) / (e^k - 1)`, for a tension `k`.
+    fn tension(k: f64, x: f64) -> f64 {
+        if k.abs() < Self::EPSILON {
+            x
+        } else {
+            (k * x).exp_m1() / k.exp_m1()
+        }
+    }
+
+    /// Converts a dynamic range in dB into the equivalent tension.
+    fn db_tension(db: f64) -> f64 {
+        db * std::f64::consts::LN_10 / 20.0
+    }
+
+    /// Returns how far along a segment from `from` to `to` we are, as a value from `0.0` to `1.0`,
+    /// given the elapsed fraction `x` of the segment.
+    #[must_use]
+    pub fn progress(&self, from: f64, to: f64, x: f64) -> f64 {
+        let rising = to >= from;
+        match *self {
+            Self::Linear => x,
+            Self::Hold => 0.0,
+            Self::Tension(k) => Self::tension(k, x),
+            Self::Exp(db) => {
+                let k = Self::db_tension(db);
+                Self::tension(if rising { k } else { -k }, x)
+            }
+            Self::Log(db) => {
+                let k = Self::db_tension(db);
+                Self::tension(if rising { -k } else { k }, x)
+            }
+            Self::Rc(n) => Self::tension(-n, x),
+        }
+    }
+
+    /// Returns the level of a segment from `from` to `to`, given the elapsed fraction `x` of the
+    /// segment.
+    #[must_use]
+    pub fn eval(&self, from: f64, to: f64, x: f64) -> f64 {
+        from + (to - from) * self.progress(from, to, x)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Every shape should start at the start level and end at the end level.
+    #[test]
+    fn endpoints() {
+        for shape in [
+            Shape::Linear,
+            Shape::Tension(-4.0),
+            Shape::Tension(0.0),
+            Shape::Tension(7.5),
+            Shape::Exp(48.0),
+            Shape::Log(48.0),
+            Shape::Rc(4.0),
+        ] {
+            for (from, to) in [(0.0, 1.0), (1.0, 0.25)] {
+                assert_approx_eq::assert_approx_eq!(shape.eval(from, to, 0.0), from);
+                assert_approx_eq::assert_approx_eq!(shape.eval(from, to, 1.0), to);
+            }
+        }
+    }
+
+    /// Exponential curves are slow at low levels, both when rising and falling.
+    #[test]
+    fn exp() {
+        let shape = Shape::Exp(48.0);
+        assert!(shape.eval(0.0, 1.0, 0.5) < 0.5);
+        assert!(shape.eval(1.0, 0.0, 0.5) < 0.5);
+    }
+}
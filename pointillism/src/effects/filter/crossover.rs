@@ -0,0 +1,396 @@
+//! Implements [Linkwitz-Riley](https://en.wikipedia.org/wiki/Linkwitz%E2%80%93Riley_filter)
+//! crossovers, which split a signal into bands that sum back to the original, as well as the
+//! [`Multiband`] effect built on them.
+
+use super::{Biquad, LoFilter};
+use crate::prelude::*;
+
+/// A fourth order Linkwitz-Riley crossover, which splits a signal into a low and a high band.
+///
+/// Each band is made out of two cascaded Butterworth [`Biquad`] filters. Both bands are 6 dB down
+/// at the crossover frequency, and are always in phase, so that their sum has a flat frequency
+/// response. This sum equals the signal filtered through a Butterworth
+/// [all-pass](Biquad::all_pass).
+#[derive(Clone, Debug)]
+pub struct Crossover<A: Audio> {
+    /// The cascaded low-pass filters.
+    low: [LoFilter<A, 3, 2>; 2],
+    /// The cascaded hi-pass filters.
+    high: [LoFilter<A, 3, 2>; 2],
+}
+
+impl<A: Audio> Crossover<A> {
+    /// Initializes a new crossover at the given frequency.
+    #[must_use]
+    pub fn new(freq: unt::Freq) -> Self {
+        let low = || LoFilter::new_coefs(Biquad::low_pass(freq, unt::QFactor::BUTTERWORTH));
+        let high = || LoFilter::new_coefs(Biquad::hi_pass(freq, unt::QFactor::BUTTERWORTH));
+        Self {
+            low: [low(), low()],
+            high: [high(), high()],
+        }
+    }
+
+    /// Takes in a new input, returns the low and high bands.
+    pub fn eval(&mut self, input: A) -> (A, A) {
+        let [low1, low2] = &mut self.low;
+        let [high1, high2] = &mut self.high;
+        (low2.eval(low1.eval(input)), high2.eval(high1.eval(input)))
+    }
+
+    /// Resets the previous values to zero.
+    pub fn retrigger(&mut self) {
+        for filter in self.low.iter_mut().chain(&mut self.high) {
+            filter.retrigger();
+        }
+    }
+}
+
+/// Splits a signal into any number of bands, through a chain of [`Crossovers`](Crossover).
+///
+/// The signal is first split at the lowest frequency. The high band is then split at the next
+/// frequency, and so on. Every band but the highest is then filtered through the all-pass filters
+/// corresponding to the crossovers it hasn't gone through. This way, all bands remain in phase, and
+/// their sum has a flat frequency response.
+#[derive(Clone, Debug)]
+pub struct Split<A: Audio> {
+    /// The crossovers, from lowest to highest frequency.
+    crossovers: Vec<Crossover<A>>,
+    /// The all-pass filters applied to each band.
+    all_passes: Vec<Vec<LoFilter<A, 3, 2>>>,
+    /// The last output of each band, from lowest to highest frequency.
+    bands: Vec<A>,
+}
+
+impl<A: Audio> Split<A> {
+    /// Initializes a new band splitter. The frequencies must be sorted in increasing order.
+    ///
+    /// There's always one band more than the number of frequencies.
+    #[must_use]
+    pub fn new(freqs: &[unt::Freq]) -> Self {
+        let all_pass =
+            |freq| LoFilter::new_coefs(Biquad::all_pass(freq, unt::QFactor::BUTTERWORTH));
+
+        Self {
+            crossovers: freqs.iter().map(|&freq| Crossover::new(freq)).collect(),
+            all_passes: (0..=freqs.len())
+                .map(|band| {
+                    freqs
+                        .iter()
+                        .skip(band + 1)
+                        .map(|&freq| all_pass(freq))
+                        .collect()
+                })
+                .collect(),
+            bands: vec![A::ZERO; freqs.len() + 1],
+        }
+    }
+
+    /// The number of bands.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// Returns `false`, as there's always at least one band.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The last output of each band, from lowest to highest frequency.
+    #[must_use]
+    pub fn bands(&self) -> &[A] {
+        &self.bands
+    }
+
+    /// Takes in a new input, and splits it into bands.
+    pub fn eval(&mut self, input: A) -> &[A] {
+        let mut rest = input;
+        for (band, crossover) in self.crossovers.iter_mut().enumerate() {
+            let (low, high) = crossover.eval(rest);
+            self.bands[band] = low;
+            rest = high;
+        }
+        self.bands[self.crossovers.len()] = rest;
+
+        for (band, all_passes) in self.bands.iter_mut().zip(&mut self.all_passes) {
+            for all_pass in all_passes {
+                *band = all_pass.eval(*band);
+            }
+        }
+
+        &self.bands
+    }
+
+    /// Resets the previous values to zero.
+    pub fn retrigger(&mut self) {
+        for crossover in &mut self.crossovers {
+            crossover.retrigger();
+        }
+        for all_pass in self.all_passes.iter_mut().flatten() {
+            all_pass.retrigger();
+        }
+        self.bands.fill(A::ZERO);
+    }
+}
+
+/// A signal which outputs a single band of a [`Multiband`] effect.
+///
+/// This is the base signal of each effect within a [`Multiband`], which sets its value before
+/// advancing the effect. Advancing or retriggering this signal by itself does nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Band<A: Audio>(pub A);
+
+impl<A: Audio> Band<A> {
+    /// Initializes a new band with a value of zero.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(A::ZERO)
+    }
+}
+
+impl<A: Audio> Signal for Band<A> {
+    type Sample = A;
+
+    fn get(&self) -> A {
+        self.0
+    }
+}
+
+impl<A: Audio> SignalMut for Band<A> {
+    fn advance(&mut self) {
+        // No-op.
+    }
+
+    fn retrigger(&mut self) {
+        // No-op.
+    }
+}
+
+impl<A: Audio> Base for Band<A> {
+    impl_base!();
+}
+
+/// Splits a signal into bands through a [`Split`], applies a separate effect to each, and sums the
+/// results back together.
+///
+/// Each effect must have a [`Band`] as its [`Base`] signal. This can be any chain of effects that
+/// forwards its base, such as a [`Compressor`](eff::dnm::Compressor) or a
+/// [`Volume`](eff::Volume).
+///
+/// ## Example
+///
+/// We compress the low end of a chord much harder than its high end.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = |raw| gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(raw));
+/// let chord = rtn::Mix::new(saw(unt::RawFreq::A2), saw(unt::RawFreq::E3));
+///
+/// let comp = |ratio| {
+///     eff::dnm::Compressor::new(
+///         eff::flt::Band::new(),
+///         eff::dnm::Comp::new(
+///             unt::Vol::from_db(-20.0),
+///             ratio,
+///             eff::dnm::Ballistics::new(sec(0.005), sec(0.1)),
+///         ),
+///     )
+/// };
+///
+/// let sgn = eff::flt::Multiband::new(
+///     chord,
+///     &[unt::Freq::from_hz_default(200.0), unt::Freq::from_hz_default(2000.0)],
+///     vec![comp(8.0), comp(3.0), comp(1.5)],
+/// );
+/// Song::new(sec(2.0), unt::SampleRate::default(), sgn).export("examples/multiband.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Multiband<S: Signal, E: Base<Sample = S::Sample, Base = Band<S::Sample>>>
+where
+    S::Sample: Audio,
+{
+    /// The processed signal.
+    sgn: S,
+    /// Splits the signal into bands.
+    split: Split<S::Sample>,
+    /// The effect applied to each band.
+    bands: Vec<E>,
+}
+
+impl<S: Signal, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new multiband effect, given the crossover frequencies in increasing order, and
+    /// the effect for each band, from lowest to highest frequency.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if there isn't exactly one effect more than the number of frequencies.
+    pub fn new(sgn: S, freqs: &[unt::Freq], bands: Vec<E>) -> Self {
+        assert_eq!(
+            freqs.len() + 1,
+            bands.len(),
+            "there must be one band more than the number of frequencies"
+        );
+
+        let mut res = Self {
+            sgn,
+            split: Split::new(freqs),
+            bands,
+        };
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the processed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the processed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the effects applied to each band.
+    pub fn bands(&self) -> &[E] {
+        &self.bands
+    }
+
+    /// Returns a mutable reference to the effects applied to each band.
+    pub fn bands_mut(&mut self) -> &mut [E] {
+        &mut self.bands
+    }
+
+    /// Splits the current sample from the signal, and advances the effect on each band.
+    fn process(&mut self) {
+        let split = self.split.eval(self.sgn.get());
+        for (band, &sample) in self.bands.iter_mut().zip(split) {
+            band.base_mut().0 = sample;
+            band.advance();
+        }
+    }
+}
+
+impl<S: Signal, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Signal for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.bands.iter().map(Signal::get).sum()
+    }
+}
+
+impl<S: SignalMut, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> SignalMut
+    for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.split.retrigger();
+        for band in &mut self.bands {
+            band.base_mut().0 = S::Sample::ZERO;
+            band.retrigger();
+        }
+        self.process();
+    }
+}
+
+impl<S: Frequency, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Frequency
+    for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Base for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: Done, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Done for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Stop for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, E: Base<Sample = S::Sample, Base = Band<S::Sample>>> Panic for Multiband<S, E>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.split.retrigger();
+        for band in &mut self.bands {
+            band.base_mut().0 = S::Sample::ZERO;
+            band.retrigger();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the bands of a split sum to a flat frequency response.
+    #[test]
+    fn flat() {
+        let freqs = [100.0, 1000.0, 5000.0].map(unt::Freq::from_hz_default);
+
+        for hz in [50.0, 100.0, 440.0, 1000.0, 3000.0, 8000.0] {
+            let mut sine = gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_hz_default(hz));
+            let mut split = Split::new(&freqs);
+            assert_eq!(split.len(), 4);
+
+            let mut peak = 0.0;
+            for time in 0..20_000 {
+                let sum: smp::Mono = split.eval(sine.next()).iter().copied().sum();
+                if time >= 10_000 {
+                    peak = f64::max(peak, sum.0.abs());
+                }
+            }
+            assert_approx_eq::assert_approx_eq!(peak, 1.0, 0.01);
+        }
+    }
+}
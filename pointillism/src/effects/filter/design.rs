@@ -79,6 +79,31 @@ impl SinglePole {
 /// [`DiffEq`] for a bilinear (order 1) filter.
 pub type Bilinear = LoDiffEq<2, 1>;
 
+impl Bilinear {
+    /// Initializes a [`Bilinear`] filter from coefficients normalized to `a0 = 1`.
+    #[must_use]
+    pub const fn new_normalized(a1: f64, b0: f64, b1: f64) -> Self {
+        Self::new_raw(DenseStc::new([b0, b1]), DenseStc::new([a1]))
+    }
+
+    /// Initializes a [`Bilinear`] filter from its unnormalized coefficients.
+    #[must_use]
+    pub fn new(a0: f64, a1: f64, b0: f64, b1: f64) -> Self {
+        Self::new_normalized(a1 / a0, b0 / a0, b1 / a0)
+    }
+
+    /// A first order [all-pass](https://en.wikipedia.org/wiki/All-pass_filter) filter.
+    ///
+    /// The frequency passed is the frequency at which the phase shift is π / 2. The shift goes
+    /// from zero at DC to π at the Nyquist frequency.
+    #[must_use]
+    pub fn all_pass(freq: unt::Freq) -> Self {
+        let tan = (freq.angular() / 2.0).tan();
+        let a = (tan - 1.0) / (tan + 1.0);
+        Self::new_normalized(a, a, 1.0)
+    }
+}
+
 /// [`DiffEq`] for a biquadratic (order 2) filter.
 pub type Biquad = LoDiffEq<3, 2>;
 
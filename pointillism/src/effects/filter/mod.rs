@@ -3,9 +3,13 @@
 use crate::prelude::*;
 
 mod coefficients;
+mod crossover;
 mod design;
+mod phaser;
 pub use coefficients::*;
+pub use crossover::{Band, Crossover, Multiband, Split};
 pub use design::*;
+pub use phaser::Phaser;
 
 /// A trait for a filter's function.
 ///
@@ -27,6 +31,7 @@ pub trait FilterMap {
 /// and [`buf::Circ`], where the former is preferred for very small buffers, while the latter is
 /// preferred otherwise. You may also use [`buf::EmptyRing`] if you want to ignore the
 /// inputs/outputs, at no cost.
+#[derive(Clone, Debug)]
 pub struct Filter<A: Audio, I: Ring, O: Ring, F: FilterMap>
 where
     I::Buf: BufferMut<Item = A>,
@@ -99,6 +104,7 @@ where
 /// Note that the implementation of [`Done`] assumes that the filtered signal stops right after the
 /// original. This isn't exactly accurate, even for the simplest filters, but it should be
 /// approximately so in practice.
+#[derive(Clone, Debug)]
 pub struct Filtered<S: Signal, I: Ring, O: Ring, F: FilterMap>
 where
     S::Sample: Audio,
@@ -0,0 +1,345 @@
+//! Implements the [`Phaser`] effect, built from modulated all-pass filters.
+
+use super::{Bilinear, LoFilter};
+use crate::prelude::*;
+
+/// A phaser, which runs a signal through a chain of first order [all-pass](Bilinear::all_pass)
+/// filters whose frequency is swept, and mixes the result with the original signal.
+///
+/// The all-pass filters don't change the volume of any frequency, but they do shift its phase, by
+/// up to half a turn each. Wherever the shifted signal ends up out of phase with the original,
+/// their mix has a notch. Each pair of stages adds another notch, and sweeping the frequency moves
+/// these around. Feeding the output back into the input deepens the effect.
+///
+/// The frequency sweeps exponentially between [`Self::low`] and [`Self::high`], according to a
+/// modulation signal with values between `0.0` and `1.0`. This is either an [`eff::Lfo`], or any
+/// envelope. A stereo modulation sweeps each channel separately, while a mono one or an envelope
+/// sweeps them all alike. The filter
+/// coefficients are recomputed every sample, from a frequency which glides towards the target, so
+/// that fast modulation doesn't cause clicks.
+///
+/// ## Example
+///
+/// We play a stereo phaser on a sustained chord.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let saw = |raw| gen::Loop::<smp::Stereo, _>::new(crv::Saw, unt::Freq::from_raw_default(raw));
+/// let chord = rtn::Mix::new(saw(unt::RawFreq::A2), saw(unt::RawFreq::E3));
+///
+/// let mut sgn = eff::flt::Phaser::new_lfo(
+///     eff::Volume::new(chord, unt::Vol::HALF),
+///     6,
+///     unt::Freq::from_hz_default(0.3),
+///     unt::Val::new(0.25),
+/// );
+/// sgn.feedback = unt::Vol::new(0.5);
+/// Song::new(sec(3.0), unt::SampleRate::default(), sgn).export("examples/phaser.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Phaser<S: Signal, M: SignalMut>
+where
+    S::Sample: Audio,
+{
+    /// The processed signal.
+    sgn: S,
+    /// The modulation signal.
+    sweep: M,
+    /// The lowest frequency of the sweep.
+    pub low: unt::Freq,
+    /// The highest frequency of the sweep.
+    pub high: unt::Freq,
+    /// The volume with which the output of the filters is fed back into their input.
+    ///
+    /// A negative gain inverts the signal fed back, which moves the notches.
+    pub feedback: unt::Vol,
+    /// The time it takes for the filter frequency to get most of the way to its target.
+    pub smoothing: unt::Time,
+    /// The volume of the filtered signal.
+    pub wet: unt::Vol,
+    /// The volume of the original signal.
+    pub dry: unt::Vol,
+
+    /// The all-pass filters for each channel.
+    stages: Vec<Vec<LoFilter<smp::Mono, 2, 1>>>,
+    /// The current frequency of the filters on each channel, as a natural logarithm.
+    freqs: Vec<f64>,
+    /// Whether the frequency should snap to its target on the next sample, instead of gliding.
+    snap: bool,
+    /// The last output of the filters.
+    filtered: S::Sample,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal, M: SignalMut> Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new phaser with the given number of stages and modulation signal.
+    ///
+    /// The frequency sweeps between 200 Hz and 4 kHz, with 5 ms of smoothing. The original and
+    /// filtered signals are mixed at half volume, and there's no feedback.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the number of stages isn't between 2 and 12.
+    pub fn new(sgn: S, stages: usize, sweep: M) -> Self {
+        assert!(
+            (2..=12).contains(&stages),
+            "a phaser must have between 2 and 12 stages"
+        );
+
+        let all_pass = || LoFilter::new_coefs(Bilinear::all_pass(unt::Freq::ZERO));
+        let mut res = Self {
+            sgn,
+            sweep,
+            low: unt::Freq::from_hz_default(200.0),
+            high: unt::Freq::from_hz_default(4000.0),
+            feedback: unt::Vol::ZERO,
+            smoothing: unt::Time::from_msec_default(5.0),
+            wet: unt::Vol::HALF,
+            dry: unt::Vol::HALF,
+            stages: (0..S::Sample::SIZE)
+                .map(|_| (0..stages).map(|_| all_pass()).collect())
+                .collect(),
+            freqs: vec![0.0; S::Sample::SIZE],
+            snap: true,
+            filtered: S::Sample::ZERO,
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the processed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the processed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// Returns a reference to the modulation signal.
+    pub const fn sweep(&self) -> &M {
+        &self.sweep
+    }
+
+    /// Returns a mutable reference to the modulation signal.
+    pub fn sweep_mut(&mut self) -> &mut M {
+        &mut self.sweep
+    }
+
+    /// The number of all-pass stages.
+    pub fn stages(&self) -> usize {
+        self.stages[0].len()
+    }
+
+    /// The current frequency of the all-pass filters on a given channel.
+    pub fn filter_freq(&self, channel: usize) -> unt::Freq {
+        unt::Freq::new(self.freqs[channel].exp())
+    }
+
+    /// Resets the filters, and snaps the frequency to its target.
+    pub fn clear(&mut self) {
+        for stage in self.stages.iter_mut().flatten() {
+            stage.retrigger();
+        }
+        self.snap = true;
+        self.filtered = S::Sample::ZERO;
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        let (low, high) = (self.low.samples.ln(), self.high.samples.ln());
+        let smoothing = (-1.0 / self.smoothing.samples.into_f64()).exp();
+
+        let sample = self.sgn.get();
+        let sweep = self.sweep.get();
+        for channel in 0..S::Sample::SIZE {
+            // Glides the frequency towards its target.
+            let pos = if channel == 0 {
+                sweep.fst()
+            } else {
+                sweep.snd()
+            };
+            let target = low + (high - low) * pos.clamp(0.0, 1.0);
+            let freq = &mut self.freqs[channel];
+            if self.snap {
+                *freq = target;
+            } else {
+                *freq = target + (*freq - target) * smoothing;
+            }
+
+            let coefs = Bilinear::all_pass(unt::Freq::new(freq.exp()));
+            let mut value = sample[channel] + self.filtered[channel] * self.feedback.gain;
+            for stage in &mut self.stages[channel] {
+                stage.func = coefs.clone();
+                value = stage.eval(smp::Mono(value)).0;
+            }
+            self.filtered[channel] = value;
+        }
+        self.snap = false;
+
+        self.output = sample * self.dry.gain + self.filtered * self.wet.gain;
+    }
+}
+
+impl<S: Signal> Phaser<S, eff::Lfo>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new phaser swept by an [`eff::Lfo`], with the given number of stages, rate,
+    /// and phase offset between channels.
+    ///
+    /// See [`Self::new`] for the other defaults.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the number of stages isn't between 2 and 12.
+    pub fn new_lfo(sgn: S, stages: usize, rate: unt::Freq, offset: unt::Val) -> Self {
+        Self::new(sgn, stages, eff::Lfo::new(rate, offset))
+    }
+}
+
+impl<S: Signal, M: SignalMut> Signal for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut, M: SignalMut> SignalMut for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.sweep.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.sweep.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency, M: SignalMut> Frequency for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base, M: SignalMut> Base for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+impl<S: Done, M: SignalMut> Done for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop, M: SignalMut> Stop for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic, M: SignalMut> Panic for Phaser<S, M>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the filtered signal keeps its volume, while the mix has a notch.
+    #[test]
+    fn notch() {
+        /// The peak of a sine wave through a phaser with fixed frequency.
+        fn peak(hz: f64, dry: unt::Vol) -> f64 {
+            let sine = gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_hz_default(hz));
+            let env = gen::Loop::<smp::Env, _>::new(map::Const::new(0.5), unt::Freq::ZERO);
+            let mut phaser = Phaser::new(sine, 4, env);
+            phaser.wet = unt::Vol::HALF;
+            phaser.dry = dry;
+
+            (0..20_000)
+                .map(|_| phaser.next().0.abs())
+                .skip(10_000)
+                .fold(0.0, f64::max)
+        }
+
+        for hz in [100.0, 440.0, 2000.0, 8000.0] {
+            assert_approx_eq::assert_approx_eq!(peak(hz, unt::Vol::ZERO), 0.5, 0.01);
+        }
+
+        // The center frequency of the sweep. Four stages shift its phase by a full turn. The notches
+        // lie where each stage shifts the phase by an eighth or three eighths of a turn.
+        let center = (200.0f64 * 4000.0).sqrt();
+        let ratio = std::f64::consts::FRAC_PI_8.tan();
+        assert_approx_eq::assert_approx_eq!(peak(center, unt::Vol::HALF), 1.0, 0.01);
+        assert!(peak(center * ratio, unt::Vol::HALF) < 0.05);
+        assert!(peak(center / ratio, unt::Vol::HALF) < 0.05);
+    }
+
+    /// Test that clearing the phaser snaps the frequency to its target, instead of gliding.
+    #[test]
+    fn clear() {
+        let env = gen::Loop::<smp::Env, _>::new(map::Const::new(1.0), unt::Freq::ZERO);
+        let silence = gen::Func::new(|| smp::Mono::ZERO);
+        let mut phaser = Phaser::new(silence, 2, env);
+        let high = unt::Freq::from_hz_default(1000.0);
+        phaser.high = high;
+
+        phaser.next();
+        assert!(phaser.filter_freq(0).samples > high.samples + 1e-6);
+
+        phaser.clear();
+        phaser.next();
+        assert_approx_eq::assert_approx_eq!(phaser.filter_freq(0).samples, high.samples);
+    }
+}
@@ -0,0 +1,65 @@
+//! Implements the [`Lfo`] used to modulate effects such as the [`eff::dly::ModDelay`] and the
+//! [`eff::flt::Phaser`].
+
+use crate::prelude::*;
+
+/// A stereo sine LFO, which outputs values between `0.0` and `1.0`, so that it can be used in place
+/// of an envelope.
+///
+/// The LFO on the right channel is ahead of the one on the left by a phase offset. On a mono
+/// signal, only the left channel is usually read.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lfo {
+    /// The frequency of the LFO.
+    pub rate: unt::Freq,
+    /// The phase offset between channels.
+    pub offset: unt::Val,
+    /// The phase of the LFO on the left channel.
+    phase: unt::Val,
+}
+
+impl Lfo {
+    /// Initializes a new LFO with the given rate, phase offset between channels, and initial phase.
+    #[must_use]
+    pub const fn new_phase(rate: unt::Freq, offset: unt::Val, phase: unt::Val) -> Self {
+        Self {
+            rate,
+            offset,
+            phase,
+        }
+    }
+
+    /// Initializes a new LFO with the given rate and phase offset between channels.
+    #[must_use]
+    pub const fn new(rate: unt::Freq, offset: unt::Val) -> Self {
+        Self::new_phase(rate, offset, unt::Val::ZERO)
+    }
+
+    /// The phase of the LFO on the left channel.
+    #[must_use]
+    pub const fn phase(&self) -> unt::Val {
+        self.phase
+    }
+}
+
+impl Signal for Lfo {
+    type Sample = smp::Stereo;
+
+    fn get(&self) -> smp::Stereo {
+        let pos = |offset: f64| {
+            let phase = unt::Val::fract(self.phase.inner() + offset);
+            0.5 + (std::f64::consts::TAU * phase.inner()).sin() / 2.0
+        };
+        smp::Stereo(pos(0.0), pos(self.offset.inner()))
+    }
+}
+
+impl SignalMut for Lfo {
+    fn advance(&mut self) {
+        self.phase.advance_freq(self.rate);
+    }
+
+    fn retrigger(&mut self) {
+        self.phase = unt::Val::ZERO;
+    }
+}
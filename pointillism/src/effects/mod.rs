@@ -8,19 +8,27 @@
 
 pub mod delay;
 pub mod distortion;
+pub mod dynamics;
 pub mod envelopes;
 pub mod filter;
 mod freq;
+mod lfo;
+mod pitch;
+pub mod reverb;
 mod trailing;
 mod vol;
 
 pub use delay as dly;
 pub use distortion as dst;
+pub use dynamics as dnm;
 pub use envelopes as env;
 pub use filter as flt;
+pub use reverb as rvb;
 pub mod pan;
 
 pub use freq::{Vib, Vibrato};
+pub use lfo::Lfo;
+pub use pitch::{Bend, PitchBend, PitchShift};
 pub use trailing::{Retrigger, Stopping, Trailing};
 pub use vol::{Gate, StopTremolo, Trem, Tremolo, Volume};
 
@@ -164,6 +172,17 @@ where
     }
 }
 
+/// Renders the original signal, and maps each sample in place.
+#[cfg(feature = "rayon")]
+impl<S: Render, F: Map<Input = S::Sample, Output = S::Sample>> Render for MapSgn<S, F> {
+    fn render(&mut self, buf: &mut [S::Sample], workers: &sgn::Workers) {
+        self.sgn_mut().render(buf, workers);
+        for sample in buf {
+            *sample = self.map().eval(*sample);
+        }
+    }
+}
+
 /// A [`MapSgn`] taking in a [`map::Pw`] function.
 pub type PwMapSgn<S, F> = MapSgn<S, map::Pw<<S as Signal>::Sample, F>>;
 
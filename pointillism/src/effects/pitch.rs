@@ -0,0 +1,536 @@
+//! Implements the [`PitchShift`] effect, which transposes a signal without changing its length.
+
+use crate::prelude::*;
+use eff::dly::Line;
+
+/// The order of the linear prediction used to preserve formants.
+const ORDER: usize = 16;
+/// The number of samples analyzed to preserve formants.
+const FRAME: usize = 1024;
+/// The number of samples between each analysis.
+const HOP: usize = 256;
+
+/// Estimates the spectral envelope of a single channel through linear prediction.
+///
+/// The signal is whitened through the inverse of this envelope before being shifted, and the
+/// envelope is then reapplied. This way, the resonances of the original signal stay in place.
+#[derive(Clone, Debug)]
+struct Lpc {
+    /// The last few samples of the signal.
+    frame: buf::Circ<buf::Dyn<smp::Mono>>,
+    /// The prediction coefficients `a₁, a₂, …`.
+    coefs: [f64; ORDER],
+    /// The last few inputs, from newest to oldest.
+    inputs: [f64; ORDER],
+    /// The last few outputs, from newest to oldest.
+    outputs: [f64; ORDER],
+    /// The number of samples since the last analysis.
+    count: usize,
+}
+
+impl Lpc {
+    /// Initializes a new linear predictor, which leaves the signal unchanged.
+    fn new() -> Self {
+        Self {
+            frame: buf::Circ::new(buf::Dyn::new(FRAME)),
+            coefs: [0.0; ORDER],
+            inputs: [0.0; ORDER],
+            outputs: [0.0; ORDER],
+            count: 0,
+        }
+    }
+
+    /// Resets the predictor.
+    fn clear(&mut self) {
+        self.frame.clear();
+        self.coefs = [0.0; ORDER];
+        self.inputs = [0.0; ORDER];
+        self.outputs = [0.0; ORDER];
+        self.count = 0;
+    }
+
+    /// Recomputes the prediction coefficients from the last frame, through the autocorrelation
+    /// method and the Levinson-Durbin recursion.
+    #[allow(clippy::cast_precision_loss)]
+    fn update(&mut self) {
+        let mut frame = [0.0; FRAME];
+        for (index, value) in frame.iter_mut().enumerate() {
+            let window = (std::f64::consts::PI * index as f64 / FRAME as f64).sin();
+            *value = self.frame.get(index).0 * window * window;
+        }
+
+        let mut corr = [0.0; ORDER + 1];
+        for (lag, value) in corr.iter_mut().enumerate() {
+            *value = frame.iter().zip(&frame[lag..]).map(|(x, y)| x * y).sum();
+        }
+
+        // Silence has no envelope. Otherwise, we add a tiny bit of noise for stability.
+        if corr[0] < 1e-9 {
+            return;
+        }
+        corr[0] *= 1.0 + 1e-4;
+
+        let mut coefs = [0.0; ORDER + 1];
+        coefs[0] = 1.0;
+        let mut error = corr[0];
+        for i in 1..=ORDER {
+            let acc: f64 = corr[i] + (1..i).map(|j| coefs[j] * corr[i - j]).sum::<f64>();
+            let k = -acc / error;
+
+            let prev = coefs;
+            for j in 1..i {
+                coefs[j] = prev[j] + k * prev[i - j];
+            }
+            coefs[i] = k;
+            error *= 1.0 - k * k;
+        }
+
+        self.coefs.copy_from_slice(&coefs[1..]);
+    }
+
+    /// Runs a new input through the inverse of the envelope.
+    fn analyze(&mut self, input: f64) -> f64 {
+        let residual = input
+            + self
+                .coefs
+                .iter()
+                .zip(&self.inputs)
+                .map(|(a, x)| a * x)
+                .sum::<f64>();
+        self.inputs.rotate_right(1);
+        self.inputs[0] = input;
+
+        self.frame.push(smp::Mono(input));
+        self.count += 1;
+        if self.count == HOP {
+            self.update();
+            self.count = 0;
+        }
+
+        residual
+    }
+
+    /// Applies the envelope to a new input.
+    fn synthesize(&mut self, input: f64) -> f64 {
+        let output = input
+            - self
+                .coefs
+                .iter()
+                .zip(&self.outputs)
+                .map(|(a, y)| a * y)
+                .sum::<f64>();
+        self.outputs.rotate_right(1);
+        self.outputs[0] = output;
+        output
+    }
+}
+
+/// Transposes a signal by some [`unt::Interval`], without changing its length.
+///
+/// The signal is written into a delay [`Line`], and read back by two grains, which move through it
+/// at a speed given by the interval. Once a grain reaches the end of the window, it jumps back to
+/// the other end. Each grain is faded in and out by a Hann window, and they're half a window apart,
+/// so that their crossfades add up to a constant volume.
+///
+/// Longer windows give smoother results on sustained sounds, whereas shorter ones smear transients
+/// less. On average, the output is delayed by half a window.
+///
+/// ## Formants
+///
+/// Shifting a voice or an instrument moves its resonances, or formants, along with its pitch. This
+/// can make it sound unnatural. In the formant-preserving mode, see [`Self::new_formant`], the
+/// spectral envelope of the signal is estimated through linear prediction. The signal is flattened
+/// through the inverse of this envelope, shifted, and then has the envelope applied back, so that
+/// the formants stay in place.
+///
+/// ## Modulation
+///
+/// The [`Self::interval`] can be changed at any point. To modulate it through an envelope, see
+/// [`PitchBend`].
+///
+/// ## Example
+///
+/// We play a note along with a copy shifted up a fifth.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let c4 = unt::Freq::from_raw_default(unt::RawFreq::C4);
+/// let tri = || gen::Loop::<smp::Mono, _>::new(crv::Tri, c4);
+/// let window = unt::Time::from_msec_default(40.0);
+/// let shift = eff::PitchShift::new(tri(), unt::Interval::P5, window);
+/// let sgn = rtn::Mix::new(tri(), shift);
+/// Song::new(sec(2.0), unt::SampleRate::default(), eff::Volume::new(sgn, unt::Vol::HALF))
+///     .export("examples/pitch_shift.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PitchShift<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The transposed signal.
+    sgn: S,
+    /// The interval by which the signal is transposed.
+    pub interval: unt::Interval,
+
+    /// The length of the window, in samples.
+    window: f64,
+    /// The delay line the grains are read from.
+    line: Line<S::Sample, buf::int::Hermite<S::Sample>>,
+    /// The position of the first grain within the window.
+    phase: f64,
+    /// The linear predictors for each channel, in the formant-preserving mode.
+    formants: Option<Vec<Lpc>>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal> PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a pitch shifter with a given interval and window length, and optionally linear
+    /// predictors for each channel.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the window is zero.
+    fn new_with(
+        sgn: S,
+        interval: unt::Interval,
+        window: unt::Time,
+        formants: Option<Vec<Lpc>>,
+    ) -> Self {
+        assert!(window > unt::Time::ZERO, "the window must be positive");
+        let line = Line::new(window + unt::Time::from_samples(2));
+        let window = window.samples.into_f64();
+        let mut res = Self {
+            sgn,
+            interval,
+            window,
+            line,
+            phase: 0.0,
+            formants,
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Initializes a pitch shifter with a given interval and window length.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the window is zero.
+    pub fn new(sgn: S, interval: unt::Interval, window: unt::Time) -> Self {
+        Self::new_with(sgn, interval, window, None)
+    }
+
+    /// Initializes a pitch shifter which preserves formants, with a given interval and window
+    /// length.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the window is zero.
+    pub fn new_formant(sgn: S, interval: unt::Interval, window: unt::Time) -> Self {
+        let formants = (0..S::Sample::SIZE).map(|_| Lpc::new()).collect();
+        Self::new_with(sgn, interval, window, Some(formants))
+    }
+
+    /// Returns a reference to the transposed signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the transposed signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The length of the window.
+    pub fn window(&self) -> unt::Time {
+        unt::Time::new(unt::FracInt::from_f64(self.window))
+    }
+
+    /// Whether formants are preserved.
+    pub const fn formant(&self) -> bool {
+        self.formants.is_some()
+    }
+
+    /// Clears the delay line and the formant analysis.
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.phase = 0.0;
+        if let Some(formants) = &mut self.formants {
+            for lpc in formants {
+                lpc.clear();
+            }
+        }
+    }
+
+    /// Clears the state, and processes the current sample again.
+    fn retrigger_inner(&mut self) {
+        self.clear();
+        self.process();
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        let input = match &mut self.formants {
+            Some(formants) => {
+                S::Sample::from_fn(|channel| formants[channel].analyze(sample[channel]))
+            }
+            None => sample,
+        };
+
+        // Reads both grains.
+        let min = self.line.min().samples.into_f64();
+        let mut shifted = S::Sample::ZERO;
+        for grain in [0.0, 0.5] {
+            let pos = (self.phase + grain).fract();
+            let time = unt::Time::new(unt::FracInt::from_f64(min + pos * self.window));
+            let gain = (std::f64::consts::PI * pos).sin();
+            shifted += self.line.read(time) * (gain * gain);
+        }
+        self.line.push(input);
+
+        // A grain read at the same speed as the signal stays in place.
+        self.phase = (self.phase + (1.0 - self.interval.ratio) / self.window).rem_euclid(1.0);
+
+        self.output = match &mut self.formants {
+            Some(formants) => {
+                S::Sample::from_fn(|channel| formants[channel].synthesize(shifted[channel]))
+            }
+            None => shifted,
+        };
+    }
+}
+
+impl<S: Signal> Signal for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.retrigger_inner();
+    }
+}
+
+impl<S: Frequency> Frequency for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the delay from the window.
+impl<S: Done> Done for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for PitchShift<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+/// The function that modulates the interval of a [`PitchShift`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bend {
+    /// Base interval.
+    pub base: unt::Interval,
+}
+
+impl Bend {
+    /// Initializes a new [`Bend`].
+    #[must_use]
+    pub const fn new(base: unt::Interval) -> Self {
+        Self { base }
+    }
+}
+
+impl<S: Signal> map::Val<PitchShift<S>> for Bend
+where
+    S::Sample: Audio,
+{
+    type Val = smp::Env;
+    fn modify_val(&mut self, sgn: &mut PitchShift<S>, bend: smp::Env) {
+        sgn.interval = unt::Interval::new(self.base.ratio * bend.0);
+    }
+}
+
+/// Transposes a signal by an interval that changes according to an envelope.
+///
+/// The envelope serves as a multiplier for a base interval.
+pub type PitchBend<S, E> = eff::MutSgn<PitchShift<S>, E, Bend>;
+
+impl<S: Signal, E: Signal<Sample = smp::Env>> PitchBend<S, E>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new [`PitchBend`] from a pitch shifter and an envelope. The base interval is
+    /// the one the shifter was initialized with.
+    pub fn new_bend(shift: PitchShift<S>, env: E) -> Self {
+        let base = shift.interval;
+        Self::new(shift, env, Bend::new(base))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Counts the upward zero crossings in a second of output, after some time to settle.
+    fn crossings<S: SignalMut<Sample = smp::Mono>>(mut sgn: S) -> usize {
+        let len = unt::Time::from_sec_default(1.0).samples.int();
+        let mut prev = 0.0;
+        let mut count = 0;
+        for time in 0..(2 * len) {
+            let sample = sgn.next().0;
+            if time >= len && prev < 0.0 && sample >= 0.0 {
+                count += 1;
+            }
+            prev = sample;
+        }
+        count
+    }
+
+    /// Test that a sine wave is shifted up an octave.
+    #[test]
+    fn octave() {
+        let sine = gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_hz_default(440.0));
+        let window = unt::Time::from_msec_default(40.0);
+        let count = crossings(PitchShift::new(sine, unt::Interval::OCTAVE, window));
+        assert!((860..=900).contains(&count), "{count} crossings");
+    }
+
+    /// Test that the formant analysis and resynthesis leave a steady signal unchanged, when it's not
+    /// transposed.
+    #[test]
+    fn formant() {
+        let saw = || gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_hz_default(220.0));
+        let window = unt::Time::from_samples(1000);
+        let mut sgn = saw();
+        let mut shift = PitchShift::new_formant(saw(), unt::Interval::UNISON, window);
+
+        // The original signal is delayed by half a window, plus the minimum delay of the line.
+        let delay = 502;
+        let original: Vec<_> = (0..20_000).map(|_| sgn.next().0).collect();
+        let shifted: Vec<_> = (0..20_000).map(|_| shift.next().0).collect();
+        for (x, y) in original.iter().zip(&shifted[delay..]).skip(10_000) {
+            assert_approx_eq::assert_approx_eq!(x, y, 0.05);
+        }
+    }
+
+    /// Out of some frequency bands, returns the center of the one where a signal is loudest.
+    fn loudest<S: SignalMut<Sample = smp::Mono>>(mut sgn: S, centers: &[unt::Freq]) -> unt::Freq {
+        let len = unt::Time::from_sec_default(1.0).samples.int();
+        for _ in 0..len {
+            sgn.advance();
+        }
+        let samples: Vec<_> = (0..len).map(|_| sgn.next()).collect();
+
+        let energy = |center: unt::Freq| {
+            let coefs = eff::flt::Biquad::band_pass(center, unt::QFactor::new(4.0));
+            let mut filter = eff::flt::LoFilter::new_coefs(coefs);
+            samples
+                .iter()
+                .map(|&sample| filter.eval(sample).0.powi(2))
+                .sum::<f64>()
+        };
+        centers
+            .iter()
+            .copied()
+            .max_by(|&x, &y| energy(x).total_cmp(&energy(y)))
+            .unwrap()
+    }
+
+    /// Test that the resonance of a signal moves with the pitch, unless formants are preserved.
+    #[test]
+    fn resonance() {
+        let hz = unt::Freq::from_hz_default;
+        let res = hz(1500.0);
+        let voice = || {
+            let saw = gen::Loop::<smp::Mono, _>::new(crv::Saw, hz(110.0));
+            let coefs = eff::flt::Biquad::band_pass(res, unt::QFactor::new(10.0));
+            eff::flt::LoFiltered::new_coefs(saw, coefs)
+        };
+        let window = unt::Time::from_msec_default(40.0);
+
+        // Bands around the resonance, and around where it moves when shifted up a fifth.
+        let centers = [hz(1000.0), res, hz(2250.0), hz(3000.0)];
+        let plain = PitchShift::new(voice(), unt::Interval::P5, window);
+        let formant = PitchShift::new_formant(voice(), unt::Interval::P5, window);
+        assert_eq!(loudest(plain, &centers), centers[2]);
+        assert_eq!(loudest(formant, &centers), centers[1]);
+    }
+
+    /// Test that an envelope multiplies the base interval of the shifter.
+    #[test]
+    fn bend() {
+        let sine = gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_hz_default(440.0));
+        let window = unt::Time::from_msec_default(40.0);
+        let shift = PitchShift::new(sine, unt::Interval::OCTAVE, window);
+        let env = gen::Loop::<smp::Env, _>::new(map::Const::new(0.75), unt::Freq::ZERO);
+        let count = crossings(PitchBend::new_bend(shift, env));
+        assert!((640..=680).contains(&count), "{count} crossings");
+    }
+}
@@ -0,0 +1,572 @@
+//! Implements the [`Convolution`] reverb.
+
+use super::fft::{fft, Complex};
+use crate::prelude::*;
+
+/// Converts a time into a number of samples.
+///
+/// ## Panics
+///
+/// On a 32-bit machine, panics if the time is too large.
+fn samples(time: unt::Time) -> usize {
+    time.samples.int().try_into().expect("time too large")
+}
+
+/// Trims a buffer to the given range of samples.
+fn trim<A: Audio>(buf: &mut buf::Dyn<A>, start: usize, length: usize) {
+    let start = start.min(buf.data.len());
+    buf.data.drain(..start);
+    buf.data.truncate(length);
+}
+
+/// Linearly fades out the given number of samples at the end of a buffer.
+#[allow(clippy::cast_precision_loss)]
+fn fade<A: Audio>(buf: &mut buf::Dyn<A>, length: usize) {
+    let len = buf.data.len();
+    let length = length.min(len);
+    for (index, sample) in buf.data[(len - length)..].iter_mut().enumerate() {
+        *sample *= 1.0 - (index + 1) as f64 / length as f64;
+    }
+}
+
+/// Returns a single channel of a buffer.
+fn channel<A: Audio>(buf: &buf::Dyn<A>, channel: usize) -> Vec<f64> {
+    buf.data.iter().map(|sample| sample[channel]).collect()
+}
+
+/// Averages some channels.
+#[allow(clippy::cast_precision_loss)]
+fn average(channels: &[Vec<f64>]) -> Vec<f64> {
+    let len = channels.iter().map(Vec::len).max().unwrap_or(0);
+    let mut res = vec![0.0; len];
+    for channel in channels {
+        for (x, y) in res.iter_mut().zip(channel) {
+            *x += y;
+        }
+    }
+
+    let count = channels.len() as f64;
+    for x in &mut res {
+        *x /= count;
+    }
+    res
+}
+
+/// An impulse response, used in a [`Convolution`] reverb.
+///
+/// Impulse responses are often recorded in real rooms, and are loaded from WAV files through
+/// [`buf::Dyn::from_wav`], or [`Ir::from_wav`].
+#[derive(Clone, Debug)]
+pub enum Ir {
+    /// A mono impulse response, which is applied to every channel separately.
+    Mono(buf::Dyn<smp::Mono>),
+    /// A stereo impulse response. Each channel is applied to the corresponding channel of the
+    /// signal.
+    Stereo(buf::Dyn<smp::Stereo>),
+    /// A true stereo impulse response, made out of the responses to the left and right input
+    /// channels. Each of these is a stereo buffer, whose channels are added to the corresponding
+    /// output channels.
+    TrueStereo(buf::Dyn<smp::Stereo>, buf::Dyn<smp::Stereo>),
+}
+
+impl Ir {
+    /// Loads an impulse response from a mono or stereo WAV file.
+    ///
+    /// ## Errors
+    ///
+    /// Will error in case of an IO error, or if the WAV file is in an unsupported format.
+    #[cfg(feature = "hound")]
+    pub fn from_wav<P: AsRef<std::path::Path>>(path: P) -> Result<Self, buf::wav::Error> {
+        match buf::Dyn::<smp::Mono>::from_wav(&path) {
+            Err(buf::wav::Error::ChannelMismatch { .. }) => {
+                Ok(Self::Stereo(buf::Dyn::<smp::Stereo>::from_wav(path)?))
+            }
+            res => res.map(Self::Mono),
+        }
+    }
+
+    /// Loads a true stereo impulse response from two stereo WAV files, containing the responses to
+    /// the left and right input channels.
+    ///
+    /// ## Errors
+    ///
+    /// Will error in case of an IO error, if the WAV files are in an unsupported format, or if
+    /// they aren't stereo.
+    #[cfg(feature = "hound")]
+    pub fn from_wav_true_stereo<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+        left: P,
+        right: Q,
+    ) -> Result<Self, buf::wav::Error> {
+        Ok(Self::TrueStereo(
+            buf::Dyn::<smp::Stereo>::from_wav(left)?,
+            buf::Dyn::<smp::Stereo>::from_wav(right)?,
+        ))
+    }
+
+    /// The length of the impulse response.
+    #[must_use]
+    pub fn time(&self) -> unt::Time {
+        match self {
+            Self::Mono(buf) => buf.time(),
+            Self::Stereo(buf) => buf.time(),
+            Self::TrueStereo(left, right) => left.time().max(right.time()),
+        }
+    }
+
+    /// Trims the impulse response, so that it starts at the given time and has at most the given
+    /// length.
+    ///
+    /// This can be used to remove the silence at the start of a recording, or to cut a long tail
+    /// short. In the latter case, consider also using [`Self::fade`] to avoid a sudden cutoff.
+    pub fn trim(&mut self, start: unt::Time, length: unt::Time) {
+        let (start, length) = (samples(start), samples(length));
+        match self {
+            Self::Mono(buf) => trim(buf, start, length),
+            Self::Stereo(buf) => trim(buf, start, length),
+            Self::TrueStereo(left, right) => {
+                trim(left, start, length);
+                trim(right, start, length);
+            }
+        }
+    }
+
+    /// Linearly fades out the end of the impulse response over the given time.
+    pub fn fade(&mut self, length: unt::Time) {
+        let length = samples(length);
+        match self {
+            Self::Mono(buf) => fade(buf, length),
+            Self::Stereo(buf) => fade(buf, length),
+            Self::TrueStereo(left, right) => {
+                fade(left, length);
+                fade(right, length);
+            }
+        }
+    }
+
+    /// Returns the impulse responses from each input channel to each output channel, for a signal
+    /// with the given number of channels.
+    ///
+    /// A mono signal is fed into both inputs of a stereo impulse response, and its outputs are
+    /// averaged.
+    fn paths(&self, size: usize) -> Vec<(usize, usize, Vec<f64>)> {
+        let stereo = size == 2;
+        match self {
+            Self::Mono(buf) => (0..size).map(|c| (c, c, channel(buf, 0))).collect(),
+            Self::Stereo(buf) => {
+                let (left, right) = (channel(buf, 0), channel(buf, 1));
+                if stereo {
+                    vec![(0, 0, left), (1, 1, right)]
+                } else {
+                    vec![(0, 0, average(&[left, right]))]
+                }
+            }
+            Self::TrueStereo(left, right) => {
+                let (ll, lr) = (channel(left, 0), channel(left, 1));
+                let (rl, rr) = (channel(right, 0), channel(right, 1));
+                if stereo {
+                    vec![(0, 0, ll), (0, 1, lr), (1, 0, rl), (1, 1, rr)]
+                } else {
+                    // The average of both outputs, when the same signal is fed into both inputs.
+                    let mut sum = average(&[ll, lr, rl, rr]);
+                    for x in &mut sum {
+                        *x *= 2.0;
+                    }
+                    vec![(0, 0, sum)]
+                }
+            }
+        }
+    }
+}
+
+/// An impulse response from an input channel to an output channel, split into blocks and
+/// transformed into the frequency domain.
+#[derive(Clone, Debug)]
+struct Kernel {
+    /// The input channel.
+    input: usize,
+    /// The output channel.
+    output: usize,
+    /// The spectrum of each block.
+    parts: Vec<Vec<Complex>>,
+}
+
+/// A convolution reverb, which applies a recorded [impulse response](Ir) to a signal.
+///
+/// This uses uniformly partitioned convolution. The impulse response is split into blocks of equal
+/// size, whose spectra are computed through an FFT. The signal is processed in blocks of this same
+/// size, so that the reverb tail is always delayed by one block. Smaller blocks mean less latency,
+/// but more computation.
+///
+/// The pre-delay is the time between the original signal and the reverb tail. This can't be
+/// shorter than a block.
+///
+/// ## Example
+///
+/// We record an impulse response out of some exponentially decaying noise, and apply it to some
+/// plucks.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+///
+/// // Two seconds of exponentially decaying noise.
+/// let mut noise = gen::NoiseGen::<smp::Mono>::new();
+/// let len = sec(2.0).samples.int() as usize;
+/// let ir = buf::Dyn::from_data(
+///     (0..len)
+///         .map(|t| noise.next() * (-6.9 * t as f64 / len as f64).exp() * 0.05)
+///         .collect(),
+/// );
+///
+/// let pluck = ctr::Loop::new(
+///     vec![sec(0.5)],
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Mono, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3)),
+///         eff::env::Ar::new(sec(0.005), sec(0.1)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let sgn = eff::rvb::Convolution::new(
+///     pluck,
+///     &eff::rvb::Ir::Mono(ir),
+///     256,
+///     unt::Time::from_msec_default(20.0),
+/// );
+/// Song::new(sec(3.0), unt::SampleRate::default(), sgn).export("examples/convolution.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Convolution<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The reverberated signal.
+    sgn: S,
+    /// The volume of the reverb tail.
+    pub wet: unt::Vol,
+    /// The volume of the original signal.
+    pub dry: unt::Vol,
+
+    /// The size of each block.
+    block: usize,
+    /// The impulse responses between each channel.
+    kernels: Vec<Kernel>,
+    /// The last two blocks of the signal, for each channel.
+    inputs: [Vec<f64>; 2],
+    /// The spectra of the last few blocks of the signal, for each channel.
+    spectra: [Vec<Vec<Complex>>; 2],
+    /// The index of the last spectrum written.
+    head: usize,
+    /// The current block of the reverb tail, for each channel.
+    outputs: [Vec<f64>; 2],
+    /// The position within the current block.
+    pos: usize,
+    /// Memory in which FFTs are computed.
+    scratch: Vec<Complex>,
+    /// Delays the reverb tail, so that it matches the pre-delay.
+    delay: buf::Circ<buf::Dyn<S::Sample>>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal> Convolution<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new convolution reverb, with the given block size and pre-delay.
+    ///
+    /// The volume of the reverb tail is set to -10 dB, and the original signal is left at full
+    /// volume.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the block size isn't a power of two. On a 32-bit machine, also panics if the
+    /// pre-delay is too large.
+    pub fn new(sgn: S, ir: &Ir, block: usize, pre_delay: unt::Time) -> Self {
+        assert!(block.is_power_of_two(), "block size must be a power of two");
+
+        let kernels: Vec<_> = ir
+            .paths(S::Sample::SIZE)
+            .into_iter()
+            .map(|(input, output, data)| Kernel {
+                input,
+                output,
+                parts: data
+                    .chunks(block)
+                    .map(|chunk| {
+                        let mut part = vec![Complex::ZERO; 2 * block];
+                        for (x, &y) in part.iter_mut().zip(chunk) {
+                            x.re = y;
+                        }
+                        fft(&mut part, false);
+                        part
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let count = kernels
+            .iter()
+            .map(|kernel| kernel.parts.len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let delay = samples(pre_delay).saturating_sub(block) + 1;
+
+        let mut res = Self {
+            sgn,
+            wet: unt::Vol::MDB10,
+            dry: unt::Vol::FULL,
+            block,
+            kernels,
+            inputs: [vec![0.0; 2 * block], vec![0.0; 2 * block]],
+            spectra: [
+                vec![vec![Complex::ZERO; 2 * block]; count],
+                vec![vec![Complex::ZERO; 2 * block]; count],
+            ],
+            head: 0,
+            outputs: [vec![0.0; block], vec![0.0; block]],
+            pos: 0,
+            scratch: vec![Complex::ZERO; 2 * block],
+            delay: buf::Circ::new(buf::Dyn::new(delay)),
+            output: S::Sample::ZERO,
+        };
+
+        res.process();
+        res
+    }
+
+    /// Returns a reference to the reverberated signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the reverberated signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The size of each block.
+    pub const fn block(&self) -> usize {
+        self.block
+    }
+
+    /// The time between the original signal and the reverb tail.
+    pub fn pre_delay(&self) -> unt::Time {
+        unt::Time::from_samples((self.block + self.delay.capacity() - 1) as u64)
+    }
+
+    /// Clears the reverb tail.
+    pub fn clear(&mut self) {
+        for channel in 0..2 {
+            self.inputs[channel].fill(0.0);
+            self.outputs[channel].fill(0.0);
+            for spectrum in &mut self.spectra[channel] {
+                spectrum.fill(Complex::ZERO);
+            }
+        }
+        self.delay.clear();
+        self.pos = 0;
+    }
+
+    /// Convolves the last block of the signal with the impulse response.
+    #[allow(clippy::cast_precision_loss)]
+    fn convolve(&mut self) {
+        let count = self.spectra[0].len();
+        self.head = (self.head + 1) % count;
+
+        // Computes the spectrum of the last two blocks.
+        for channel in 0..S::Sample::SIZE {
+            let input = &mut self.inputs[channel];
+            let spectrum = &mut self.spectra[channel][self.head];
+            for (x, &y) in spectrum.iter_mut().zip(input.iter()) {
+                *x = Complex::new(y, 0.0);
+            }
+            fft(spectrum, false);
+            input.copy_within(self.block.., 0);
+        }
+
+        // Multiplies each block of the impulse response with the corresponding block of the
+        // signal.
+        let norm = (2 * self.block) as f64;
+        for channel in 0..S::Sample::SIZE {
+            self.scratch.fill(Complex::ZERO);
+            for kernel in self
+                .kernels
+                .iter()
+                .filter(|kernel| kernel.output == channel)
+            {
+                for (index, part) in kernel.parts.iter().enumerate() {
+                    let spectrum = &self.spectra[kernel.input][(self.head + count - index) % count];
+                    for ((x, &y), &z) in self.scratch.iter_mut().zip(spectrum).zip(part) {
+                        *x += y * z;
+                    }
+                }
+            }
+
+            // By overlap-save, only the second half of the result is valid.
+            fft(&mut self.scratch, true);
+            for (x, y) in self.outputs[channel]
+                .iter_mut()
+                .zip(&self.scratch[self.block..])
+            {
+                *x = y.re / norm;
+            }
+        }
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        let index = self.block + self.pos;
+        S::Sample::for_each(|channel| self.inputs[channel][index] = sample[channel]);
+        let tail = S::Sample::from_fn(|channel| self.outputs[channel][self.pos]);
+
+        self.pos += 1;
+        if self.pos == self.block {
+            self.convolve();
+            self.pos = 0;
+        }
+
+        self.delay.push(tail);
+        let tail = self.delay.get(self.delay.capacity() - 1);
+        self.output = sample * self.dry.gain + tail * self.wet.gain;
+    }
+}
+
+impl<S: Signal> Signal for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the reverb tail.
+impl<S: Done> Done for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for Convolution<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test the convolution against a direct computation.
+    #[test]
+    fn direct() {
+        let ir = [1.0, 0.5, -0.25, 0.0, 0.125, 0.3, -0.7];
+        #[allow(clippy::cast_precision_loss)]
+        let input = |time: usize| smp::Mono((time as f64 * 0.7).sin());
+
+        let mut time = 0;
+        let sgn = gen::Func::new(move || {
+            time += 1;
+            input(time - 1)
+        });
+        let mut conv = Convolution::new(
+            sgn,
+            &Ir::Mono(buf::Dyn::from_data(ir.map(smp::Mono).to_vec())),
+            2,
+            unt::Time::ZERO,
+        );
+        conv.wet = unt::Vol::FULL;
+        conv.dry = unt::Vol::ZERO;
+
+        // The first sample is output before the original signal is muted.
+        conv.advance();
+        for time in 1..100 {
+            let expected: f64 = (0..ir.len())
+                .filter(|&index| index + 2 <= time)
+                .map(|index| ir[index] * input(time - 2 - index).0)
+                .sum();
+            assert_approx_eq::assert_approx_eq!(conv.next().0, expected);
+        }
+    }
+
+    /// Test that trimming and fading an impulse response work as expected.
+    #[test]
+    fn trim_fade() {
+        let mut ir = Ir::Mono(buf::Dyn::from_data(vec![smp::Mono(1.0); 10]));
+        ir.trim(unt::Time::from_samples(2), unt::Time::from_samples(6));
+        ir.fade(unt::Time::from_samples(4));
+
+        let Ir::Mono(buf) = ir else { unreachable!() };
+        let data: Vec<_> = buf.data.iter().map(|x| x.0).collect();
+        assert_eq!(data.len(), 6);
+        for (x, y) in data.iter().zip([1.0, 1.0, 0.75, 0.5, 0.25, 0.0]) {
+            assert_approx_eq::assert_approx_eq!(x, y);
+        }
+    }
+}
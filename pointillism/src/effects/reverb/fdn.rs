@@ -0,0 +1,584 @@
+//! Implements the [`Fdn`] reverb.
+
+use crate::prelude::*;
+
+/// The orthogonal matrix which mixes the outputs of the delay lines of an [`Fdn`], before feeding
+/// them back.
+///
+/// Since the matrix is orthogonal, it preserves the energy in the network. This means that all of
+/// the decay comes from the damping filters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Matrix {
+    /// A normalized [Hadamard matrix](https://en.wikipedia.org/wiki/Hadamard_matrix), which mixes
+    /// every line into every other with the same weight. This can only be used when the number of
+    /// lines is a power of two.
+    #[default]
+    Hadamard,
+    /// A [Householder reflection](https://en.wikipedia.org/wiki/Householder_transformation) along
+    /// the all-ones vector. This can be used with any number of lines, but mixes them less
+    /// thoroughly.
+    Householder,
+}
+
+impl Matrix {
+    /// Multiplies a vector by the matrix in place.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if a Hadamard matrix is applied to a vector whose length isn't a power of two.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn apply(self, values: &mut [f64]) {
+        let n = values.len();
+        match self {
+            Self::Hadamard => {
+                assert!(
+                    n.is_power_of_two(),
+                    "Hadamard matrices need a power of two size"
+                );
+
+                // The fast Walsh-Hadamard transform.
+                let mut len = 1;
+                while len < n {
+                    for start in (0..n).step_by(2 * len) {
+                        for i in start..(start + len) {
+                            let (x, y) = (values[i], values[i + len]);
+                            values[i] = x + y;
+                            values[i + len] = x - y;
+                        }
+                    }
+                    len *= 2;
+                }
+
+                let norm = (n as f64).sqrt().recip();
+                for value in values {
+                    *value *= norm;
+                }
+            }
+
+            Self::Householder => {
+                let sum: f64 = values.iter().sum();
+                let diff = 2.0 * sum / n as f64;
+                for value in values {
+                    *value -= diff;
+                }
+            }
+        }
+    }
+}
+
+/// An early reflection in an [`Fdn`] reverb.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tap {
+    /// The time after which the reflection is heard.
+    pub time: unt::Time,
+    /// The gain of the reflection on each channel.
+    pub gain: smp::Stereo,
+}
+
+impl Tap {
+    /// Initializes a new early reflection.
+    #[must_use]
+    pub const fn new(time: unt::Time, gain: smp::Stereo) -> Self {
+        Self { time, gain }
+    }
+}
+
+/// The default lengths of the delay lines of an [`Fdn`], in samples at 44.1 kHz. These are prime
+/// numbers, so that the echoes of the different lines don't line up.
+const LENS: [u64; 8] = [1327, 1523, 1693, 1871, 2053, 2269, 2459, 2671];
+
+/// The default early reflections of an [`Fdn`], as a time in samples at 44.1 kHz, and a gain on
+/// each channel.
+const TAPS: [(u64, f64, f64); 6] = [
+    (331, 0.6, 0.3),
+    (487, 0.3, 0.55),
+    (743, 0.5, 0.25),
+    (997, 0.2, 0.45),
+    (1297, 0.35, 0.2),
+    (1627, 0.15, 0.3),
+];
+
+/// Converts a number of samples at 44.1 kHz into a time at the given sample rate.
+#[allow(clippy::cast_precision_loss)]
+fn scale(samples: u64, sample_rate: unt::SampleRate) -> unt::Time {
+    let raw = unt::RawTime::new(samples as f64 / f64::from(unt::SampleRate::CD));
+    unt::Time::from_raw(raw, sample_rate)
+}
+
+/// A single delay line in an [`Fdn`].
+#[derive(Clone, Debug)]
+struct Line {
+    /// The delay line.
+    delay: buf::Circ<buf::Dyn<smp::Mono>>,
+    /// The unmodulated length of the line in samples.
+    len: f64,
+    /// The coefficients `(b0, a1)` of the damping filter.
+    coefs: (f64, f64),
+    /// The last output of the damping filter.
+    filter: f64,
+}
+
+impl Line {
+    /// Reads the value from the given number of samples ago, through Hermite interpolation.
+    fn read(&self, time: f64) -> f64 {
+        // The integer part is at least 2.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let int = time as usize;
+        let frac = time - time.floor();
+        buf::int::hermite(
+            self.delay.get(int - 2),
+            self.delay.get(int - 1),
+            self.delay.get(int),
+            self.delay.get(int + 1),
+            unt::Val::new(frac),
+        )
+        .0
+    }
+
+    /// Computes the coefficients of a one-pole low-pass, such that the line decays according to the
+    /// given RT60 times at zero and at the Nyquist frequency.
+    fn damping(&self, low: unt::Time, high: unt::Time) -> (f64, f64) {
+        let gain = |rt60: unt::Time| 10f64.powf(-3.0 * self.len / rt60.samples.into_f64());
+        let (low, high) = (gain(low), gain(high));
+        let a = (low - high) / (low + high);
+        (low * (1.0 - a), a)
+    }
+
+    /// Runs a new input through the damping filter.
+    fn damp(&mut self, input: f64) -> f64 {
+        let (b0, a1) = self.coefs;
+        self.filter = b0 * input + a1 * self.filter;
+        self.filter
+    }
+}
+
+/// A [feedback delay network](https://ccrma.stanford.edu/~jos/pasp/Feedback_Delay_Networks_FDN.html)
+/// reverb.
+///
+/// The input is fed into a number of delay lines of different lengths, whose outputs are mixed
+/// through an orthogonal [`Matrix`] and fed back into them. Each line has a damping filter, set so
+/// that the reverb decays by 60 dB after a given time. This decay time can differ between low and
+/// high frequencies.
+///
+/// The lengths of the delay lines are slowly modulated, each with a different phase. This breaks up
+/// the resonances of the network, which would otherwise sound metallic. Separately, the input is
+/// read at a few early reflection [`Taps`](Tap), which are added to the output.
+///
+/// The even delay lines make up the left channel of the reverb tail, while the odd lines make up
+/// the right channel. In the case of mono audio, both are mixed together.
+///
+/// ## Example
+///
+/// We add a long, dark reverb to some plucks.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let pluck = ctr::Loop::new(
+///     vec![sec(0.5)],
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Stereo, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3)),
+///         eff::env::Ar::new(sec(0.005), sec(0.1)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let mut sgn = eff::rvb::Fdn::new_default(pluck);
+/// sgn.rt60_low = sec(3.0);
+/// sgn.rt60_high = sec(0.8);
+/// Song::new(sec(3.0), unt::SampleRate::default(), sgn).export("examples/fdn.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Fdn<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The reverberated signal.
+    sgn: S,
+    /// The feedback matrix.
+    matrix: Matrix,
+    /// The early reflections.
+    taps: Vec<Tap>,
+
+    /// The time it takes for low frequencies to decay by 60 dB.
+    pub rt60_low: unt::Time,
+    /// The time it takes for high frequencies to decay by 60 dB.
+    pub rt60_high: unt::Time,
+    /// The frequency at which the lengths of the delay lines are modulated.
+    pub mod_freq: unt::Freq,
+    /// How much the lengths of the delay lines are modulated in either direction. This can't be
+    /// changed, as it determines the size of the delay lines.
+    mod_depth: f64,
+    /// The volume of the reverb tail.
+    pub wet: unt::Vol,
+    /// The volume of the early reflections.
+    pub early: unt::Vol,
+    /// The volume of the original signal.
+    pub dry: unt::Vol,
+
+    /// The delay lines.
+    lines: Vec<Line>,
+    /// Stores the input, so that the early reflections can be read.
+    input: buf::Circ<buf::Dyn<smp::Mono>>,
+    /// The decay times for which the damping filters were last computed.
+    damped: Option<(unt::Time, unt::Time)>,
+    /// The phase of the modulation.
+    phase: unt::Val,
+    /// Stores the values to be mixed by the feedback matrix.
+    values: Vec<f64>,
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal> Fdn<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new FDN reverb.
+    ///
+    /// The decay times are set to 2 seconds, the modulation frequency is set to 0.5 Hz, the volume
+    /// of the reverb tail and early reflections are set to -10 dB, and the original signal is left
+    /// at full volume.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if there are no delay lines, if any of them is shorter than the modulation depth plus
+    /// two samples, or if a Hadamard matrix is used with a number of lines that isn't a power of
+    /// two.
+    pub fn new(
+        sgn: S,
+        lens: &[unt::Time],
+        matrix: Matrix,
+        mod_depth: unt::Time,
+        taps: Vec<Tap>,
+    ) -> Self {
+        assert!(!lens.is_empty(), "an FDN needs at least one delay line");
+        if matrix == Matrix::Hadamard {
+            assert!(
+                lens.len().is_power_of_two(),
+                "Hadamard matrices need a power of two size"
+            );
+        }
+
+        let mod_depth = mod_depth.samples.into_f64();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let lines = lens
+            .iter()
+            .map(|len| {
+                let len = len.samples.into_f64();
+                assert!(len >= mod_depth + 2.0, "delay line too short");
+                Line {
+                    delay: buf::Circ::new(buf::Dyn::new((len + mod_depth) as usize + 2)),
+                    len,
+                    coefs: (0.0, 0.0),
+                    filter: 0.0,
+                }
+            })
+            .collect();
+
+        #[allow(clippy::cast_possible_truncation)]
+        let input = taps
+            .iter()
+            .map(|tap| tap.time.samples.int())
+            .max()
+            .unwrap_or(0) as usize
+            + 1;
+
+        let mut fdn = Self {
+            sgn,
+            matrix,
+            taps,
+            rt60_low: unt::Time::from_sec_default(2.0),
+            rt60_high: unt::Time::from_sec_default(2.0),
+            mod_freq: unt::Freq::from_hz_default(0.5),
+            mod_depth,
+            wet: unt::Vol::MDB10,
+            early: unt::Vol::MDB10,
+            dry: unt::Vol::FULL,
+            lines,
+            input: buf::Circ::new(buf::Dyn::new(input)),
+            damped: None,
+            phase: unt::Val::ZERO,
+            values: vec![0.0; lens.len()],
+            output: S::Sample::ZERO,
+        };
+
+        fdn.process();
+        fdn
+    }
+
+    /// Initializes a new FDN reverb with eight delay lines between 30 and 61 ms long, a Hadamard
+    /// matrix, a modulation depth of 8 samples, and a few early reflections. These lengths are
+    /// given at 44.1 kHz, and scaled to the given sample rate.
+    ///
+    /// See [`Self::new`] for the other parameters.
+    pub fn new_preset(sgn: S, sample_rate: unt::SampleRate) -> Self {
+        let taps = TAPS.map(|(time, left, right)| {
+            Tap::new(scale(time, sample_rate), smp::Stereo(left, right))
+        });
+
+        Self::new(
+            sgn,
+            &LENS.map(|len| scale(len, sample_rate)),
+            Matrix::Hadamard,
+            scale(8, sample_rate),
+            taps.to_vec(),
+        )
+    }
+
+    /// Initializes a new FDN reverb with the parameters of [`Self::new_preset`], at the default
+    /// sample rate.
+    pub fn new_default(sgn: S) -> Self {
+        Self::new_preset(sgn, unt::SampleRate::default())
+    }
+
+    /// Returns a reference to the reverberated signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the reverberated signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The feedback matrix.
+    pub const fn matrix(&self) -> Matrix {
+        self.matrix
+    }
+
+    /// The early reflections.
+    pub fn taps(&self) -> &[Tap] {
+        &self.taps
+    }
+
+    /// How much the lengths of the delay lines are modulated in either direction.
+    pub fn mod_depth(&self) -> unt::Time {
+        unt::Time::new(unt::FracInt::from_f64(self.mod_depth))
+    }
+
+    /// Clears the reverb tail.
+    pub fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.delay.clear();
+            line.filter = 0.0;
+        }
+        self.input.clear();
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    #[allow(clippy::cast_precision_loss)]
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        let mut input = 0.0;
+        S::Sample::for_each(|channel| input += sample[channel]);
+        input /= S::Sample::SIZE as f64;
+        self.input.push(smp::Mono(input));
+
+        // Early reflections.
+        let mut early = smp::Stereo::ZERO;
+        for tap in &self.taps {
+            #[allow(clippy::cast_possible_truncation)]
+            let time = tap.time.samples.int() as usize;
+            early += tap.gain * self.input.get(time).0;
+        }
+
+        // The damping filters only need to be recomputed when the decay times change.
+        let rt60 = (self.rt60_low, self.rt60_high);
+        if self.damped != Some(rt60) {
+            for line in &mut self.lines {
+                line.coefs = line.damping(rt60.0, rt60.1);
+            }
+            self.damped = Some(rt60);
+        }
+
+        // Reads and damps the output of each line.
+        let n = self.lines.len();
+        let mut tail = smp::Stereo::ZERO;
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            let phase = self.phase.inner() + index as f64 / n as f64;
+            let time = line.len + self.mod_depth * (std::f64::consts::TAU * phase).sin();
+            let value = line.damp(line.read(time));
+            self.values[index] = value;
+            tail[index % 2] += value;
+        }
+
+        // Mixes the outputs and feeds them back, along with the input.
+        self.matrix.apply(&mut self.values);
+        let gain = (n as f64).sqrt().recip();
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            let sign = if index % 2 == 0 { 1.0 } else { -1.0 };
+            line.delay
+                .push(smp::Mono(self.values[index] + sign * gain * input));
+        }
+        self.phase.advance_freq(self.mod_freq);
+
+        let tail = tail * self.wet.gain * gain + early * self.early.gain;
+        self.output = if S::Sample::SIZE == 1 {
+            S::Sample::from_fn(|channel| {
+                sample[channel] * self.dry.gain + f64::midpoint(tail.0, tail.1)
+            })
+        } else {
+            S::Sample::from_fn(|channel| sample[channel] * self.dry.gain + tail[channel])
+        };
+    }
+}
+
+impl<S: Signal> Signal for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.clear();
+        self.phase = unt::Val::ZERO;
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the reverb tail.
+impl<S: Done> Done for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for Fdn<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that both matrices preserve the norm of a vector.
+    #[test]
+    fn orthogonal() {
+        let norm = |values: &[f64]| values.iter().map(|x| x * x).sum::<f64>();
+        let values = [0.3, -1.2, 0.5, 2.0, 0.0, 0.7, -0.1, 1.1];
+
+        for matrix in [Matrix::Hadamard, Matrix::Householder] {
+            let mut mixed = values;
+            matrix.apply(&mut mixed);
+            assert_approx_eq::assert_approx_eq!(norm(&mixed), norm(&values));
+        }
+
+        // A Householder matrix also works with other sizes.
+        let mut mixed = [1.0, 2.0, 3.0];
+        Matrix::Householder.apply(&mut mixed);
+        assert_approx_eq::assert_approx_eq!(norm(&mixed), 14.0);
+    }
+
+    /// Test that the reverb tail decays according to the decay time.
+    #[test]
+    fn decay() {
+        let impulse = crate::test_util::impulse(0, smp::Mono(1.0).duplicate());
+        let mut fdn = Fdn::new_default(impulse);
+        fdn.rt60_low = unt::Time::from_samples(10_000);
+        fdn.rt60_high = unt::Time::from_samples(10_000);
+        fdn.early = unt::Vol::ZERO;
+
+        let out: Vec<_> = (0..40_000).map(|_| fdn.next().0.abs()).collect();
+        let max = crate::test_util::max;
+
+        // After every decay time, the tail is 60 dB quieter.
+        let early = max(&out[5_000..10_000]);
+        let late = max(&out[25_000..30_000]);
+        assert!(late > 0.0);
+        assert!(late < early * 1e-5);
+        assert!(late > early * 1e-7);
+    }
+
+    /// Test that changing the decay time takes effect.
+    #[test]
+    fn rt60() {
+        let impulse = crate::test_util::impulse(0, smp::Mono(1.0).duplicate());
+        let mut fdn = Fdn::new_default(impulse);
+        fdn.early = unt::Vol::ZERO;
+        let out: Vec<_> = (0..5_000).map(|_| fdn.next().0.abs()).collect();
+        assert!(crate::test_util::max(&out) > 0.0);
+
+        // An instant decay silences the tail.
+        fdn.rt60_low = unt::Time::from_samples(1);
+        fdn.rt60_high = unt::Time::from_samples(1);
+        fdn.next();
+        let out: Vec<_> = (0..5_000).map(|_| fdn.next().0.abs()).collect();
+        assert!(crate::test_util::max(&out) < 1e-12);
+    }
+
+    /// Test that the early reflections are scaled to the sample rate.
+    #[test]
+    fn sample_rate() {
+        let impulse = crate::test_util::impulse(0, smp::Mono(1.0).duplicate());
+        let mut fdn = Fdn::new_preset(impulse, unt::SampleRate::CD * 2);
+        fdn.dry = unt::Vol::ZERO;
+        fdn.wet = unt::Vol::ZERO;
+
+        let out: Vec<_> = (0..1_000).map(|_| fdn.next().0.abs()).collect();
+        assert!(crate::test_util::max(&out[1..662]) < 1e-12);
+        assert!(out[662] > 0.0);
+    }
+}
@@ -0,0 +1,81 @@
+//! A minimal radix-2 fast Fourier transform, used for convolution.
+
+/// A complex number.
+#[derive(Clone, Copy, Debug, Default, PartialEq, derive_more::Add, derive_more::AddAssign)]
+pub(super) struct Complex {
+    /// The real part.
+    pub re: f64,
+    /// The imaginary part.
+    pub im: f64,
+}
+
+impl Complex {
+    /// Zero.
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+
+    /// Initializes a complex number from its real and imaginary parts.
+    pub const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// Computes the discrete Fourier transform of some data in place, or its inverse without
+/// normalization.
+///
+/// ## Panics
+///
+/// Panics if the length of the data isn't a power of two.
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn fft(data: &mut [Complex], inverse: bool) {
+    let size = data.len();
+    assert!(size.is_power_of_two(), "FFT size must be a power of two");
+
+    // Bit reversal permutation.
+    let mut rev = 0;
+    for index in 1..size {
+        let mut bit = size >> 1;
+        while rev & bit != 0 {
+            rev ^= bit;
+            bit >>= 1;
+        }
+        rev |= bit;
+        if index < rev {
+            data.swap(index, rev);
+        }
+    }
+
+    // Butterflies.
+    let angle = if inverse {
+        std::f64::consts::TAU
+    } else {
+        -std::f64::consts::TAU
+    };
+    let mut len = 2;
+    while len <= size {
+        let (im, re) = (angle / len as f64).sin_cos();
+        let root = Complex::new(re, im);
+
+        for start in (0..size).step_by(len) {
+            let mut twiddle = Complex::new(1.0, 0.0);
+            for index in start..(start + len / 2) {
+                let even = data[index];
+                let odd = data[index + len / 2] * twiddle;
+                data[index] = even + odd;
+                data[index + len / 2] = Complex::new(even.re - odd.re, even.im - odd.im);
+                twiddle = twiddle * root;
+            }
+        }
+        len *= 2;
+    }
+}
@@ -0,0 +1,510 @@
+//! Implements algorithmic and convolution reverbs.
+//!
+//! The [`Reverb`] effect is built from the two classic building blocks of algorithmic reverbs:
+//! damped [`Comb`] filters, which are run in parallel and create a dense series of echoes, and
+//! [`AllPass`] filters, which are run in series and diffuse these echoes further. Two different
+//! tunings for these are provided, as a [`Design`].
+//!
+//! The [`Fdn`] reverb instead feeds a network of delay lines back into itself through an orthogonal
+//! [`Matrix`]. This results in a denser, smoother tail.
+//!
+//! Finally, the [`Convolution`] reverb applies a recorded impulse response, or [`Ir`], to a signal.
+
+mod convolution;
+mod fdn;
+mod fft;
+
+pub use convolution::{Convolution, Ir};
+pub use fdn::{Fdn, Matrix, Tap};
+
+use crate::prelude::*;
+
+/// A feedback comb filter with a low-pass filter in its feedback loop.
+///
+/// Each sample is played back after a fixed delay, and fed back into the filter. The feedback
+/// determines how fast the echoes decay, while the damping determines how fast their high
+/// frequencies decay.
+#[derive(Clone, Debug)]
+pub struct Comb {
+    /// The delay line.
+    delay: buf::Circ<buf::Dyn<smp::Mono>>,
+    /// The last output of the low-pass filter.
+    filter: f64,
+}
+
+impl Comb {
+    /// Initializes a new comb filter with a delay of the given number of samples.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the length is zero.
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        assert_ne!(len, 0, "comb filters can't be empty");
+        Self {
+            delay: buf::Circ::new(buf::Dyn::new(len)),
+            filter: 0.0,
+        }
+    }
+
+    /// Takes in a new input, returns a new output.
+    ///
+    /// The feedback and damping should be between `0.0` and `1.0`. A feedback of `1.0` results in
+    /// echoes that never decay.
+    pub fn eval(&mut self, input: f64, feedback: f64, damping: f64) -> f64 {
+        let output = self.delay.get(self.delay.capacity() - 1).0;
+        self.filter = output + damping * (self.filter - output);
+        self.delay.push(smp::Mono(input + feedback * self.filter));
+        output
+    }
+
+    /// Clears the delay line.
+    pub fn clear(&mut self) {
+        self.delay.clear();
+        self.filter = 0.0;
+    }
+}
+
+/// A Schroeder all-pass filter.
+///
+/// This is a comb filter with both feedback and feedforward, which lets every frequency through
+/// with the same gain, but smears the phase of the signal.
+#[derive(Clone, Debug)]
+pub struct AllPass {
+    /// The delay line.
+    delay: buf::Circ<buf::Dyn<smp::Mono>>,
+    /// The feedback gain, between `0.0` and `1.0`.
+    pub gain: f64,
+}
+
+impl AllPass {
+    /// Initializes a new all-pass filter with a delay of the given number of samples.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the length is zero.
+    #[must_use]
+    pub fn new(len: usize, gain: f64) -> Self {
+        assert_ne!(len, 0, "all-pass filters can't be empty");
+        Self {
+            delay: buf::Circ::new(buf::Dyn::new(len)),
+            gain,
+        }
+    }
+
+    /// Takes in a new input, returns a new output.
+    pub fn eval(&mut self, input: f64) -> f64 {
+        let delayed = self.delay.get(self.delay.capacity() - 1).0;
+        let write = input + self.gain * delayed;
+        self.delay.push(smp::Mono(write));
+        delayed - self.gain * write
+    }
+
+    /// Clears the delay line.
+    pub fn clear(&mut self) {
+        self.delay.clear();
+    }
+}
+
+/// The tuning of a [`Reverb`].
+///
+/// Delay lengths are tuned in samples at 44.1 kHz, and scaled to the sample rate in use. The right
+/// channel uses slightly longer delays than the left one, which decorrelates them and gives a sense
+/// of width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Design {
+    /// The original design by [Schroeder](https://www.aes.org/e-lib/browse.cfm?elib=343): four
+    /// parallel combs, followed by two all-passes.
+    Schroeder,
+    /// The design of [Freeverb](https://ccrma.stanford.edu/~jos/pasp/Freeverb.html) by Jezar at
+    /// Dreampoint: eight parallel combs, followed by four all-passes.
+    #[default]
+    Freeverb,
+}
+
+impl Design {
+    /// How many samples longer the delays of the right channel are, at 44.1 kHz.
+    const SPREAD: usize = 23;
+
+    /// The length of each comb filter, in samples at 44.1 kHz.
+    const fn cd_combs(self) -> &'static [usize] {
+        match self {
+            Self::Schroeder => &[1310, 1636, 1813, 1927],
+            Self::Freeverb => &[1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617],
+        }
+    }
+
+    /// The length of each all-pass filter, in samples at 44.1 kHz.
+    const fn cd_all_passes(self) -> &'static [usize] {
+        match self {
+            Self::Schroeder => &[221, 75],
+            Self::Freeverb => &[556, 441, 341, 225],
+        }
+    }
+
+    /// Scales a length in samples at 44.1 kHz to the given sample rate. The result is at least one.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn scale(len: usize, sample_rate: unt::SampleRate) -> usize {
+        let ratio = f64::from(sample_rate) / f64::from(unt::SampleRate::CD);
+        ((len as f64 * ratio).round() as usize).max(1)
+    }
+
+    /// How many samples longer the delays of the right channel are, at the given sample rate.
+    #[must_use]
+    pub fn spread(sample_rate: unt::SampleRate) -> usize {
+        Self::scale(Self::SPREAD, sample_rate)
+    }
+
+    /// The length of each comb filter, at the given sample rate.
+    #[must_use]
+    pub fn combs(self, sample_rate: unt::SampleRate) -> Vec<usize> {
+        self.cd_combs()
+            .iter()
+            .map(|&len| Self::scale(len, sample_rate))
+            .collect()
+    }
+
+    /// The length of each all-pass filter, at the given sample rate.
+    #[must_use]
+    pub fn all_passes(self, sample_rate: unt::SampleRate) -> Vec<usize> {
+        self.cd_all_passes()
+            .iter()
+            .map(|&len| Self::scale(len, sample_rate))
+            .collect()
+    }
+
+    /// The gain of the all-pass filters.
+    #[must_use]
+    pub const fn all_pass_gain(self) -> f64 {
+        match self {
+            Self::Schroeder => 0.7,
+            Self::Freeverb => 0.5,
+        }
+    }
+
+    /// The gain applied to the input, so that the output has a reasonable volume.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn input_gain(self) -> f64 {
+        // Each comb filter adds up to the same output.
+        0.12 / self.cd_combs().len() as f64
+    }
+}
+
+/// The filters for a single channel of a [`Reverb`].
+#[derive(Clone, Debug)]
+struct Tank {
+    /// The parallel comb filters.
+    combs: Vec<Comb>,
+    /// The series all-pass filters.
+    all_passes: Vec<AllPass>,
+}
+
+impl Tank {
+    /// Initializes the filters for a design at a given sample rate, with delays lengthened by the
+    /// given spread.
+    fn new(design: Design, sample_rate: unt::SampleRate, spread: usize) -> Self {
+        Self {
+            combs: design
+                .combs(sample_rate)
+                .into_iter()
+                .map(|len| Comb::new(len + spread))
+                .collect(),
+            all_passes: design
+                .all_passes(sample_rate)
+                .into_iter()
+                .map(|len| AllPass::new(len + spread, design.all_pass_gain()))
+                .collect(),
+        }
+    }
+
+    /// Takes in a new input, returns a new output.
+    fn eval(&mut self, input: f64, feedback: f64, damping: f64) -> f64 {
+        let sum = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.eval(input, feedback, damping))
+            .sum();
+        self.all_passes
+            .iter_mut()
+            .fold(sum, |sample, all_pass| all_pass.eval(sample))
+    }
+
+    /// Clears all filters.
+    fn clear(&mut self) {
+        for comb in &mut self.combs {
+            comb.clear();
+        }
+        for all_pass in &mut self.all_passes {
+            all_pass.clear();
+        }
+    }
+}
+
+/// An algorithmic reverb, made out of parallel damped [`Combs`](Comb), and series
+/// [`AllPasses`](AllPass).
+///
+/// The channels of the signal are mixed down before going into the reverb, which then outputs a
+/// separate reverb tail for each channel. In the case of mono audio, only the left tail is used.
+///
+/// ## Example
+///
+/// We add a large reverb to some plucks.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let pluck = ctr::Loop::new(
+///     vec![sec(0.5)],
+///     eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Stereo, _>::new(crv::Saw, unt::Freq::from_raw_default(unt::RawFreq::A3)),
+///         eff::env::Ar::new(sec(0.005), sec(0.1)),
+///     ),
+///     map::Func::new(|sgn: &mut eff::env::ArEnv<_>| sgn.retrigger()),
+/// );
+///
+/// let mut sgn = eff::rvb::Reverb::new_default(pluck, eff::rvb::Design::Freeverb);
+/// sgn.room = 0.9;
+/// Song::new(sec(3.0), unt::SampleRate::default(), sgn).export("examples/reverb.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Reverb<S: Signal>
+where
+    S::Sample: Audio,
+{
+    /// The reverberated signal.
+    sgn: S,
+    /// The tuning of the filters.
+    design: Design,
+    /// The room size, between `0.0` and `1.0`. Larger rooms have longer reverb tails.
+    pub room: f64,
+    /// The damping, between `0.0` and `1.0`. Higher values make high frequencies decay faster.
+    pub damping: f64,
+    /// The stereo width, between `0.0` and `1.0`. A width of zero results in a mono reverb tail.
+    pub width: f64,
+    /// The volume of the reverb tail.
+    pub wet: unt::Vol,
+    /// The volume of the original signal.
+    pub dry: unt::Vol,
+
+    /// The filters for each channel.
+    tanks: [Tank; 2],
+    /// The current output.
+    output: S::Sample,
+}
+
+impl<S: Signal> Reverb<S>
+where
+    S::Sample: Audio,
+{
+    /// Initializes a new reverb with the given design, at the given sample rate.
+    ///
+    /// The room size and damping are set to `0.5`, the width is set to `1.0`, the volume of the
+    /// reverb tail is set to -10 dB, and the original signal is left at full volume.
+    pub fn new(sgn: S, design: Design, sample_rate: unt::SampleRate) -> Self {
+        let mut reverb = Self {
+            sgn,
+            design,
+            room: 0.5,
+            damping: 0.5,
+            width: 1.0,
+            wet: unt::Vol::MDB10,
+            dry: unt::Vol::FULL,
+            tanks: [
+                Tank::new(design, sample_rate, 0),
+                Tank::new(design, sample_rate, Design::spread(sample_rate)),
+            ],
+            output: S::Sample::ZERO,
+        };
+
+        reverb.process();
+        reverb
+    }
+
+    /// Initializes a new reverb with the given design, at the default sample rate.
+    ///
+    /// See [`Self::new`] for the other defaults.
+    pub fn new_default(sgn: S, design: Design) -> Self {
+        Self::new(sgn, design, unt::SampleRate::default())
+    }
+
+    /// Returns a reference to the reverberated signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the reverberated signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The tuning of the filters.
+    pub const fn design(&self) -> Design {
+        self.design
+    }
+
+    /// Clears the reverb tail.
+    pub fn clear(&mut self) {
+        for tank in &mut self.tanks {
+            tank.clear();
+        }
+    }
+
+    /// Reads the current sample from the signal, and computes the output.
+    fn process(&mut self) {
+        let sample = self.sgn.get();
+        let mut input = 0.0;
+        S::Sample::for_each(|channel| input += sample[channel]);
+        #[allow(clippy::cast_precision_loss)]
+        let input = input / S::Sample::SIZE as f64 * self.design.input_gain();
+
+        // These constants come from Freeverb.
+        let feedback = 0.7 + 0.28 * self.room;
+        let damping = 0.4 * self.damping;
+
+        let left = self.tanks[0].eval(input, feedback, damping);
+        let wet = self.wet.gain;
+        self.output = if S::Sample::SIZE == 1 {
+            S::Sample::from_fn(|channel| sample[channel] * self.dry.gain + left * wet)
+        } else {
+            let right = self.tanks[1].eval(input, feedback, damping);
+            let wet1 = wet * (1.0 + self.width) / 2.0;
+            let wet2 = wet * (1.0 - self.width) / 2.0;
+            let tails = [left * wet1 + right * wet2, right * wet1 + left * wet2];
+            S::Sample::from_fn(|channel| sample[channel] * self.dry.gain + tails[channel])
+        };
+    }
+}
+
+impl<S: Signal> Signal for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.output
+    }
+}
+
+impl<S: SignalMut> SignalMut for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+        self.process();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+        self.clear();
+        self.process();
+    }
+}
+
+impl<S: Frequency> Frequency for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    fn freq(&self) -> unt::Freq {
+        self.sgn.freq()
+    }
+
+    fn freq_mut(&mut self) -> &mut unt::Freq {
+        self.sgn.freq_mut()
+    }
+}
+
+impl<S: Base> Base for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    type Base = S::Base;
+
+    fn base(&self) -> &S::Base {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut S::Base {
+        self.sgn.base_mut()
+    }
+}
+
+/// This doesn't take into account the reverb tail.
+impl<S: Done> Done for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    fn is_done(&self) -> bool {
+        self.sgn.is_done()
+    }
+}
+
+impl<S: Stop> Stop for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    fn stop(&mut self) {
+        self.sgn.stop();
+    }
+}
+
+impl<S: Panic> Panic for Reverb<S>
+where
+    S::Sample: Audio,
+{
+    fn panic(&mut self) {
+        self.sgn.panic();
+        self.clear();
+        self.output = S::Sample::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the reverb tail of an impulse starts after the shortest comb, and decays.
+    #[test]
+    fn impulse() {
+        for design in [Design::Schroeder, Design::Freeverb] {
+            let impulse = crate::test_util::impulse(0, smp::Mono(1.0));
+            let mut reverb = Reverb::new_default(impulse, design);
+            reverb.dry = unt::Vol::ZERO;
+
+            let out: Vec<_> = (0..100_000).map(|_| reverb.next().0.abs()).collect();
+            let max = crate::test_util::max;
+            let first = design.combs(unt::SampleRate::default())[0];
+
+            // The first sample is output before the original signal is muted.
+            assert!(max(&out[1..first]) < 1e-12);
+            assert!(out[first] > 0.0);
+
+            assert!(max(&out[90_000..]) < max(&out[..10_000]) / 100.0);
+        }
+    }
+
+    /// Test that the delays are scaled to the sample rate.
+    #[test]
+    fn sample_rate() {
+        let design = Design::Freeverb;
+        let cd = design.combs(unt::SampleRate::CD);
+        let double = design.combs(unt::SampleRate::CD * 2);
+        for (x, y) in cd.iter().zip(&double) {
+            assert_eq!(2 * x, *y);
+        }
+        assert_eq!(Design::spread(unt::SampleRate::CD * 2), 46);
+
+        let impulse = crate::test_util::impulse(0, smp::Mono(1.0));
+        let mut reverb = Reverb::new(impulse, design, unt::SampleRate::CD * 2);
+        reverb.dry = unt::Vol::ZERO;
+        let out: Vec<_> = (0..3000).map(|_| reverb.next().0.abs()).collect();
+        assert!(crate::test_util::max(&out[1..double[0]]) < 1e-12);
+        assert!(out[double[0]] > 0.0);
+    }
+}
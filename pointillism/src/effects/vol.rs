@@ -59,6 +59,13 @@ impl<S: SignalMut> SignalMut for Volume<S> {
     }
 }
 
+#[cfg(feature = "rayon")]
+impl<S: Render> Render for Volume<S> {
+    fn render(&mut self, buf: &mut [S::Sample], workers: &sgn::Workers) {
+        self.inner.render(buf, workers);
+    }
+}
+
 impl<S: Frequency> Frequency for Volume<S> {
     fn freq(&self) -> unt::Freq {
         self.inner.freq()
@@ -71,6 +71,7 @@
 //! | [`hound`](https://docs.rs/hound/latest/hound)* | Saving songs as WAV files. |
 //! | [`cpal`](https://docs.rs/cpal/latest/cpal) | Playing songs in a dedicated thread. |
 //! | [`midly`](https://docs.rs/midly/latest/midly) | Reading and playing back MIDI files. |
+//! | [`rayon`](https://docs.rs/rayon/latest/rayon) | Rendering independent signals in parallel. |
 //! | [`human-duration`](https://docs.rs/human-duration/latest/human_duration)* | Pretty-printing for the [`unt::RawTime`] type. |
 //!
 //! \* Features marked with an asterisk are enabled by default.
@@ -79,8 +80,6 @@
 //!
 //! Future goals of pointillism are:
 //!
-//! - (Better) algorithmic reverbs
-//! - Limiters, compressors, sidechaining
 //! - [Me](https://viiii.bandcamp.com) making a whole album with it :D
 //!
 //! # Disclaimer
@@ -113,6 +112,8 @@ pub mod units;
 
 #[cfg(feature = "cpal")]
 pub mod cpal;
+#[cfg(test)]
+mod test_util;
 #[cfg(feature = "hound")]
 pub use with_hound::*;
 
@@ -261,6 +262,55 @@ mod with_hound {
             self.export_res(filename).expect("IO error");
         }
     }
+
+    #[cfg(feature = "rayon")]
+    impl<S: Render> Song<S>
+    where
+        S::Sample: Audio,
+    {
+        /// Exports a song as a WAV file, rendering it in blocks whose work is split among the
+        /// given number of threads. Requires the [`hound`] and `rayon` features.
+        ///
+        /// The threads are spawned once, and reused for every block. The output is exactly the
+        /// same as that of [`Self::export_res`]. See [`Render`] for more information.
+        ///
+        /// ## Errors
+        ///
+        /// This should only return an error in the case of an IO error.
+        pub fn export_par_res<P: AsRef<std::path::Path>>(
+            &mut self,
+            filename: P,
+            threads: usize,
+        ) -> hound::Result<()> {
+            let mut remaining = self.length.samples.int();
+            let mut writer =
+                hound::WavWriter::create(filename, spec(S::Sample::size_u8(), self.sample_rate))?;
+            let workers = sgn::Workers::new(threads);
+            let mut buf = [S::Sample::ZERO; sgn::BLOCK_LEN];
+
+            while remaining > 0 {
+                let len = usize::try_from(remaining)
+                    .map_or(sgn::BLOCK_LEN, |rem| rem.min(sgn::BLOCK_LEN));
+                self.sgn.render(&mut buf[..len], &workers);
+                for sample in &buf[..len] {
+                    sample.write(&mut writer)?;
+                }
+                remaining -= len as u64;
+            }
+
+            writer.finalize()
+        }
+
+        /// A convenience function for calling [`Self::export_par_res`], panicking in case of an IO
+        /// error.
+        ///
+        /// ## Panics
+        ///
+        /// Panics in case of an IO error.
+        pub fn export_par<P: AsRef<std::path::Path>>(&mut self, filename: P, threads: usize) {
+            self.export_par_res(filename, threads).expect("IO error");
+        }
+    }
 }
 
 /// The crate prelude.
@@ -276,11 +326,15 @@ pub mod prelude {
     // Import traits.
     pub use crate::{
         buf::{Buffer, BufferMut, Ring},
+        ctr::Express,
         eff::flt::FilterMap,
         map::{Map, Mut, Val},
         sgn::{Base, Done, Frequency, Panic, Signal, SignalMut, Stop},
         smp::{Array, Audio, Sample, SampleBase},
         Song,
     };
+
+    #[cfg(feature = "rayon")]
+    pub use crate::sgn::Render;
     pub(crate) use sgn::impl_base;
 }
@@ -4,14 +4,16 @@
 //! ## Todo
 //!
 //! Explain what each struct does.
-//!
-//! Define another kind of polyphony that works with limited voices instead?
 
 use crate::prelude::*;
 use std::{collections::HashMap, hash::Hash};
 
 mod unison;
-pub use unison::{DetuneCurveSgn, DetuneSgn, Unison, UnisonCurve, UnisonRef};
+pub use unison::{DetuneCurveSgn, DetuneSgn, Phase, Spread, Unison, UnisonCurve, UnisonRef};
+mod pool;
+mod voices;
+pub use pool::Pool;
+pub use voices::{Steal, Voices};
 
 /// A polyphonic signal.
 ///
@@ -21,16 +23,32 @@ pub use unison::{DetuneCurveSgn, DetuneSgn, Unison, UnisonCurve, UnisonRef};
 /// We currently use a [`HashMap`] to store these signals, but this is subject to change. Likewise,
 /// the exact trait requirements on `K` may change in the future, although unsigned integers and the
 /// like should always be supported.
+///
+/// Adding signals might allocate memory. If this is a problem, as in a real-time audio callback, use
+/// a [`Pool`] or [`Voices`] instead.
+///
+/// Since the voices are independent of each other, they can be rendered in parallel through the
+/// `Render` trait, which requires the `rayon` feature. See `Song::export_par` for a convenient way
+/// to do this.
 #[derive(Clone, Debug)]
 pub struct Polyphony<K: Eq + Hash + Clone, S: Done> {
     /// The signals currently playing.
     signals: HashMap<K, S>,
+
+    /// A buffer for rendering each voice, together with the number of samples written to it.
+    ///
+    /// These are kept between calls to [`Render::render`], and only grow when there's more voices
+    /// than ever before.
+    #[cfg(feature = "rayon")]
+    buffers: Vec<(Vec<S::Sample>, usize)>,
 }
 
 impl<K: Eq + Hash + Clone, S: Done> Default for Polyphony<K, S> {
     fn default() -> Self {
         Self {
             signals: HashMap::new(),
+            #[cfg(feature = "rayon")]
+            buffers: Vec::new(),
         }
     }
 }
@@ -96,6 +114,14 @@ impl<K: Eq + Hash + Clone, S: Done> Polyphony<K, S> {
         self.modify(key, S::stop)
     }
 
+    /// Sends an expression event to a given signal, returns whether it was successful.
+    pub fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.modify(key, |sgn| sgn.express(expr))
+    }
+
     // We don't implement `panic` as it would clash with the `Panic` impl.
 
     /// Stops all signals currently playing.
@@ -119,26 +145,17 @@ impl<K: Eq + Hash + Clone, S: Done> Signal for Polyphony<K, S> {
 
 impl<K: Eq + Hash + Clone, S: SignalMut + Done> SignalMut for Polyphony<K, S> {
     fn advance(&mut self) {
-        // Generators to clear.
-        let mut clear = Vec::new();
-
-        for (index, sgn) in &mut self.signals {
+        for sgn in self.signals.values_mut() {
             sgn.advance();
-
-            if sgn.is_done() {
-                clear.push(index.clone());
-            }
         }
 
-        for index in clear {
-            self.signals.remove(&index);
-        }
+        // This works in place, and never allocates.
+        self.signals.retain(|_, sgn| !sgn.is_done());
     }
 
     fn retrigger(&mut self) {
         self.signals.clear();
     }
-
 }
 
 impl<K: Eq + Hash + Clone, S: SignalMut + Done> Base for Polyphony<K, S> {
@@ -150,3 +167,160 @@ impl<K: Eq + Hash + Clone, S: SignalMut + Done> Panic for Polyphony<K, S> {
         self.retrigger();
     }
 }
+
+/// A collection of signals which can be added, stopped, and sent expression events through their
+/// keys.
+///
+/// This is implemented for [`Polyphony`], [`Pool`], and [`Voices`], and allows a
+/// [`ctr::NoteReader`] to drive any of them.
+pub trait Polyphonic<K, S: Signal>: SignalMut<Sample = S::Sample> {
+    /// Adds a signal, using a given key.
+    fn add(&mut self, key: K, sgn: S);
+
+    /// Stops a given signal, returns whether it was successful.
+    fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop;
+
+    /// Sends an expression event to a given signal, returns whether it was successful.
+    fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express;
+}
+
+impl<K: Eq + Hash + Clone, S: SignalMut + Done> Polyphonic<K, S> for Polyphony<K, S> {
+    fn add(&mut self, key: K, sgn: S) {
+        self.add(key, sgn);
+    }
+
+    fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop,
+    {
+        self.stop(key)
+    }
+
+    fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.express(key, expr)
+    }
+}
+
+/// Renders a single voice into a buffer, stopping once it's done. Returns the number of samples
+/// written.
+#[cfg(feature = "rayon")]
+fn render_voice<S: SignalMut + Done>(sgn: &mut S, buf: &mut [S::Sample]) -> usize {
+    for (index, sample) in buf.iter_mut().enumerate() {
+        *sample = sgn.next();
+        if sgn.is_done() {
+            return index + 1;
+        }
+    }
+
+    buf.len()
+}
+
+#[cfg(feature = "rayon")]
+impl<K: Eq + Hash + Clone + Send, S: SignalMut + Done + Send> Polyphony<K, S>
+where
+    S::Sample: Send,
+{
+    /// Renders a single block, whose length is at most [`sgn::BLOCK_LEN`].
+    fn render_block(&mut self, buf: &mut [S::Sample], workers: &sgn::Workers) {
+        let len = buf.len();
+        let voices = self.signals.len();
+        if self.buffers.len() < voices {
+            self.buffers
+                .resize_with(voices, || (vec![S::Sample::ZERO; sgn::BLOCK_LEN], 0));
+        }
+
+        let buffers = &mut self.buffers;
+        let signals = &mut self.signals;
+        workers.scope(|scope| {
+            for (sgn, (out, done)) in signals.values_mut().zip(buffers.iter_mut()) {
+                scope.spawn(move |_| *done = render_voice(sgn, &mut out[..len]));
+            }
+        });
+
+        // Samples are added in the exact same order as in the serial path. We also start from the
+        // same value as an empty sum, which might be negative zero.
+        buf.fill(std::iter::empty().sum());
+        for (out, done) in &self.buffers[..voices] {
+            for (sample, &voice) in buf.iter_mut().zip(&out[..*done]) {
+                *sample += voice;
+            }
+        }
+
+        self.signals.retain(|_, sgn| !sgn.is_done());
+    }
+}
+
+/// Renders each voice as a separate task on the workers, one [block](sgn::BLOCK_LEN) at a time,
+/// and then adds them together in the same order as [`Signal::get`] would.
+#[cfg(feature = "rayon")]
+impl<K: Eq + Hash + Clone + Send, S: SignalMut + Done + Send> Render for Polyphony<K, S>
+where
+    S::Sample: Send,
+{
+    fn render(&mut self, buf: &mut [S::Sample], workers: &sgn::Workers) {
+        for buf in buf.chunks_mut(sgn::BLOCK_LEN) {
+            self.render_block(buf, workers);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "rayon")]
+mod test {
+    use super::*;
+
+    /// A melody with many overlapping notes of different lengths.
+    fn melody(offset: u64) -> impl Render<Sample = smp::Mono> + Clone + Send {
+        let at = unt::Time::from_samples;
+        let melody = ctr::Melody::piano_roll(
+            (0..40).map(|i| ctr::Note::new(at(7 * i + offset), at(13 + i * 5 % 17), i)),
+            |idx| idx,
+        );
+
+        #[allow(clippy::cast_precision_loss)]
+        ctr::MelSeq::new_melody(
+            melody,
+            map::Func::new(move |i: u64| {
+                eff::env::ArEnv::new_ar(
+                    gen::Loop::<smp::Mono, _>::new(
+                        crv::Sin,
+                        unt::Freq::new(0.01 + i as f64 * 1e-3),
+                    ),
+                    eff::env::Ar::new(at(3), at(11)),
+                )
+            }),
+        )
+    }
+
+    /// Test that parallel rendering is bit-identical to serial rendering.
+    ///
+    /// Since the order in which voices are added depends on the random state of each hash map, we
+    /// compare clones of the same signals.
+    #[test]
+    fn render() {
+        let (x, y) = (melody(0), melody(3));
+
+        let mix = |x, y| eff::Volume::new(rtn::Mix::new(x, y), unt::Vol::HALF);
+        let mut sgn = mix(x.clone(), y.clone());
+        let serial: Vec<_> = (0..1000).map(|_| sgn.next().0.to_bits()).collect();
+
+        let mut sgn = mix(x, y);
+        let workers = sgn::Workers::new(3);
+        let mut buf = [smp::Mono::ZERO; 1000];
+        let mut start = 0;
+        for len in [1, 5, 64, 100, 230, 600] {
+            sgn.render(&mut buf[start..start + len], &workers);
+            start += len;
+        }
+        let parallel: Vec<_> = buf.iter().map(|sample| sample.0.to_bits()).collect();
+
+        assert_eq!(serial, parallel);
+    }
+}
@@ -0,0 +1,238 @@
+//! Implements the [`Pool`] type, a polyphony that never allocates after initialization.
+
+use crate::prelude::*;
+
+/// A polyphonic signal backed by a preallocated pool of voices.
+///
+/// This has the same API as [`poly::Polyphony`], but it stores its signals in a buffer that's
+/// allocated once on initialization, and never grows. Adding, stopping, and removing signals never
+/// allocates, which makes this suitable for use within a real-time audio callback, such as the one
+/// in `Song::build_output_stream`.
+///
+/// Signals are looked up by a linear search over the voices. This is faster than hashing for the
+/// small number of voices that are usually playing at once.
+///
+/// If all voices are in use, new signals are discarded. See [`poly::Voices`] for a type that steals
+/// voices instead.
+///
+/// ## Example
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let mut pool = poly::Pool::new(2);
+///
+/// for (key, raw) in [unt::RawFreq::C4, unt::RawFreq::E4, unt::RawFreq::G4]
+///     .into_iter()
+///     .enumerate()
+/// {
+///     let sgn = eff::env::ArEnv::new_ar(
+///         gen::Loop::<smp::Mono, _>::new(crv::Sin, unt::Freq::from_raw_default(raw)),
+///         eff::env::Ar::new(sec(0.1), sec(0.5)),
+///     );
+///
+///     // There's only room for two notes.
+///     assert_eq!(pool.add(key, sgn), key < 2);
+/// }
+///
+/// assert_eq!(pool.len(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pool<K: Eq + Clone, S: Done> {
+    /// The signals currently playing, together with their keys.
+    signals: Vec<(K, S)>,
+    /// The maximum number of signals.
+    capacity: usize,
+}
+
+impl<K: Eq + Clone, S: Done> Pool<K, S> {
+    /// Initializes a new pool with room for a given number of signals, playing nothing.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            signals: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The maximum number of signals that can play at once.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of signals currently playing.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.signals.len()
+    }
+
+    /// Whether there are no signals playing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.signals.is_empty()
+    }
+
+    /// Whether all voices are in use, so that new signals will be discarded.
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity
+    }
+
+    /// Returns an iterator over the inner signals and their keys.
+    pub fn signals(&self) -> impl Iterator<Item = (&K, &S)> {
+        self.signals.iter().map(|(key, sgn)| (key, sgn))
+    }
+
+    /// Returns an iterator over mutable references to the inner signals and their keys.
+    pub fn signals_mut(&mut self) -> impl Iterator<Item = (&K, &mut S)> {
+        self.signals.iter_mut().map(|(key, sgn)| (&*key, sgn))
+    }
+
+    /// Adds a signal, using a given key.
+    ///
+    /// If the key was already in use, the signal is overwritten. Otherwise, if all voices are in
+    /// use, the signal is discarded. Returns whether the signal was added.
+    pub fn add(&mut self, key: K, sgn: S) -> bool {
+        if let Some(old) = self.get_mut(&key) {
+            *old = sgn;
+        } else if self.is_full() {
+            return false;
+        } else {
+            self.signals.push((key, sgn));
+        }
+
+        true
+    }
+
+    /// Gets a reference to a particular signal.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&S> {
+        self.signals
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, sgn)| sgn)
+    }
+
+    /// Gets a mutable reference to a particular signal.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut S> {
+        (self.signals.iter_mut())
+            .find(|(k, _)| k == key)
+            .map(|(_, sgn)| sgn)
+    }
+
+    /// Modifies a signal with the given key using the specified function.
+    ///
+    /// Returns whether the signal was found.
+    pub fn modify<F: Fn(&mut S)>(&mut self, key: &K, f: F) -> bool {
+        if let Some(sgn) = self.get_mut(key) {
+            f(sgn);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stops a given signal, returns whether it was successful.
+    pub fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop,
+    {
+        self.modify(key, S::stop)
+    }
+
+    /// Sends an expression event to a given signal, returns whether it was successful.
+    pub fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.modify(key, |sgn| sgn.express(expr))
+    }
+
+    /// Stops all signals currently playing.
+    pub fn stop_all(&mut self)
+    where
+        S: Stop,
+    {
+        for (_, sgn) in self.signals_mut() {
+            sgn.stop();
+        }
+    }
+}
+
+impl<K: Eq + Clone, S: Done> Signal for Pool<K, S> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.signals.iter().map(|(_, sgn)| sgn.get()).sum()
+    }
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> SignalMut for Pool<K, S> {
+    fn advance(&mut self) {
+        for (_, sgn) in &mut self.signals {
+            sgn.advance();
+        }
+
+        // This works in place, and never allocates.
+        self.signals.retain(|(_, sgn)| !sgn.is_done());
+    }
+
+    fn retrigger(&mut self) {
+        self.signals.clear();
+    }
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> Base for Pool<K, S> {
+    impl_base!();
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> Panic for Pool<K, S> {
+    fn panic(&mut self) {
+        self.retrigger();
+    }
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> poly::Polyphonic<K, S> for Pool<K, S> {
+    fn add(&mut self, key: K, sgn: S) {
+        self.add(key, sgn);
+    }
+
+    fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop,
+    {
+        self.stop(key)
+    }
+
+    fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.express(key, expr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that the pool never reallocates.
+    #[test]
+    fn no_alloc() {
+        let mut pool = Pool::new(4);
+        let ptr = pool.signals.as_ptr();
+
+        for key in 0..16 {
+            pool.add(
+                key % 6,
+                gen::Once::<smp::Mono, _>::new(crv::Sin, unt::Time::from_samples(key)),
+            );
+            pool.advance();
+        }
+
+        assert!(pool.len() <= 4);
+        assert_eq!(pool.signals.as_ptr(), ptr);
+        assert_eq!(pool.signals.capacity(), 4);
+    }
+}
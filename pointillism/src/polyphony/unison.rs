@@ -1,10 +1,8 @@
 //! Declares the [`Unison`] struct.
 //!
-//! This can be used in order to more effectively play multiple copies of a base signal.
-//!
-//! ## Todo
-//!
-//! Implement some sort of simple mechanism for building overtones.
+//! This can be used in order to more effectively play multiple copies of a base signal, either
+//! slightly detuned from each other, or as the partials of a more complex sound. Each copy can have
+//! its own gain, and a [`Spread`] can pan them across the stereo field.
 
 use crate::prelude::*;
 
@@ -70,6 +68,20 @@ impl Iterator for DetuneIter {
     }
 }
 
+/// How the phases of the curves in a [`UnisonCurve`] are set when it's retriggered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// All curves start at the same phase.
+    #[default]
+    Zero,
+    /// The phases are evenly spread out.
+    Even,
+    /// The phases are random.
+    ///
+    /// This can help if you're getting a lot of interference between the different curves.
+    Random,
+}
+
 /// Plays multiple copies of a curve in unison.
 pub struct UnisonCurve<C: Map<Input = unt::Val>>
 where
@@ -81,12 +93,15 @@ where
     /// The base frequency.
     base: unt::Freq,
 
-    /// The values and intervals from the base frequency for each curve.
+    /// The values, intervals from the base frequency, and gains for each curve.
     ///
-    /// These two are needed in order to play curves in unison. By bundling data like this, we save
-    /// on allocations. More importantly, we guarantee that there aren't mismatches between the
-    /// number of these values.
-    val_inters: Vec<(unt::Val, unt::Interval)>,
+    /// These are needed in order to play curves in unison. By bundling data like this, we save on
+    /// allocations. More importantly, we guarantee that there aren't mismatches between the number
+    /// of these values.
+    voices: Vec<(unt::Val, unt::Interval, unt::Vol)>,
+
+    /// How the phases are set when the signal is retriggered.
+    phase: Phase,
 }
 
 impl<C: Map<Input = unt::Val>> UnisonCurve<C>
@@ -95,17 +110,18 @@ where
 {
     /// Initializes a new [`UnisonCurve`].
     ///
-    /// This will play multiple copies of a curve at the specified frequency multipliers, with the
-    /// given initial phases.
+    /// This will play multiple copies of a curve at the specified frequency multipliers and gains,
+    /// with the given initial phases.
     pub const fn new_curve_phases(
         map: C,
         base: unt::Freq,
-        val_inters: Vec<(unt::Val, unt::Interval)>,
+        voices: Vec<(unt::Val, unt::Interval, unt::Vol)>,
     ) -> Self {
         Self {
             map,
             base,
-            val_inters,
+            voices,
+            phase: Phase::Zero,
         }
     }
 
@@ -118,11 +134,13 @@ where
         base: unt::Freq,
         intervals: I,
     ) -> Self {
-        Self {
+        Self::new_curve_phases(
             map,
             base,
-            val_inters: intervals.into_iter().map(|x| (unt::Val::ZERO, x)).collect(),
-        }
+            (intervals.into_iter())
+                .map(|x| (unt::Val::ZERO, x, unt::Vol::FULL))
+                .collect(),
+        )
     }
 
     /// Plays copies of a curve, centered at a certain base frequency, spaced out by a given
@@ -134,14 +152,68 @@ where
         Self::new_curve(map, base, DetuneIter::new(detune, num))
     }
 
+    /// Plays copies of a curve at the given ratios from the base frequency, as the partials of a
+    /// single sound.
+    ///
+    /// The gain of each partial is its ratio raised to the power of `-rolloff`. For instance, a
+    /// rolloff of `1.0` gives the amplitudes of a saw wave when the ratios are the harmonics. You
+    /// might want to normalize the output afterwards.
+    pub fn overtones_curve<I: IntoIterator<Item = f64>>(
+        map: C,
+        base: unt::Freq,
+        ratios: I,
+        rolloff: f64,
+    ) -> Self {
+        Self::new_curve_phases(
+            map,
+            base,
+            (ratios.into_iter())
+                .map(|r| {
+                    let gain = unt::Vol::new(r.powf(-rolloff));
+                    (unt::Val::ZERO, unt::Interval::new(r), gain)
+                })
+                .collect(),
+        )
+    }
+
+    /// Plays copies of a curve at the first `num` harmonics of the base frequency.
+    ///
+    /// See [`Self::overtones_curve`] for an explanation of the rolloff.
+    pub fn harmonics_curve(map: C, base: unt::Freq, num: u16, rolloff: f64) -> Self {
+        Self::overtones_curve(map, base, (1..=num).map(f64::from), rolloff)
+    }
+
+    /// Plays `num` detuned copies of a curve around each of the given partials.
+    ///
+    /// The copies around each partial are spaced as in [`Self::detune_curve`], and their gains add
+    /// up to the gain of the partial, as given in [`Self::overtones_curve`].
+    pub fn detune_overtones_curve<I: IntoIterator<Item = f64>>(
+        map: C,
+        base: unt::Freq,
+        ratios: I,
+        rolloff: f64,
+        detune: unt::Interval,
+        num: u16,
+    ) -> Self {
+        let mut voices = Vec::new();
+        for ratio in ratios {
+            let gain = unt::Vol::new(ratio.powf(-rolloff) / f64::from(num));
+            for interval in DetuneIter::new(detune, num) {
+                voices.push((unt::Val::ZERO, unt::Interval::new(ratio) * interval, gain));
+            }
+        }
+
+        Self::new_curve_phases(map, base, voices)
+    }
+
     /// The number of copies of the signal that play.
     pub fn len(&self) -> usize {
-        self.val_inters.len()
+        self.voices.len()
     }
 
     /// Whether there are no signals to be played.
     pub fn is_empty(&self) -> bool {
-        self.val_inters.is_empty()
+        self.voices.is_empty()
     }
 
     /// A reference to the curve being played.
@@ -156,32 +228,92 @@ where
 
     /// Returns an iterator over the intervals for the different curves.
     pub fn intervals(&self) -> impl Iterator<Item = unt::Interval> + '_ {
-        self.val_inters.iter().map(|&(_, interval)| interval)
+        self.voices.iter().map(|&(_, interval, _)| interval)
     }
 
     /// Returns an iterator over the mutable references to the intervals for the different curves.
     pub fn intervals_mut(&mut self) -> impl Iterator<Item = &mut unt::Interval> {
-        self.val_inters.iter_mut().map(|(_, interval)| interval)
+        self.voices.iter_mut().map(|(_, interval, _)| interval)
     }
 
     /// Returns an iterator over the values for the different curves.
     pub fn val(&self) -> impl Iterator<Item = unt::Val> + '_ {
-        self.val_inters.iter().map(|&(val, _)| val)
+        self.voices.iter().map(|&(val, _, _)| val)
     }
 
     /// Returns an iterator over the mutable references to the values for the different curves.
     pub fn val_mut(&mut self) -> impl Iterator<Item = &mut unt::Val> {
-        self.val_inters.iter_mut().map(|(val, _)| val)
+        self.voices.iter_mut().map(|(val, _, _)| val)
+    }
+
+    /// Returns an iterator over the gains for the different curves.
+    pub fn gains(&self) -> impl Iterator<Item = unt::Vol> + '_ {
+        self.voices.iter().map(|&(_, _, gain)| gain)
+    }
+
+    /// Returns an iterator over the mutable references to the gains for the different curves.
+    pub fn gains_mut(&mut self) -> impl Iterator<Item = &mut unt::Vol> {
+        self.voices.iter_mut().map(|(_, _, gain)| gain)
+    }
+
+    /// Sets the gain of each curve according to a gain curve.
+    ///
+    /// The gain curve is evaluated at evenly spaced values from `0.0` for the first curve, to `1.0`
+    /// for the last one.
+    pub fn set_gain_curve<F: Map<Input = unt::Val, Output = f64>>(&mut self, curve: &F) {
+        let len = self.len();
+        for (index, gain) in self.gains_mut().enumerate() {
+            *gain = unt::Vol::new(curve.eval(spread_val(index, len)));
+        }
     }
 
     /// Returns the current output from a given curve.
     pub fn get_at(&self, index: u8) -> C::Output {
-        self.map().eval(self.val_inters[index as usize].0)
+        let (val, _, gain) = self.voices[index as usize];
+        self.map().eval(val) * gain.gain
+    }
+
+    /// Returns an iterator over the current outputs from the different curves.
+    pub fn outputs(&self) -> impl Iterator<Item = C::Output> + '_ {
+        (self.voices.iter()).map(|&(val, _, gain)| self.map().eval(val) * gain.gain)
+    }
+
+    /// How the phases are set when the signal is retriggered.
+    pub const fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Sets how the phases are set when the signal is retriggered, and sets them accordingly.
+    pub fn set_phase(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.reset_phases();
+    }
+
+    /// Sets the phases according to the current [`Phase`] mode.
+    fn reset_phases(&mut self) {
+        match self.phase {
+            Phase::Zero => {
+                for val in self.val_mut() {
+                    *val = unt::Val::ZERO;
+                }
+            }
+            Phase::Even => {
+                let len = self.len();
+                for (index, val) in self.val_mut().enumerate() {
+                    // The quotient is less than one.
+                    #[allow(clippy::cast_precision_loss)]
+                    let phase = index as f64 / len as f64;
+                    *val = unt::Val::new(phase);
+                }
+            }
+            Phase::Random => self.randomize_phases(),
+        }
     }
 
     /// Randomizes the phases.
     ///
-    /// This can help if you're getting a lot of interference between the different curves.
+    /// This can help if you're getting a lot of interference between the different curves. To
+    /// also randomize them on every retrigger, use [`Phase::Random`].
     pub fn randomize_phases(&mut self) {
         for val in self.val_mut() {
             use rand::Rng;
@@ -197,10 +329,7 @@ where
     type Sample = C::Output;
 
     fn get(&self) -> C::Output {
-        self.val_inters
-            .iter()
-            .map(|&(val, _)| self.map().eval(val))
-            .sum()
+        self.outputs().sum()
     }
 }
 
@@ -209,15 +338,13 @@ where
     C::Output: smp::Sample,
 {
     fn advance(&mut self) {
-        for (val, interval) in &mut self.val_inters {
+        for (val, interval, _) in &mut self.voices {
             val.advance_freq(*interval * self.base);
         }
     }
 
     fn retrigger(&mut self) {
-        for vf in &mut self.val_inters {
-            vf.0 = unt::Val::ZERO;
-        }
+        self.reset_phases();
     }
 }
 
@@ -250,14 +377,14 @@ pub type Unison<S, C> = UnisonCurve<gen::CurvePlayer<S, C>>;
 impl<S: smp::Sample, C: Map<Input = unt::Val, Output = f64>> Unison<S, C> {
     /// Initializes a new [`Unison`].
     ///
-    /// This will play multiple copies of a curve at the specified frequency multipliers, with the
-    /// given initial phases.
+    /// This will play multiple copies of a curve at the specified frequency multipliers and gains,
+    /// with the given initial phases.
     pub fn new_phases<I: IntoIterator<Item = f64>>(
         map: C,
         base: unt::Freq,
-        val_inters: Vec<(unt::Val, unt::Interval)>,
+        voices: Vec<(unt::Val, unt::Interval, unt::Vol)>,
     ) -> Self {
-        Self::new_curve_phases(gen::CurvePlayer::new(map), base, val_inters)
+        Self::new_curve_phases(gen::CurvePlayer::new(map), base, voices)
     }
 
     /// Initializes a new [`Unison`].
@@ -280,6 +407,207 @@ impl<S: smp::Sample, C: Map<Input = unt::Val, Output = f64>> Unison<S, C> {
     pub fn detune(map: C, base: unt::Freq, detune: unt::Interval, num: u16) -> Self {
         Self::detune_curve(gen::CurvePlayer::new(map), base, detune, num)
     }
+
+    /// Plays copies of a curve at the given ratios from the base frequency, as the partials of a
+    /// single sound.
+    ///
+    /// See [`UnisonCurve::overtones_curve`] for more info.
+    pub fn overtones<I: IntoIterator<Item = f64>>(
+        map: C,
+        base: unt::Freq,
+        ratios: I,
+        rolloff: f64,
+    ) -> Self {
+        Self::overtones_curve(gen::CurvePlayer::new(map), base, ratios, rolloff)
+    }
+
+    /// Plays copies of a curve at the first `num` harmonics of the base frequency.
+    ///
+    /// See [`UnisonCurve::overtones_curve`] for more info.
+    ///
+    /// ## Example
+    ///
+    /// We build a square-ish wave from its odd harmonics.
+    ///
+    /// ```
+    /// # use pointillism::prelude::*;
+    /// let base = unt::Freq::from_raw_default(unt::RawFreq::A3);
+    /// let sgn = poly::Unison::<smp::Mono, _>::overtones(
+    ///     crv::Sin,
+    ///     base,
+    ///     (0..8).map(|n| f64::from(2 * n + 1)),
+    ///     1.0,
+    /// );
+    ///
+    /// Song::new(
+    ///     unt::Time::from_sec_default(1.0),
+    ///     unt::SampleRate::default(),
+    ///     eff::Volume::new(sgn, unt::Vol::new(0.5)),
+    /// )
+    /// .export("examples/overtones.wav");
+    /// ```
+    pub fn harmonics(map: C, base: unt::Freq, num: u16, rolloff: f64) -> Self {
+        Self::harmonics_curve(gen::CurvePlayer::new(map), base, num, rolloff)
+    }
+
+    /// Plays `num` detuned copies of a curve around each of the given partials.
+    ///
+    /// See [`UnisonCurve::detune_overtones_curve`] for more info.
+    pub fn detune_overtones<I: IntoIterator<Item = f64>>(
+        map: C,
+        base: unt::Freq,
+        ratios: I,
+        rolloff: f64,
+        detune: unt::Interval,
+        num: u16,
+    ) -> Self {
+        Self::detune_overtones_curve(
+            gen::CurvePlayer::new(map),
+            base,
+            ratios,
+            rolloff,
+            detune,
+            num,
+        )
+    }
+}
+
+/// Evenly spaces an index from `0.0` to `1.0`. A single index is placed at `0.5`.
+fn spread_val(index: usize, len: usize) -> unt::Val {
+    if len <= 1 {
+        unt::Val::HALF
+    } else {
+        // The quotient is between zero and one.
+        #[allow(clippy::cast_precision_loss)]
+        unt::Val::new(index as f64 / (len - 1) as f64)
+    }
+}
+
+/// Pans the curves in a [`UnisonCurve`] across the stereo field, according to a pan [`Law`].
+///
+/// The inner signal can be the [`UnisonCurve`] itself, or any signal whose [`Base`] is one, such as
+/// a [`DetuneCurveSgn`].
+///
+/// ## Example
+///
+/// We play a detuned saw, whose copies are spread out from left to right.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let base = unt::Freq::from_raw_default(unt::RawFreq::A2);
+/// let mut unison =
+///     poly::Unison::<smp::Mono, _>::detune(crv::Saw, base, unt::Interval::note(0.1), 7);
+/// unison.set_phase(poly::Phase::Random);
+///
+/// let sgn = poly::Spread::<_, eff::pan::Power>::new(unison, 1.0);
+/// Song::new(
+///     unt::Time::from_sec_default(1.0),
+///     unt::SampleRate::default(),
+///     eff::Volume::new(sgn, unt::Vol::new(0.1)),
+/// )
+/// .export("examples/spread.wav");
+/// ```
+///
+/// [`Law`]: eff::pan::Law
+pub struct Spread<S: SignalMut, P: eff::pan::Law> {
+    /// The inner signal.
+    sgn: S,
+    /// The pan law for each curve.
+    pans: Vec<P>,
+}
+
+impl<C: Map<Input = unt::Val>, S: Base<Base = UnisonCurve<C>>, P: eff::pan::Law> Spread<S, P>
+where
+    C::Output: Audio,
+{
+    /// Initializes a new [`Spread`]. The curves are spread out evenly, from left to right, with a
+    /// given width between `0.0` and `1.0`.
+    pub fn new(sgn: S, width: f64) -> Self {
+        let mut spread = Self {
+            pans: vec![P::default(); sgn.base().len()],
+            sgn,
+        };
+        spread.set_width(width);
+        spread
+    }
+
+    /// Returns a reference to the inner signal.
+    pub const fn sgn(&self) -> &S {
+        &self.sgn
+    }
+
+    /// Returns a mutable reference to the inner signal.
+    pub fn sgn_mut(&mut self) -> &mut S {
+        &mut self.sgn
+    }
+
+    /// The pan law for each curve.
+    pub fn pans(&self) -> &[P] {
+        &self.pans
+    }
+
+    /// A mutable reference to the pan law for each curve.
+    pub fn pans_mut(&mut self) -> &mut [P] {
+        &mut self.pans
+    }
+
+    /// Spreads out the curves evenly, from left to right, with a given width between `0.0` and
+    /// `1.0`.
+    pub fn set_width(&mut self, width: f64) {
+        let len = self.pans.len();
+        for (index, pan) in self.pans.iter_mut().enumerate() {
+            *pan.angle_mut() = 0.5 + width * (spread_val(index, len).inner() - 0.5);
+        }
+    }
+}
+
+impl<C: Map<Input = unt::Val>, S: Base<Base = UnisonCurve<C>>, P: eff::pan::Law> Signal
+    for Spread<S, P>
+where
+    C::Output: Audio,
+{
+    type Sample = smp::Stereo;
+
+    fn get(&self) -> smp::Stereo {
+        (self.sgn.base().outputs())
+            .zip(&self.pans)
+            .map(|(sample, pan)| {
+                let smp::Stereo(sl, sr) = sample.duplicate();
+                let (gl, gr) = pan.gain();
+                smp::Stereo(sl * gl, sr * gr)
+            })
+            .sum()
+    }
+}
+
+impl<C: Map<Input = unt::Val>, S: Base<Base = UnisonCurve<C>>, P: eff::pan::Law> SignalMut
+    for Spread<S, P>
+where
+    C::Output: Audio,
+{
+    fn advance(&mut self) {
+        self.sgn.advance();
+    }
+
+    fn retrigger(&mut self) {
+        self.sgn.retrigger();
+    }
+}
+
+impl<C: Map<Input = unt::Val>, S: Base<Base = UnisonCurve<C>>, P: eff::pan::Law> Base
+    for Spread<S, P>
+where
+    C::Output: Audio,
+{
+    type Base = UnisonCurve<C>;
+
+    fn base(&self) -> &UnisonCurve<C> {
+        self.sgn.base()
+    }
+
+    fn base_mut(&mut self) -> &mut UnisonCurve<C> {
+        self.sgn.base_mut()
+    }
 }
 
 /// The function that applies a detune effect to a [`UnisonCurve`].
@@ -371,7 +699,7 @@ impl<S: smp::Sample, C: Map<Input = unt::Val, Output = f64>, E: SignalMut<Sample
 /// let len = unt::Time::from_raw_default(LEN);
 ///
 /// // Plays a number of notes, and detunes them up to an octave.
-/// let mut unison = gen::uni::DetuneSgn::<smp::Mono, _, _>::new_detune(
+/// let mut unison = poly::DetuneSgn::<smp::Mono, _, _>::new_detune(
 ///     crv::Saw,
 ///     unt::Freq::from_raw_default(unt::RawFreq::A3),
 ///     NUM,
@@ -387,7 +715,7 @@ impl<S: smp::Sample, C: Map<Input = unt::Val, Output = f64>, E: SignalMut<Sample
 ///         .into_iter()
 ///         .map(|i| {
 ///             eff::pan::MixedPanner::new_pan(
-///                 gen::uni::UnisonRef::new(unison.base(), i),
+///                 poly::UnisonRef::new(unison.base(), i),
 ///                 i as f64 / (NUM - 1) as f64,
 ///             )
 ///             .get()
@@ -431,3 +759,35 @@ where
         self.unison.get_at(self.index)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test the gains of the partials built from overtones.
+    #[test]
+    fn overtones() {
+        let unison = Unison::<smp::Mono, _>::harmonics(crv::Sin, unt::Freq::new(0.01), 4, 1.0);
+        let gains: Vec<_> = unison.gains().map(|vol| vol.gain).collect();
+        for (gain, expected) in gains.into_iter().zip([1.0, 0.5, 1.0 / 3.0, 0.25]) {
+            assert_approx_eq::assert_approx_eq!(gain, expected);
+        }
+
+        let intervals: Vec<_> = unison.intervals().map(|int| int.ratio).collect();
+        assert_eq!(intervals.len(), 4);
+        assert_approx_eq::assert_approx_eq!(intervals[3], 4.0);
+    }
+
+    /// Test that a fully spread unison pans its outer curves hard left and right.
+    #[test]
+    fn spread() {
+        let unison =
+            Unison::<smp::Mono, _>::new(crv::Sin, unt::Freq::new(0.01), [unt::Interval::UNISON; 3]);
+        let spread = Spread::<_, eff::pan::Linear>::new(unison, 1.0);
+        let angles: Vec<_> = spread.pans().iter().map(eff::pan::Law::angle).collect();
+
+        for (angle, expected) in angles.into_iter().zip([0.0, 0.5, 1.0]) {
+            assert_approx_eq::assert_approx_eq!(angle, expected);
+        }
+    }
+}
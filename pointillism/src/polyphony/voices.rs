@@ -0,0 +1,482 @@
+//! Implements the [`Voices`] type, a polyphony with a limited number of voices.
+
+use crate::prelude::*;
+
+/// How fast the level of a voice decays, per sample. This gives a time constant of around 20 ms at
+/// the default sample rate.
+const LEVEL_DECAY: f64 = 0.999;
+
+/// The policy used by [`Voices`] to choose which voice to steal when all of them are in use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Steal {
+    /// Steals the voice that was added first.
+    #[default]
+    Oldest,
+    /// Steals the voice with the lowest level.
+    ///
+    /// The level is estimated by a peak meter with a fast decay. Voices that haven't yet been
+    /// advanced have no level, and are never stolen.
+    Quietest,
+    /// Steals the voice with the lowest frequency.
+    ///
+    /// This requires adding signals through [`Voices::add_by_freq`]. Otherwise, the oldest voice
+    /// is stolen.
+    Lowest,
+    /// Steals the voice with the highest frequency.
+    ///
+    /// This requires adding signals through [`Voices::add_by_freq`]. Otherwise, the oldest voice
+    /// is stolen.
+    Highest,
+    /// Steals the oldest voice that has been stopped, or the oldest voice if none has.
+    Released,
+}
+
+/// A voice in a [`Voices`] struct.
+#[derive(Clone, Debug)]
+struct Voice<K, S> {
+    /// The key of the voice.
+    key: K,
+    /// The signal being played.
+    sgn: S,
+    /// The order in which the voice was added.
+    age: u64,
+    /// Whether the voice has been stopped.
+    released: bool,
+    /// An estimate of the level of the voice.
+    ///
+    /// This is infinite until the voice is first advanced.
+    level: f64,
+}
+
+/// A voice that was stolen, and is fading out.
+#[derive(Clone, Debug)]
+struct Fading<S> {
+    /// The signal being faded out.
+    sgn: S,
+    /// The current gain of the signal.
+    gain: f64,
+}
+
+/// A polyphonic signal with a fixed number of voices.
+///
+/// This works like [`poly::Polyphony`], except that at most a given number of signals can play at
+/// once. When a signal is added and all voices are in use, one of them is stolen according to a
+/// [`Steal`] policy.
+///
+/// Instead of being cut off, which would cause a click, the stolen voice is quickly faded out over
+/// the time set in [`Self::fade`]. A fading voice no longer counts towards the voice limit, and can
+/// no longer be accessed through its key.
+///
+/// The memory for all voices is reserved on initialization, so that adding and removing signals
+/// never allocates.
+///
+/// ## Example
+///
+/// We play a chromatic scale using only three voices. Since each note takes a while to release,
+/// older notes get stolen.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let sec = unt::Time::from_sec_default;
+/// let sgn = |note: i32| {
+///     eff::env::AdsrEnv::new(
+///         gen::Loop::<smp::Mono, _>::new(
+///             crv::Saw,
+///             unt::Freq::from_raw_default(unt::RawFreq::C4.bend(f64::from(note))),
+///         ),
+///         eff::env::Adsr::new(sec(0.05), sec(0.5), unt::Vol::HALF, sec(1.0)),
+///     )
+/// };
+///
+/// let mut note = 0;
+/// let seq = ctr::Seq::new(
+///     vec![sec(0.25); 13],
+///     poly::Voices::new(3, poly::Steal::Oldest),
+///     map::Func::new(move |voices: &mut poly::Voices<_, _>| {
+///         voices.stop(&(note - 1));
+///         if note < 12 {
+///             voices.add(note, sgn(note));
+///         }
+///
+///         assert!(voices.len() <= 3);
+///         note += 1;
+///     }),
+/// );
+///
+/// Song::new(sec(4.5), unt::SampleRate::default(), eff::Volume::new(seq, unt::Vol::new(0.3)))
+///     .export("examples/voices.wav");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Voices<K: Eq + Clone, S: Done> {
+    /// The voices currently playing.
+    active: Vec<Voice<K, S>>,
+    /// The voices that were stolen and are fading out.
+    fading: Vec<Fading<S>>,
+    /// The maximum number of voices.
+    capacity: usize,
+    /// The order of the next voice to be added.
+    next_age: u64,
+
+    /// The policy used to steal voices.
+    pub steal: Steal,
+    /// The time it takes for a stolen voice to fade out.
+    pub fade: unt::Time,
+}
+
+impl<K: Eq + Clone, S: Done> Voices<K, S> {
+    /// The default time it takes for a stolen voice to fade out.
+    ///
+    /// This is about 5 ms at the default sample rate.
+    pub const FADE: unt::Time = unt::Time::from_samples(220);
+
+    /// Initializes a new polyphonic signal with a given number of voices, playing nothing.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the capacity is zero.
+    #[must_use]
+    pub fn new(capacity: usize, steal: Steal) -> Self {
+        assert_ne!(capacity, 0, "there must be at least one voice");
+
+        Self {
+            active: Vec::with_capacity(capacity),
+            fading: Vec::with_capacity(capacity),
+            capacity,
+            next_age: 0,
+            steal,
+            fade: Self::FADE,
+        }
+    }
+
+    /// The maximum number of voices.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of active voices. This doesn't count stolen voices that are fading out.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Whether there are no active voices.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Whether all voices are in use, so that adding a new signal will steal one of them.
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity
+    }
+
+    /// Returns an iterator over the active signals and their keys.
+    pub fn signals(&self) -> impl Iterator<Item = (&K, &S)> {
+        self.active.iter().map(|voice| (&voice.key, &voice.sgn))
+    }
+
+    /// Returns an iterator over mutable references to the active signals and their keys.
+    pub fn signals_mut(&mut self) -> impl Iterator<Item = (&K, &mut S)> {
+        self.active
+            .iter_mut()
+            .map(|voice| (&voice.key, &mut voice.sgn))
+    }
+
+    /// The index of the voice with a given key.
+    fn position(&self, key: &K) -> Option<usize> {
+        self.active.iter().position(|voice| &voice.key == key)
+    }
+
+    /// The index of the voice to steal.
+    ///
+    /// The pitch policies need a function returning the frequency of a signal. Without it, they
+    /// steal the oldest voice.
+    fn victim(&self, freq: Option<fn(&S) -> f64>) -> usize {
+        let oldest = || self.active.iter().enumerate().min_by_key(|(_, v)| v.age);
+        let index = match (self.steal, freq) {
+            (Steal::Oldest, _) | (Steal::Lowest | Steal::Highest, None) => oldest(),
+            (Steal::Quietest, _) => (self.active.iter().enumerate())
+                .min_by(|(_, v), (_, w)| v.level.total_cmp(&w.level)),
+            (Steal::Lowest, Some(freq)) => (self.active.iter().enumerate())
+                .min_by(|(_, v), (_, w)| freq(&v.sgn).total_cmp(&freq(&w.sgn))),
+            (Steal::Highest, Some(freq)) => (self.active.iter().enumerate())
+                .max_by(|(_, v), (_, w)| freq(&v.sgn).total_cmp(&freq(&w.sgn))),
+            (Steal::Released, _) => {
+                (self.active.iter().enumerate()).min_by_key(|(_, v)| (!v.released, v.age))
+            }
+        };
+
+        index.expect("there must be at least one voice").0
+    }
+
+    /// Starts fading out a signal.
+    fn fade_out(&mut self, sgn: S) {
+        // If there's too many voices fading out, we cut off the quietest one.
+        if self.fading.len() == self.capacity {
+            let index = (self.fading.iter().enumerate())
+                .min_by(|(_, f), (_, g)| f.gain.total_cmp(&g.gain))
+                .expect("there must be at least one voice")
+                .0;
+            self.fading.swap_remove(index);
+        }
+
+        self.fading.push(Fading { sgn, gain: 1.0 });
+    }
+
+    /// Adds a signal, given the function used by the pitch policies.
+    fn insert(&mut self, key: K, sgn: S, freq: Option<fn(&S) -> f64>) -> Option<K> {
+        let index = self
+            .position(&key)
+            .or_else(|| self.is_full().then(|| self.victim(freq)));
+
+        let voice = Voice {
+            key,
+            sgn,
+            age: self.next_age,
+            released: false,
+            level: f64::INFINITY,
+        };
+        self.next_age += 1;
+
+        if let Some(index) = index {
+            let old = std::mem::replace(&mut self.active[index], voice);
+            self.fade_out(old.sgn);
+            Some(old.key)
+        } else {
+            self.active.push(voice);
+            None
+        }
+    }
+
+    /// Adds a signal, using a given key.
+    ///
+    /// If the key was already in use, the old signal is faded out and replaced. Otherwise, if all
+    /// voices are in use, one of them is stolen and faded out. In either case, the key of the
+    /// signal that was replaced is returned.
+    ///
+    /// The [`Steal::Lowest`] and [`Steal::Highest`] policies need to know the frequency of each
+    /// signal, so they steal the oldest voice instead. See [`Self::add_by_freq`].
+    pub fn add(&mut self, key: K, sgn: S) -> Option<K> {
+        self.insert(key, sgn, None)
+    }
+
+    /// Adds a signal, using a given key.
+    ///
+    /// This works like [`Self::add`], except that the [`Steal::Lowest`] and [`Steal::Highest`]
+    /// policies compare the frequencies of the signals.
+    pub fn add_by_freq(&mut self, key: K, sgn: S) -> Option<K>
+    where
+        S: Frequency,
+    {
+        self.insert(key, sgn, Some(|sgn| sgn.freq().samples))
+    }
+
+    /// Gets a reference to a particular signal.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&S> {
+        self.position(key).map(|index| &self.active[index].sgn)
+    }
+
+    /// Gets a mutable reference to a particular signal.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut S> {
+        self.position(key).map(|index| &mut self.active[index].sgn)
+    }
+
+    /// Modifies a signal with the given key using the specified function.
+    ///
+    /// Returns whether the signal was found.
+    pub fn modify<F: Fn(&mut S)>(&mut self, key: &K, f: F) -> bool {
+        if let Some(sgn) = self.get_mut(key) {
+            f(sgn);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stops a given signal, returns whether it was successful.
+    pub fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop,
+    {
+        if let Some(index) = self.position(key) {
+            let voice = &mut self.active[index];
+            voice.sgn.stop();
+            voice.released = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sends an expression event to a given signal, returns whether it was successful.
+    pub fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.modify(key, |sgn| sgn.express(expr))
+    }
+
+    /// Stops all signals currently playing.
+    pub fn stop_all(&mut self)
+    where
+        S: Stop,
+    {
+        for voice in &mut self.active {
+            voice.sgn.stop();
+            voice.released = true;
+        }
+    }
+}
+
+impl<K: Eq + Clone, S: Done> Signal for Voices<K, S> {
+    type Sample = S::Sample;
+
+    fn get(&self) -> S::Sample {
+        self.active
+            .iter()
+            .map(|voice| voice.sgn.get())
+            .sum::<S::Sample>()
+            + (self.fading.iter())
+                .map(|fading| fading.sgn.get() * fading.gain)
+                .sum::<S::Sample>()
+    }
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> SignalMut for Voices<K, S> {
+    fn advance(&mut self) {
+        for voice in &mut self.active {
+            voice.sgn.advance();
+            let sample = voice.sgn.get();
+            let peak = sample.fst().abs().max(sample.snd().abs());
+            voice.level = if voice.level.is_finite() {
+                peak.max(voice.level * LEVEL_DECAY)
+            } else {
+                peak
+            };
+        }
+        self.active.retain(|voice| !voice.sgn.is_done());
+
+        let step = unt::Time::SAMPLE / self.fade;
+        for fading in &mut self.fading {
+            fading.sgn.advance();
+            fading.gain -= step;
+        }
+        self.fading
+            .retain(|fading| fading.gain > 0.0 && !fading.sgn.is_done());
+    }
+
+    fn retrigger(&mut self) {
+        self.active.clear();
+        self.fading.clear();
+    }
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> Base for Voices<K, S> {
+    impl_base!();
+}
+
+impl<K: Eq + Clone, S: SignalMut + Done> Panic for Voices<K, S> {
+    fn panic(&mut self) {
+        self.retrigger();
+    }
+}
+
+/// Voices are added through [`Voices::add_by_freq`], so that every stealing policy can be used.
+impl<K: Eq + Clone, S: SignalMut + Done + Frequency> poly::Polyphonic<K, S> for Voices<K, S> {
+    fn add(&mut self, key: K, sgn: S) {
+        self.add_by_freq(key, sgn);
+    }
+
+    fn stop(&mut self, key: &K) -> bool
+    where
+        S: Stop,
+    {
+        self.stop(key)
+    }
+
+    fn express(&mut self, key: &K, expr: ctr::Expr) -> bool
+    where
+        S: ctr::Express,
+    {
+        self.express(key, expr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A signal with a given frequency, which never stops.
+    type Voice = eff::Trailing<gen::Loop<smp::Mono, crv::Saw>>;
+
+    /// Initializes a voice with a given frequency.
+    fn voice(freq: f64) -> Voice {
+        eff::Trailing::new(gen::Loop::new(crv::Saw, unt::Freq::new(freq)))
+    }
+
+    /// The keys of the active voices, in order.
+    fn keys(voices: &Voices<u8, Voice>) -> Vec<u8> {
+        let mut keys: Vec<_> = voices.signals().map(|(&key, _)| key).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Test the different stealing policies.
+    #[test]
+    fn steal() {
+        for (steal, kept) in [
+            (Steal::Oldest, [1, 2, 3]),
+            (Steal::Quietest, [0, 2, 3]),
+            (Steal::Lowest, [1, 2, 3]),
+            (Steal::Highest, [0, 2, 3]),
+            (Steal::Released, [0, 2, 3]),
+        ] {
+            let mut voices = Voices::new(3, steal);
+            voices.add_by_freq(0, voice(0.01));
+            voices.add_by_freq(1, voice(0.04));
+            voices.add_by_freq(2, voice(0.02));
+            voices.stop(&1);
+
+            // Each saw starts at -1, so the one with the highest frequency is now the quietest.
+            voices.advance();
+            assert!(voices.add_by_freq(3, voice(0.03)).is_some());
+            assert_eq!(keys(&voices), kept);
+            assert_eq!(voices.len(), 3);
+        }
+    }
+
+    /// Test that adding a chord at capacity doesn't steal voices added in the same sample.
+    #[test]
+    fn chord() {
+        for steal in [Steal::Oldest, Steal::Quietest, Steal::Released] {
+            let mut voices = Voices::new(3, steal);
+            for key in 0..3 {
+                voices.add(key, voice(0.01));
+            }
+            voices.advance();
+
+            for key in 3..6 {
+                voices.add(key, voice(0.01));
+            }
+            assert_eq!(keys(&voices), [3, 4, 5]);
+        }
+    }
+
+    /// Test that stolen voices fade out.
+    #[test]
+    fn fade() {
+        let mut voices = Voices::new(1, Steal::Oldest);
+        voices.fade = unt::Time::from_samples(4);
+        voices.add(0, voice(0.0));
+        voices.add(1, voice(0.0));
+        assert_eq!(voices.len(), 1);
+
+        for _ in 0..4 {
+            voices.advance();
+        }
+        assert!(voices.fading.is_empty());
+    }
+}
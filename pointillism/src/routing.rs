@@ -67,6 +67,8 @@ impl<X: Panic<Sample = smp::Mono>, Y: Panic<Sample = smp::Mono>> Panic for Stere
 ///
 /// If you want to mix more signals together (e.g. an entire song), it might be easier to manually
 /// add the samples instead.
+///
+/// If both signals implement `Render`, they can be rendered in parallel.
 pub struct Mix<X: Signal, Y: Signal<Sample = X::Sample>>(pub X, pub Y);
 
 impl<X: Signal, Y: Signal<Sample = X::Sample>> Mix<X, Y> {
@@ -96,6 +98,29 @@ impl<X: SignalMut, Y: SignalMut<Sample = X::Sample>> SignalMut for Mix<X, Y> {
     }
 }
 
+/// Renders both signals in parallel, one [block](sgn::BLOCK_LEN) at a time. The second signal is
+/// rendered into a buffer on the stack, so this never allocates.
+#[cfg(feature = "rayon")]
+impl<X: Render + Send, Y: Render<Sample = X::Sample> + Send> Render for Mix<X, Y>
+where
+    X::Sample: Send,
+{
+    fn render(&mut self, buf: &mut [X::Sample], workers: &sgn::Workers) {
+        let mut other = [X::Sample::ZERO; sgn::BLOCK_LEN];
+        for buf in buf.chunks_mut(sgn::BLOCK_LEN) {
+            let other = &mut other[..buf.len()];
+            workers.join(
+                || self.0.render(buf, workers),
+                || self.1.render(other, workers),
+            );
+
+            for (x, &y) in buf.iter_mut().zip(other.iter()) {
+                *x += y;
+            }
+        }
+    }
+}
+
 impl<X: Done, Y: Done<Sample = X::Sample>> Done for Mix<X, Y> {
     fn is_done(&self) -> bool {
         self.0.is_done() && self.1.is_done()
@@ -188,3 +188,94 @@ pub trait Panic: SignalMut {
     /// Stops all subsequent sound.
     fn panic(&mut self);
 }
+
+/// The largest number of frames that [`Render`] implementations process at once.
+///
+/// Longer buffers are split into blocks of this length, so that any intermediate buffers can be
+/// allocated once, or kept on the stack.
+#[cfg(feature = "rayon")]
+pub const BLOCK_LEN: usize = 512;
+
+/// A pool of worker threads, used to [`Render`] signals in parallel.
+///
+/// The threads are spawned on initialization and reused for every block, so that rendering
+/// many short blocks, as a [`ctr::Seq`](crate::ctr::Seq) does between events, stays cheap.
+#[cfg(feature = "rayon")]
+#[derive(Debug)]
+pub struct Workers(rayon::ThreadPool);
+
+#[cfg(feature = "rayon")]
+impl Workers {
+    /// Spawns a given number of worker threads. A thread count of zero is treated as one.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the threads can't be spawned.
+    #[must_use]
+    pub fn new(threads: usize) -> Self {
+        Self(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads.max(1))
+                .build()
+                .expect("could not spawn worker threads"),
+        )
+    }
+
+    /// The number of worker threads.
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.0.current_num_threads()
+    }
+
+    /// Runs two closures, possibly in parallel, and returns both results.
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        self.0.install(|| rayon::join(a, b))
+    }
+
+    /// Creates a scope in which tasks can be spawned onto the workers. Every task is finished by
+    /// the time this returns.
+    pub fn scope<'scope, F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&rayon::Scope<'scope>) -> R + Send,
+        R: Send,
+    {
+        self.0.scope(f)
+    }
+}
+
+/// A signal that can be rendered a block of frames at a time, possibly splitting the work among
+/// multiple threads. Requires the `rayon` feature.
+///
+/// Rendering a block must produce exactly the same samples, bit for bit, as calling
+/// [`SignalMut::next`] once per frame, and must leave the signal in the same state. This means
+/// that parallel rendering is purely an optimization, and can be turned on or off without changing
+/// the output.
+///
+/// The provided implementation of [`render`](Render::render) is the serial one. It can be used for
+/// custom signals by simply writing `impl Render for MySignal {}`.
+///
+/// ## Limitations
+///
+/// Only containers of independent signals, like [`poly::Polyphony`](crate::poly::Polyphony) and [`rtn::Mix`](crate::rtn::Mix), actually
+/// render in parallel. Sequences, and wrappers which map each sample on its own, like
+/// [`eff::MapSgn`](crate::eff::MapSgn) and [`eff::Volume`](crate::eff::Volume), forward rendering to the signals they contain.
+///
+/// Effects which keep state between samples, such as filters, dynamics, delays, and reverbs, don't
+/// implement this trait, since they need their input one frame at a time. Any signal containing
+/// them must be rendered serially.
+#[cfg(feature = "rayon")]
+pub trait Render: SignalMut {
+    /// Fills a buffer with the next samples of the signal, using the given worker threads.
+    fn render(&mut self, buf: &mut [Self::Sample], workers: &Workers) {
+        let _ = workers;
+        for sample in buf {
+            *sample = self.next();
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Helpers shared among the tests of different modules.
+
+use crate::prelude::*;
+
+/// A signal which outputs a given sample at a given time, and silence otherwise.
+///
+/// This is used to measure the impulse response of effects.
+pub fn impulse<A: Audio>(at: u64, value: A) -> gen::Func<A, impl FnMut() -> A> {
+    let mut time = 0;
+    gen::Func::new(move || {
+        time += 1;
+        if time == at + 1 {
+            value
+        } else {
+            A::ZERO
+        }
+    })
+}
+
+/// The largest value in a slice, or zero if it's empty.
+pub fn max(values: &[f64]) -> f64 {
+    values.iter().copied().fold(0.0, f64::max)
+}
@@ -0,0 +1,392 @@
+//! Defines the [`Chord`] type and the common chord [`Quality`] names.
+
+use super::{parse_pitch_class, ChordError};
+use crate::prelude::*;
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+/// The quality of a chord in 12-EDO, such as major, minor, or dominant seventh.
+///
+/// See [`Chord::new_quality`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// Major triad.
+    Major,
+    /// Minor triad.
+    Minor,
+    /// Diminished triad.
+    Dim,
+    /// Augmented triad.
+    Aug,
+    /// Suspended second.
+    Sus2,
+    /// Suspended fourth.
+    Sus4,
+    /// Power chord, made out of a root and a fifth.
+    Power,
+    /// Major sixth.
+    Maj6,
+    /// Minor sixth.
+    Min6,
+    /// Dominant seventh.
+    Dom7,
+    /// Major seventh.
+    Maj7,
+    /// Minor seventh.
+    Min7,
+    /// Half-diminished seventh.
+    HalfDim7,
+    /// Diminished seventh.
+    Dim7,
+    /// Minor-major seventh.
+    MinMaj7,
+    /// Augmented seventh.
+    Aug7,
+    /// Major triad with an added ninth.
+    Add9,
+    /// Dominant ninth.
+    Dom9,
+    /// Major ninth.
+    Maj9,
+    /// Minor ninth.
+    Min9,
+}
+
+impl Quality {
+    /// The semitones above the root for each note in the chord.
+    #[must_use]
+    pub const fn semitones(self) -> &'static [i8] {
+        match self {
+            Self::Major => &[0, 4, 7],
+            Self::Minor => &[0, 3, 7],
+            Self::Dim => &[0, 3, 6],
+            Self::Aug => &[0, 4, 8],
+            Self::Sus2 => &[0, 2, 7],
+            Self::Sus4 => &[0, 5, 7],
+            Self::Power => &[0, 7],
+            Self::Maj6 => &[0, 4, 7, 9],
+            Self::Min6 => &[0, 3, 7, 9],
+            Self::Dom7 => &[0, 4, 7, 10],
+            Self::Maj7 => &[0, 4, 7, 11],
+            Self::Min7 => &[0, 3, 7, 10],
+            Self::HalfDim7 => &[0, 3, 6, 10],
+            Self::Dim7 => &[0, 3, 6, 9],
+            Self::MinMaj7 => &[0, 3, 7, 11],
+            Self::Aug7 => &[0, 4, 8, 10],
+            Self::Add9 => &[0, 4, 7, 14],
+            Self::Dom9 => &[0, 4, 7, 10, 14],
+            Self::Maj9 => &[0, 4, 7, 11, 14],
+            Self::Min9 => &[0, 3, 7, 10, 14],
+        }
+    }
+
+    /// The symbol used for this quality in chord names, such as `"m7"` for a minor seventh.
+    ///
+    /// Many other common symbols are accepted when parsing.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Major => "",
+            Self::Minor => "m",
+            Self::Dim => "dim",
+            Self::Aug => "aug",
+            Self::Sus2 => "sus2",
+            Self::Sus4 => "sus4",
+            Self::Power => "5",
+            Self::Maj6 => "6",
+            Self::Min6 => "m6",
+            Self::Dom7 => "7",
+            Self::Maj7 => "maj7",
+            Self::Min7 => "m7",
+            Self::HalfDim7 => "m7b5",
+            Self::Dim7 => "dim7",
+            Self::MinMaj7 => "mmaj7",
+            Self::Aug7 => "aug7",
+            Self::Add9 => "add9",
+            Self::Dom9 => "9",
+            Self::Maj9 => "maj9",
+            Self::Min9 => "m9",
+        }
+    }
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl FromStr for Quality {
+    type Err = ChordError;
+
+    fn from_str(symbol: &str) -> Result<Self, ChordError> {
+        Ok(match symbol {
+            "" | "M" | "maj" => Self::Major,
+            "m" | "min" | "-" => Self::Minor,
+            "dim" | "o" | "°" => Self::Dim,
+            "aug" | "+" => Self::Aug,
+            "sus2" => Self::Sus2,
+            "sus4" | "sus" => Self::Sus4,
+            "5" => Self::Power,
+            "6" | "M6" | "maj6" => Self::Maj6,
+            "m6" | "min6" | "-6" => Self::Min6,
+            "7" | "dom7" => Self::Dom7,
+            "maj7" | "M7" | "Δ" | "Δ7" => Self::Maj7,
+            "m7" | "min7" | "-7" => Self::Min7,
+            "m7b5" | "min7b5" | "-7b5" | "ø" | "ø7" => Self::HalfDim7,
+            "dim7" | "o7" | "°7" => Self::Dim7,
+            "mmaj7" | "mM7" | "minmaj7" | "-maj7" => Self::MinMaj7,
+            "aug7" | "+7" | "7#5" => Self::Aug7,
+            "add9" => Self::Add9,
+            "9" => Self::Dom9,
+            "maj9" | "M9" | "Δ9" => Self::Maj9,
+            "m9" | "min9" | "-9" => Self::Min9,
+            _ => return Err(ChordError::Quality(symbol.to_owned())),
+        })
+    }
+}
+
+/// A chord, represented as a root frequency together with a list of intervals above it.
+///
+/// The intervals are kept in ascending order. Note that the lowest interval needn't be the unison,
+/// for instance after an [inversion](Self::inversion) or when a [bass](Self::with_bass) is added.
+///
+/// ## Example
+///
+/// Chords can be parsed from their symbols. Roots are placed in the fourth octave, while slash
+/// basses are placed right below the root.
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let chord: unt::Chord = "Cmaj7/G".parse().unwrap();
+///
+/// assert_eq!(
+///     chord.midi_notes(),
+///     ["G3", "C4", "E4", "G4", "B4"].map(|name| name.parse::<unt::MidiNote>().unwrap())
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chord {
+    /// The root of the chord.
+    pub root: unt::RawFreq,
+    /// The intervals of the notes above the root, in ascending order.
+    intervals: Vec<unt::Interval>,
+}
+
+impl Chord {
+    /// Initializes a new chord from a root and the intervals of the notes above it.
+    #[must_use]
+    pub fn new(root: unt::RawFreq, mut intervals: Vec<unt::Interval>) -> Self {
+        intervals.sort_by(|a, b| a.ratio.total_cmp(&b.ratio));
+        Self { root, intervals }
+    }
+
+    /// Initializes a new chord in 12-EDO, from a root and the number of semitones of each note
+    /// above it.
+    #[must_use]
+    pub fn new_edo(root: unt::RawFreq, semitones: &[f64]) -> Self {
+        Self::new(
+            root,
+            semitones
+                .iter()
+                .map(|&note| unt::Interval::note(note))
+                .collect(),
+        )
+    }
+
+    /// Initializes a new chord with a given root and [`Quality`].
+    #[must_use]
+    pub fn new_quality(root: unt::RawFreq, quality: Quality) -> Self {
+        Self::new(
+            root,
+            quality
+                .semitones()
+                .iter()
+                .map(|&note| unt::Interval::note(f64::from(note)))
+                .collect(),
+        )
+    }
+
+    /// The intervals of the notes above the root, in ascending order.
+    #[must_use]
+    pub fn intervals(&self) -> &[unt::Interval] {
+        &self.intervals
+    }
+
+    /// The number of notes in the chord.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Whether the chord has no notes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Sorts the intervals in ascending order.
+    fn sort(&mut self) {
+        self.intervals.sort_by(|a, b| a.ratio.total_cmp(&b.ratio));
+    }
+
+    /// Returns the `n`-th inversion of the chord, by moving the lowest note up an octave `n`
+    /// times.
+    #[must_use]
+    pub fn inversion(mut self, n: usize) -> Self {
+        if !self.is_empty() {
+            for _ in 0..n {
+                self.intervals[0] *= unt::Interval::OCTAVE;
+                self.sort();
+            }
+        }
+
+        self
+    }
+
+    /// Returns a drop voicing of the chord, by moving the `n`-th highest note down an octave. For
+    /// instance, `drop_voicing(2)` gives the "drop 2" voicing.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `n` is zero or greater than the number of notes.
+    #[must_use]
+    pub fn drop_voicing(mut self, n: usize) -> Self {
+        assert!(n != 0 && n <= self.len(), "{}", crate::OOB);
+        let index = self.len() - n;
+        self.intervals[index] /= unt::Interval::OCTAVE;
+        self.sort();
+        self
+    }
+
+    /// Spreads the chord out, by moving every other note up an octave, starting from the second
+    /// lowest.
+    #[must_use]
+    pub fn open_voicing(mut self) -> Self {
+        for interval in self.intervals.iter_mut().skip(1).step_by(2) {
+            *interval *= unt::Interval::OCTAVE;
+        }
+
+        self.sort();
+        self
+    }
+
+    /// Adds a bass note to the chord, at the given interval from the root. This should usually be
+    /// less than a unison.
+    #[must_use]
+    pub fn with_bass(mut self, bass: unt::Interval) -> Self {
+        self.intervals.push(bass);
+        self.sort();
+        self
+    }
+
+    /// Transposes the chord by a given interval.
+    #[must_use]
+    pub fn transpose(mut self, interval: unt::Interval) -> Self {
+        self.root *= interval;
+        self
+    }
+
+    /// Transposes the chord by a number of octaves.
+    #[must_use]
+    pub fn octave(self, octaves: i32) -> Self {
+        self.transpose(unt::Interval::OCTAVE.powi(octaves))
+    }
+
+    /// The frequencies of the notes in the chord, in ascending order.
+    #[must_use]
+    pub fn raw_freqs(&self) -> Vec<unt::RawFreq> {
+        self.intervals
+            .iter()
+            .map(|&interval| self.root * interval)
+            .collect()
+    }
+
+    /// The MIDI notes in the chord, in ascending order.
+    ///
+    /// Notes outside of 12-EDO are rounded to the nearest MIDI note.
+    #[must_use]
+    pub fn midi_notes(&self) -> Vec<unt::MidiNote> {
+        self.raw_freqs()
+            .into_iter()
+            .map(unt::RawFreq::round_midi)
+            .collect()
+    }
+}
+
+/// Parses a chord symbol such as `"Cmaj7/G"`.
+///
+/// The root is placed in the fourth octave, and an optional bass note after a slash is placed in
+/// the octave right below it.
+impl FromStr for Chord {
+    type Err = ChordError;
+
+    fn from_str(symbol: &str) -> Result<Self, ChordError> {
+        let (root, rest) = parse_pitch_class(symbol)?;
+        let (quality, bass) = match rest.split_once('/') {
+            Some((quality, bass)) => (quality, Some(bass)),
+            None => (rest, None),
+        };
+
+        let midi = unt::MidiNote::new(root + unt::MidiNote::C4.note);
+        let mut chord = Self::new_quality(unt::RawFreq::new_midi(midi), quality.parse()?);
+
+        if let Some(bass) = bass {
+            let (bass, rest) = parse_pitch_class(bass)?;
+            if !rest.is_empty() {
+                return Err(ChordError::Quality(symbol.to_owned()));
+            }
+
+            let semitones = (bass - root).rem_euclid(12) - 12;
+            chord = chord.with_bass(unt::Interval::note(f64::from(semitones)));
+        }
+
+        Ok(chord)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Parses a list of note names.
+    fn notes<const N: usize>(names: [&str; N]) -> [unt::MidiNote; N] {
+        names.map(|name| name.parse().unwrap())
+    }
+
+    /// Test chord symbol parsing.
+    #[test]
+    fn parse() {
+        let chord: Chord = "F#m7".parse().unwrap();
+        assert_eq!(chord.midi_notes(), notes(["F#4", "A4", "C#5", "E5"]));
+
+        let chord: Chord = "Bbsus4/F".parse().unwrap();
+        assert_eq!(chord.midi_notes(), notes(["F4", "A#4", "D#5", "F5"]));
+
+        // Accidentals may cross into the neighboring octave.
+        let chord: Chord = "Cb".parse().unwrap();
+        assert_eq!(chord.midi_notes(), notes(["B3", "D#4", "F#4"]));
+
+        let chord: Chord = "B#/E".parse().unwrap();
+        assert_eq!(chord.midi_notes(), notes(["E4", "C5", "E5", "G5"]));
+
+        assert_eq!("H7".parse::<Chord>(), Err(ChordError::Letter('H')));
+        assert!("Cfoo".parse::<Chord>().is_err());
+    }
+
+    /// Test inversions and voicings.
+    #[test]
+    fn voicings() {
+        let chord = Chord::new_quality(unt::RawFreq::C4, Quality::Maj7);
+        assert_eq!(
+            chord.clone().inversion(1).midi_notes(),
+            notes(["E4", "G4", "B4", "C5"])
+        );
+        assert_eq!(
+            chord.drop_voicing(2).midi_notes(),
+            notes(["G3", "C4", "E4", "B4"])
+        );
+    }
+}
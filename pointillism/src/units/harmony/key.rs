@@ -0,0 +1,192 @@
+//! Defines the [`Key`] type.
+
+use super::{ChordError, Quality};
+use crate::prelude::*;
+
+/// A musical key, consisting of a tonic and a [`unt::Scale`] built on top of it.
+///
+/// Besides giving the notes of the scale, a key allows building chords from its degrees, either
+/// diatonically via [`Self::triad`] and [`Self::seventh`], or from roman numerals via
+/// [`Self::numeral`].
+///
+/// As with scales, degrees are counted **from zero**, so that degree `0` is the tonic. Roman
+/// numerals start from `I` as usual.
+///
+/// ## Example
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let key = unt::Key::major(unt::RawFreq::C4);
+///
+/// // A ii-V-I progression.
+/// let progression: Vec<_> = ["ii7", "V7", "Imaj7"]
+///     .into_iter()
+///     .map(|numeral| key.numeral(numeral).unwrap().midi_notes())
+///     .collect();
+///
+/// assert_eq!(
+///     progression[1],
+///     ["G4", "B4", "D5", "F5"].map(|name| name.parse::<unt::MidiNote>().unwrap())
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Key {
+    /// The tonic of the key.
+    pub tonic: unt::RawFreq,
+    /// The scale of the key.
+    pub scale: unt::Scale,
+}
+
+impl Key {
+    /// Initializes a new key from a tonic and a scale.
+    #[must_use]
+    pub const fn new(tonic: unt::RawFreq, scale: unt::Scale) -> Self {
+        Self { tonic, scale }
+    }
+
+    /// A major key with the given tonic.
+    #[must_use]
+    pub fn major(tonic: unt::RawFreq) -> Self {
+        Self::new(tonic, unt::Scale::major())
+    }
+
+    /// A (natural) minor key with the given tonic.
+    #[must_use]
+    pub fn minor(tonic: unt::RawFreq) -> Self {
+        Self::new(tonic, unt::Scale::minor())
+    }
+
+    /// The frequency of a given degree in the key.
+    #[must_use]
+    pub fn freq(&self, degree: i32) -> unt::RawFreq {
+        self.tonic * self.scale.interval(degree)
+    }
+
+    /// The frequencies of the given degrees in the key.
+    pub fn raw_freqs<I: IntoIterator<Item = i32>>(&self, degrees: I) -> Vec<unt::RawFreq> {
+        self.scale.raw_freqs(self.tonic, degrees)
+    }
+
+    /// The MIDI notes for the given degrees in the key.
+    ///
+    /// Notes outside of 12-EDO are rounded to the nearest MIDI note.
+    pub fn midi_notes<I: IntoIterator<Item = i32>>(&self, degrees: I) -> Vec<unt::MidiNote> {
+        self.raw_freqs(degrees)
+            .into_iter()
+            .map(unt::RawFreq::round_midi)
+            .collect()
+    }
+
+    /// Builds a diatonic chord by stacking `count` thirds on top of a degree, meaning every other
+    /// note of the scale.
+    #[must_use]
+    pub fn stack(&self, degree: i32, count: u8) -> unt::Chord {
+        let root = self.scale.interval(degree);
+        unt::Chord::new(
+            self.freq(degree),
+            (0..i32::from(count))
+                .map(|i| self.scale.interval(degree + 2 * i) / root)
+                .collect(),
+        )
+    }
+
+    /// The diatonic triad on a given degree.
+    #[must_use]
+    pub fn triad(&self, degree: i32) -> unt::Chord {
+        self.stack(degree, 3)
+    }
+
+    /// The diatonic seventh chord on a given degree.
+    #[must_use]
+    pub fn seventh(&self, degree: i32) -> unt::Chord {
+        self.stack(degree, 4)
+    }
+
+    /// Builds a chord from a roman numeral, such as `"V7"` or `"bVII"`.
+    ///
+    /// The numeral may be preceded by any number of `b` or `#` signs, each of which lowers or
+    /// raises the root by a 12-EDO semitone. Uppercase numerals give major chords, and lowercase
+    /// numerals give minor chords. The numeral may then be followed by:
+    ///
+    /// - `o` or `+`, for diminished or augmented triads,
+    /// - `7`, `o7`, or `ø7`, for seventh chords matching the case of the numeral,
+    /// - `maj7`, for a major seventh or a minor-major seventh,
+    /// - or any other [`Quality`] symbol, which overrides the case of the numeral.
+    ///
+    /// The root is placed in the same octave as the tonic.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the numeral or its quality could not be parsed.
+    pub fn numeral(&self, numeral: &str) -> Result<unt::Chord, ChordError> {
+        let err = || ChordError::Numeral(numeral.to_owned());
+
+        // Read accidentals.
+        let mut bend = 0.0;
+        let mut rest = numeral;
+        loop {
+            if let Some(next) = rest.strip_prefix('b') {
+                bend -= 1.0;
+                rest = next;
+            } else if let Some(next) = rest.strip_prefix('#') {
+                bend += 1.0;
+                rest = next;
+            } else {
+                break;
+            }
+        }
+
+        // Read the numeral itself. We try longer numerals first.
+        let upper = rest.starts_with(|c: char| c.is_ascii_uppercase());
+        let (degree, suffix) = ["VII", "III", "IV", "VI", "II", "V", "I"]
+            .into_iter()
+            .zip([6, 2, 3, 5, 1, 4, 0])
+            .find_map(|(name, degree)| {
+                let len = name.len();
+                let prefix = rest.get(..len)?;
+                (prefix.eq_ignore_ascii_case(name)
+                    && prefix.chars().all(|c| c.is_ascii_uppercase() == upper))
+                .then(|| (degree, &rest[len..]))
+            })
+            .ok_or_else(err)?;
+
+        let quality = match (suffix, upper) {
+            ("", true) => Quality::Major,
+            ("", false) => Quality::Minor,
+            ("7", true) => Quality::Dom7,
+            ("7", false) => Quality::Min7,
+            ("maj7", true) => Quality::Maj7,
+            ("maj7", false) => Quality::MinMaj7,
+            (suffix, _) => suffix.parse()?,
+        };
+
+        Ok(unt::Chord::new_quality(
+            self.freq(degree).bend(bend),
+            quality,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test diatonic chords and roman numerals.
+    #[test]
+    fn numerals() {
+        let key = Key::minor(unt::RawFreq::A3);
+        assert_eq!(
+            key.triad(1).midi_notes(),
+            key.numeral("iio").unwrap().midi_notes()
+        );
+        assert_eq!(
+            key.seventh(4).midi_notes(),
+            key.numeral("v7").unwrap().midi_notes()
+        );
+        assert_eq!(
+            key.numeral("bII").unwrap().midi_notes(),
+            ["A#3", "D4", "F4"].map(|name| name.parse::<unt::MidiNote>().unwrap())
+        );
+        assert!(key.numeral("X").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! Implements the basic types for harmony: [`Scale`], [`Chord`], and [`Key`].
+//!
+//! These types don't do anything on their own. Instead, they produce lists of [`unt::RawFreq`] or
+//! [`unt::MidiNote`], which can then be fed into arpeggios, melodies, or polyphonic synths.
+//!
+//! Although most of the named scales and chords assume 12-EDO, all of these types are ultimately
+//! built out of [`unt::Interval`]s, and thus work just as well with just intonation or other
+//! tunings.
+
+mod chord;
+mod key;
+mod scale;
+
+pub use chord::{Chord, Quality};
+pub use key::Key;
+pub use scale::{Mode, Scale};
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// An error in parsing a [`Chord`] symbol or a roman numeral in a [`Key`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChordError {
+    /// The string was empty.
+    Empty,
+
+    /// An invalid letter name for a note was read.
+    ///
+    /// Note that this is case-sensitive.
+    Letter(char),
+
+    /// The chord quality could not be parsed.
+    Quality(String),
+
+    /// The roman numeral could not be parsed.
+    Numeral(String),
+}
+
+impl Display for ChordError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Empty => write!(f, "the string was empty"),
+            Self::Letter(c) => write!(f, "letter {c} is invalid"),
+            Self::Quality(q) => write!(f, "chord quality {q} is invalid"),
+            Self::Numeral(n) => write!(f, "roman numeral {n} is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for ChordError {}
+
+/// Parses a pitch class, consisting of a letter `A` - `G` and an optional `#` or `b`, from the
+/// start of a string.
+///
+/// Returns the number of semitones from the `C` in the same octave as the letter, and the rest of the
+/// string. The accidental is applied after the letter is placed in its octave, so that `Cb` gives
+/// -1 and `B#` gives 12.
+fn parse_pitch_class(name: &str) -> Result<(i16, &str), ChordError> {
+    let mut chars = name.chars();
+    let letter = chars.next().ok_or(ChordError::Empty)?;
+    let note =
+        i16::from(crate::units::midi::letter_to_note(letter).ok_or(ChordError::Letter(letter))?);
+    let rest = chars.as_str();
+
+    Ok(if let Some(rest) = rest.strip_prefix('#') {
+        (note + 1, rest)
+    } else if let Some(rest) = rest.strip_prefix('b') {
+        (note - 1, rest)
+    } else {
+        (note, rest)
+    })
+}
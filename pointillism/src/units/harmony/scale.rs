@@ -0,0 +1,245 @@
+//! Defines the [`Scale`] type and the diatonic [`Mode`]s.
+
+use crate::prelude::*;
+
+/// One of the seven modes of the diatonic scale.
+///
+/// See [`Scale::new_mode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// The major scale.
+    Ionian,
+    /// The major scale starting from its second degree.
+    Dorian,
+    /// The major scale starting from its third degree.
+    Phrygian,
+    /// The major scale starting from its fourth degree.
+    Lydian,
+    /// The major scale starting from its fifth degree.
+    Mixolydian,
+    /// The natural minor scale.
+    Aeolian,
+    /// The major scale starting from its seventh degree.
+    Locrian,
+}
+
+impl Mode {
+    /// The degree of the major scale at which this mode starts, counting from zero.
+    #[must_use]
+    pub const fn degree(self) -> usize {
+        self as usize
+    }
+}
+
+/// A musical scale, represented as a list of intervals from the root, which repeats every period.
+///
+/// The degrees of a scale are counted **from zero**, so that degree `0` is the root. Degrees
+/// outside of a single period wrap around, so that for instance degree `7` of a major scale is the
+/// root one octave higher, and degree `-1` is the leading tone one octave lower.
+///
+/// ## Example
+///
+/// ```
+/// # use pointillism::prelude::*;
+/// let scale = unt::Scale::new_mode(unt::Mode::Dorian);
+/// let notes = scale.midi_notes(unt::MidiNote::D4, 0..8);
+///
+/// // D dorian is made out of the white keys.
+/// assert_eq!(
+///     notes,
+///     ["D4", "E4", "F4", "G4", "A4", "B4", "C5", "D5"]
+///         .map(|name| name.parse::<unt::MidiNote>().unwrap())
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scale {
+    /// The intervals of the scale relative to the root, in ascending order, starting from the
+    /// unison.
+    intervals: Vec<unt::Interval>,
+    /// The interval after which the scale repeats, usually an octave.
+    pub period: unt::Interval,
+}
+
+impl Scale {
+    /// Initializes a new scale from a list of intervals and a period.
+    ///
+    /// The intervals are sorted, and should all be between the unison (inclusive) and the period
+    /// (exclusive). The unison is added if it's not already present.
+    #[must_use]
+    pub fn new(mut intervals: Vec<unt::Interval>, period: unt::Interval) -> Self {
+        intervals.sort_by(|a, b| a.ratio.total_cmp(&b.ratio));
+        if intervals.first() != Some(&unt::Interval::UNISON) {
+            intervals.insert(0, unt::Interval::UNISON);
+        }
+
+        Self { intervals, period }
+    }
+
+    /// Initializes a new scale in 12-EDO, from the number of semitones of each note above the
+    /// root. The period is an octave.
+    #[must_use]
+    pub fn new_edo(semitones: &[f64]) -> Self {
+        Self::new(
+            semitones
+                .iter()
+                .map(|&note| unt::Interval::note(note))
+                .collect(),
+            unt::Interval::OCTAVE,
+        )
+    }
+
+    /// The major scale.
+    #[must_use]
+    pub fn major() -> Self {
+        Self::new_edo(&[0.0, 2.0, 4.0, 5.0, 7.0, 9.0, 11.0])
+    }
+
+    /// The natural minor scale.
+    #[must_use]
+    pub fn minor() -> Self {
+        Self::new_mode(Mode::Aeolian)
+    }
+
+    /// The harmonic minor scale.
+    #[must_use]
+    pub fn harmonic_minor() -> Self {
+        Self::new_edo(&[0.0, 2.0, 3.0, 5.0, 7.0, 8.0, 11.0])
+    }
+
+    /// The (ascending) melodic minor scale.
+    #[must_use]
+    pub fn melodic_minor() -> Self {
+        Self::new_edo(&[0.0, 2.0, 3.0, 5.0, 7.0, 9.0, 11.0])
+    }
+
+    /// The major pentatonic scale.
+    #[must_use]
+    pub fn major_pentatonic() -> Self {
+        Self::new_edo(&[0.0, 2.0, 4.0, 7.0, 9.0])
+    }
+
+    /// The minor pentatonic scale.
+    #[must_use]
+    pub fn minor_pentatonic() -> Self {
+        Self::new_edo(&[0.0, 3.0, 5.0, 7.0, 10.0])
+    }
+
+    /// The minor blues scale.
+    #[must_use]
+    pub fn blues() -> Self {
+        Self::new_edo(&[0.0, 3.0, 5.0, 6.0, 7.0, 10.0])
+    }
+
+    /// The whole tone scale.
+    #[must_use]
+    pub fn whole_tone() -> Self {
+        Self::new_edo(&[0.0, 2.0, 4.0, 6.0, 8.0, 10.0])
+    }
+
+    /// The chromatic scale.
+    #[must_use]
+    pub fn chromatic() -> Self {
+        Self::new_edo(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0])
+    }
+
+    /// One of the modes of the major scale.
+    #[must_use]
+    pub fn new_mode(mode: Mode) -> Self {
+        Self::major().mode(mode.degree())
+    }
+
+    /// The intervals of the scale relative to the root, in ascending order, starting from the
+    /// unison.
+    #[must_use]
+    pub fn intervals(&self) -> &[unt::Interval] {
+        &self.intervals
+    }
+
+    /// The number of notes in a single period of the scale.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Whether the scale has no notes. This is never the case, as the unison is always present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// The length of the scale as an `i32`.
+    fn len_i32(&self) -> i32 {
+        i32::try_from(self.len()).expect("scale is too long")
+    }
+
+    /// The interval from the root to a given degree of the scale.
+    ///
+    /// Degrees outside of a single period wrap around.
+    #[must_use]
+    pub fn interval(&self, degree: i32) -> unt::Interval {
+        let len = self.len_i32();
+        // The remainder is non-negative.
+        #[allow(clippy::cast_sign_loss)]
+        let index = degree.rem_euclid(len) as usize;
+        self.intervals[index] * self.period.powi(degree.div_euclid(len))
+    }
+
+    /// Returns the `n`-th mode of this scale, meaning the same scale starting from degree `n`.
+    ///
+    /// Note that `n` wraps around, so that the zeroth mode and the mode at the length of the scale
+    /// are the same as the original scale.
+    #[must_use]
+    pub fn mode(&self, n: usize) -> Self {
+        let len = self.len();
+        let n = n % len;
+        let root = self.intervals[n];
+
+        let intervals = (0..len)
+            .map(|i| {
+                let index = n + i;
+                if index < len {
+                    self.intervals[index] / root
+                } else {
+                    self.intervals[index - len] * self.period / root
+                }
+            })
+            .collect();
+
+        Self {
+            intervals,
+            period: self.period,
+        }
+    }
+
+    /// The frequencies of the given degrees of the scale, starting from a root.
+    pub fn raw_freqs<I: IntoIterator<Item = i32>>(
+        &self,
+        root: unt::RawFreq,
+        degrees: I,
+    ) -> Vec<unt::RawFreq> {
+        degrees
+            .into_iter()
+            .map(|degree| root * self.interval(degree))
+            .collect()
+    }
+
+    /// The MIDI notes for the given degrees of the scale, starting from a root.
+    ///
+    /// Notes outside of 12-EDO are rounded to the nearest MIDI note.
+    pub fn midi_notes<I: IntoIterator<Item = i32>>(
+        &self,
+        root: unt::MidiNote,
+        degrees: I,
+    ) -> Vec<unt::MidiNote> {
+        self.raw_freqs(unt::RawFreq::new_midi(root), degrees)
+            .into_iter()
+            .map(unt::RawFreq::round_midi)
+            .collect()
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self::major()
+    }
+}
@@ -13,6 +13,7 @@
 //! | Sample rate | We recognize the [44.1 kHz](https://en.wikipedia.org/wiki/44,100_Hz) sample rate as being the most common for audio, and have thus set it as the type default. However, we recognize both that other standards (notably 48 kHz) exist, and that there's utility in audio with lower or higher sample rates. Thus, we've abstained from making many helper methods and constants with this assumption. |
 
 mod freq;
+mod harmony;
 mod midi;
 mod q_factor;
 mod sample_rate;
@@ -22,6 +23,7 @@ mod vol;
 use std::ops::{Div, Mul};
 
 pub use freq::{Freq, Interval, RawFreq};
+pub use harmony::{Chord, ChordError, Key, Mode, Quality, Scale};
 pub use midi::MidiNote;
 pub use q_factor::QFactor;
 pub use sample_rate::SampleRate;
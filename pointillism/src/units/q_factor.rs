@@ -17,6 +17,10 @@ impl Default for QFactor {
 }
 
 impl QFactor {
+    /// The Q factor of a second order [Butterworth](https://en.wikipedia.org/wiki/Butterworth_filter)
+    /// filter, which has the flattest possible passband. This equals 1 / √2.
+    pub const BUTTERWORTH: Self = Self(std::f64::consts::FRAC_1_SQRT_2);
+
     /// Initializes a Q factor.
     ///
     /// This should be a positive quantity.
@@ -137,6 +137,21 @@ impl Time {
         Self::from_msec(millis, unt::SampleRate::default())
     }
 
+    /// Initializes a [`Time`] from a number of beats at a given BPM, and a sample rate.
+    ///
+    /// This can be used to sync effects to a tempo. For instance, a dotted eighth note is `0.75`
+    /// beats.
+    #[must_use]
+    pub fn from_beats(beats: f64, bpm: f64, sample_rate: unt::SampleRate) -> Self {
+        Self::from_raw(RawTime::new_beat(bpm), sample_rate) * beats
+    }
+
+    /// Initializes a [`Time`] from a number of beats at a given BPM, using the default sample rate.
+    #[must_use]
+    pub fn from_beats_default(beats: f64, bpm: f64) -> Self {
+        Self::from_beats(beats, bpm, unt::SampleRate::default())
+    }
+
     /// Converts [`Time`] into [`RawTime`], using the specified sample rate.
     #[must_use]
     pub fn into_raw(self, sample_rate: unt::SampleRate) -> RawTime {